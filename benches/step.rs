@@ -0,0 +1,60 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use physics::backend::SimBackend;
+use physics::bench::system;
+use physics::gpu::{self, GpuSim};
+use physics::params::Params;
+use physics::sim::{self, Neighbours};
+
+const PARTICLES: [usize; 3] = [250, 1000, 4000];
+const SPECIES: [usize; 2] = [2, 6];
+const SEED: u64 = 1;
+
+fn cpu_step(c: &mut Criterion) {
+    let params = Params::default();
+    let mut group = c.benchmark_group("cpu_step");
+    group.sample_size(10);
+    for neighbours in Neighbours::ALL {
+        for particles in PARTICLES {
+            for species in SPECIES {
+                let (prev, rules) = system(particles, species, &params, SEED);
+                let mut next = prev.clone();
+                group.throughput(Throughput::Elements(particles as u64));
+                let id = format!("{}x{}", particles, species);
+                group.bench_function(BenchmarkId::new(neighbours.name(), &id), |b| {
+                    b.iter(|| sim::step_with(&prev, &mut next, &rules, &params, sim::DT, neighbours))
+                });
+                group.bench_function(BenchmarkId::new(format!("{}-mt", neighbours.name()), &id), |b| {
+                    b.iter(|| sim::par_step_with(&prev, &mut next, &rules, &params, sim::DT, neighbours))
+                });
+            }
+        }
+    }
+    group.finish();
+}
+
+fn gpu_step(c: &mut Criterion) {
+    let Some((device, queue, _)) = pollster::block_on(gpu::headless()) else {
+        eprintln!("no GPU adapter, skipping gpu_step");
+        return;
+    };
+    let params = Params::default();
+    let mut group = c.benchmark_group("gpu_step");
+    for particles in PARTICLES {
+        for species in SPECIES {
+            let (circles, rules) = system(particles, species, &params, SEED);
+            let mut sim = GpuSim::new(device.clone(), queue.clone(), &circles, &rules, &params).unwrap();
+            group.throughput(Throughput::Elements(particles as u64));
+            group.bench_function(BenchmarkId::new("all", format!("{}x{}", particles, species)), |b| {
+                b.iter(|| {
+                    sim.step(1);
+                    sim.wait();
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, cpu_step, gpu_step);
+criterion_main!(benches);
@@ -0,0 +1,128 @@
+use std::env;
+use std::str::FromStr;
+
+use physics::backend::Backend;
+use physics::contact::Contact;
+use physics::friction::Friction;
+use physics::kernel::Kernel;
+use physics::sim::Neighbours;
+
+const USAGE: &str = "usage: physics [--code CODE | --preset FILE] [--seed N] [--backend gpu|cpu|cpu-mt|cpu-simd] [--neighbours all|grid]
+                       [--kernel triangle|cosine|lennard-jones|gaussian|inverse-square|tabulated] [--curves FILE]
+                       [--friction none|linear|quadratic|half-life|cap] [--contact push|spring|constraint]
+       physics --evolve DIR [--generations N] [--population N] [--steps N] [--particles N]
+       physics --novelty DIR [--threshold X] [--generations N] [--population N] [--steps N] [--particles N]
+       physics --rdf FILE [--window N] [--steps N] [--particles N]
+       physics --record FILE [--every N] [--steps N] [--particles N]
+       physics --export FILE [--format xyz|extxyz|csv] [--from TRAJ | --every N --steps N --particles N]
+       physics --replay FILE
+       physics --bench FILE|- [--steps N]";
+
+pub struct Args {
+    pub code: Option<String>,
+    pub preset: Option<String>,
+    pub seed: Option<u64>,
+    pub evolve: Option<String>,
+    pub novelty: Option<String>,
+    pub threshold: f32,
+    pub rdf: Option<String>,
+    pub window: usize,
+    pub record: Option<String>,
+    pub every: u32,
+    pub replay: Option<String>,
+    pub export: Option<String>,
+    pub format: Option<String>,
+    pub from: Option<String>,
+    pub bench: Option<String>,
+    pub backend: Option<Backend>,
+    pub neighbours: Neighbours,
+    pub kernel: Option<Kernel>,
+    pub curves: Option<String>,
+    pub friction: Option<Friction>,
+    pub contact: Option<Contact>,
+    pub generations: usize,
+    pub population: usize,
+    pub steps: usize,
+    pub particles: usize,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            code: None,
+            preset: None,
+            seed: None,
+            evolve: None,
+            novelty: None,
+            threshold: 1.0,
+            rdf: None,
+            window: 500,
+            record: None,
+            every: 5,
+            replay: None,
+            export: None,
+            format: None,
+            from: None,
+            bench: None,
+            backend: None,
+            neighbours: Neighbours::All,
+            kernel: None,
+            curves: None,
+            friction: None,
+            contact: None,
+            generations: 20,
+            population: 16,
+            steps: 2000,
+            particles: 600,
+        }
+    }
+}
+
+impl Args {
+    pub fn parse() -> Result<Self, String> {
+        let mut args = Args::default();
+        let mut it = env::args().skip(1);
+        while let Some(arg) = it.next() {
+            match arg.as_str() {
+                "--code" => args.code = Some(value(&mut it, &arg)?),
+                "--preset" => args.preset = Some(value(&mut it, &arg)?),
+                "--seed" => args.seed = Some(value(&mut it, &arg)?),
+                "--evolve" => args.evolve = Some(value(&mut it, &arg)?),
+                "--novelty" => args.novelty = Some(value(&mut it, &arg)?),
+                "--threshold" => args.threshold = value(&mut it, &arg)?,
+                "--rdf" => args.rdf = Some(value(&mut it, &arg)?),
+                "--window" => args.window = value(&mut it, &arg)?,
+                "--record" => args.record = Some(value(&mut it, &arg)?),
+                "--every" => args.every = value(&mut it, &arg)?,
+                "--replay" => args.replay = Some(value(&mut it, &arg)?),
+                "--export" => args.export = Some(value(&mut it, &arg)?),
+                "--format" => args.format = Some(value(&mut it, &arg)?),
+                "--from" => args.from = Some(value(&mut it, &arg)?),
+                "--bench" => args.bench = Some(value(&mut it, &arg)?),
+                "--backend" => args.backend = Some(named(&mut it, &arg, Backend::parse)?),
+                "--neighbours" => args.neighbours = named(&mut it, &arg, Neighbours::parse)?,
+                "--kernel" => args.kernel = Some(named(&mut it, &arg, Kernel::parse)?),
+                "--curves" => args.curves = Some(value(&mut it, &arg)?),
+                "--friction" => args.friction = Some(named(&mut it, &arg, Friction::parse)?),
+                "--contact" => args.contact = Some(named(&mut it, &arg, Contact::parse)?),
+                "--generations" => args.generations = value(&mut it, &arg)?,
+                "--population" => args.population = value(&mut it, &arg)?,
+                "--steps" => args.steps = value(&mut it, &arg)?,
+                "--particles" => args.particles = value(&mut it, &arg)?,
+                "-h" | "--help" => return Err(USAGE.to_string()),
+                _ => return Err(format!("unknown argument `{}`\n{}", arg, USAGE)),
+            }
+        }
+        Ok(args)
+    }
+}
+
+fn value<T: FromStr>(it: &mut impl Iterator<Item = String>, flag: &str) -> Result<T, String> {
+    let v = it.next().ok_or_else(|| format!("{} needs a value", flag))?;
+    v.parse().map_err(|_| format!("bad value `{}` for {}", v, flag))
+}
+
+fn named<T>(it: &mut impl Iterator<Item = String>, flag: &str, parse: impl Fn(&str) -> Option<T>) -> Result<T, String> {
+    let v: String = value(it, flag)?;
+    parse(&v).ok_or_else(|| format!("bad value `{}` for {}", v, flag))
+}
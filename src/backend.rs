@@ -0,0 +1,79 @@
+use crate::bonds::Bond;
+use crate::circle::Circle;
+use crate::cpu::CpuSim;
+use crate::gpu::{self, GpuSim};
+use crate::params::Params;
+use crate::rules::Rules;
+use crate::sim::Neighbours;
+
+/// A particle simulation that can be stepped without caring where it runs.
+pub trait SimBackend {
+    /// Replaces the particles, failing when the backend can't hold them
+    /// and their births.
+    fn upload(&mut self, circles: &[Circle]) -> Result<(), String>;
+    fn set_rules(&mut self, rules: &Rules);
+    fn set_params(&mut self, params: &Params);
+    fn set_dt(&mut self, dt: f32);
+    /// Restarts the random numbers behind thermal noise from `seed`.
+    fn set_seed(&mut self, seed: u64);
+    fn step(&mut self, steps: usize);
+    /// Copies the particles out, waiting for any steps still running.
+    fn download(&self) -> Vec<Circle>;
+    /// Replaces the bonds, dropping any that don't join two particles there
+    /// are. Uploading particles clears them.
+    fn set_bonds(&mut self, bonds: &[Bond]);
+    /// Copies the bonds out, which follow their particles as others die. The
+    /// GPU lists them in whatever order its passes ran in.
+    fn bonds(&self) -> Vec<Bond>;
+
+    /// The GPU simulation behind this backend, whose particle buffer can be
+    /// drawn without downloading it.
+    fn gpu(&self) -> Option<&GpuSim> {
+        None
+    }
+}
+
+/// Where the simulation step runs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// `compute_main` on the GPU
+    Gpu,
+    /// the CPU port on one thread
+    Cpu,
+    /// the CPU port on every core
+    CpuThreaded,
+    /// the SIMD kernel over structure-of-arrays particles, on every core
+    CpuSimd,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 4] = [Backend::Gpu, Backend::Cpu, Backend::CpuThreaded, Backend::CpuSimd];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Gpu => "gpu",
+            Backend::Cpu => "cpu",
+            Backend::CpuThreaded => "cpu-mt",
+            Backend::CpuSimd => "cpu-simd",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|b| b.name() == name)
+    }
+
+    /// Builds the simulation this backend names. The GPU backend gets a
+    /// headless device of its own, and fails when there is no adapter or
+    /// the particles don't fit on it.
+    pub fn create(self, circles: &[Circle], rules: &Rules, params: &Params, neighbours: Neighbours) -> Result<Box<dyn SimBackend>, String> {
+        match self {
+            Backend::Gpu => {
+                let (device, queue, _) = pollster::block_on(gpu::headless()).ok_or_else(|| format!("no adapter for the {} backend", self.name()))?;
+                Ok(Box::new(GpuSim::new(device, queue, circles, rules, params)?))
+            }
+            _ => CpuSim::for_backend(self, circles, rules, params, neighbours)
+                .map(|cpu| Box::new(cpu) as Box<dyn SimBackend>)
+                .ok_or_else(|| format!("no {} backend", self.name())),
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use std::time::{Duration, Instant};
+
+use rand::{rngs::StdRng, SeedableRng};
+use serde::Serialize;
+
+use crate::backend::{Backend, SimBackend};
+use crate::circle::Circle;
+use crate::gpu::{self, GpuSim};
+use crate::params::Params;
+use crate::rules::{Generator, Rules};
+use crate::sim::{self, Neighbours};
+
+pub const PARTICLES: [usize; 3] = [500, 2000, 8000];
+pub const SPECIES: [usize; 3] = [2, 4, 6];
+// most GPU steps submitted per timed batch; batches start at one step and
+// double so a slow adapter doesn't overrun the budget
+const GPU_BATCH: usize = 32;
+
+pub struct Config {
+    pub particles: Vec<usize>,
+    pub species: Vec<usize>,
+    pub params: Params,
+    pub seed: u64,
+    /// most steps timed per sample
+    pub steps: usize,
+    /// stop a sample early once it has run this long
+    pub budget: Duration,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Sample {
+    /// name of the backend, as given to `--backend`
+    pub backend: &'static str,
+    /// CPU threads stepping the particles
+    pub threads: usize,
+    /// neighbour search on the CPU; the GPU and SIMD kernel always test
+    /// every pair
+    pub neighbours: &'static str,
+    pub particles: usize,
+    pub species: usize,
+    pub steps: usize,
+    pub seconds: f64,
+    pub steps_per_second: f64,
+    /// "wall" clock, or GPU "timestamp" queries
+    pub timer: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Report {
+    /// name of the GPU adapter, if one was found
+    pub adapter: Option<String>,
+    pub samples: Vec<Sample>,
+}
+
+/// The system every backend is timed on: `particles` particles of
+/// `species` species scattered over most of the world, with random rules.
+pub fn system(particles: usize, species: usize, params: &Params, seed: u64) -> (Vec<Circle>, Rules) {
+    let rules = Generator::Random.generate(species, &mut StdRng::seed_from_u64(seed));
+    let circles = Circle::scatter(particles, species as u32, params.world_size * 0.8, seed);
+    (circles, rules)
+}
+
+fn sample(backend: Backend, neighbours: &'static str, particles: usize, species: usize, steps: usize, seconds: f64, timer: &'static str) -> Sample {
+    Sample {
+        backend: backend.name(),
+        threads: match backend {
+            Backend::CpuThreaded | Backend::CpuSimd => rayon::current_num_threads(),
+            _ => 1,
+        },
+        neighbours,
+        particles,
+        species,
+        steps,
+        seconds,
+        steps_per_second: steps as f64 / seconds.max(f64::MIN_POSITIVE),
+        timer,
+    }
+}
+
+pub fn cpu(config: &Config, backend: Backend, particles: usize, species: usize, neighbours: Neighbours) -> Sample {
+    let (circles, rules) = system(particles, species, &config.params, config.seed);
+    let mut cpu = backend.create(&circles, &rules, &config.params, neighbours).expect("CPU backends always start");
+    // one untimed step to warm the caches
+    cpu.step(1);
+
+    let start = Instant::now();
+    let mut steps = 0;
+    while steps < config.steps.max(1) && start.elapsed() < config.budget {
+        cpu.step(1);
+        steps += 1;
+    }
+    let seconds = start.elapsed().as_secs_f64();
+    let neighbours = if backend == Backend::CpuSimd { Neighbours::All } else { neighbours };
+    sample(backend, neighbours.name(), particles, species, steps, seconds, "wall")
+}
+
+/// Times `GpuSim` steps with timestamp queries when the device has them,
+/// and by waiting on the wall clock when it doesn't.
+pub fn gpu(config: &Config, gpu: &mut GpuSim, particles: usize, species: usize) -> Sample {
+    let (circles, rules) = system(particles, species, &config.params, config.seed);
+    gpu.upload(&circles).expect("benchmarks only upload what the GPU steps");
+    gpu.set_rules(&rules);
+    gpu.set_params(&config.params);
+    gpu.set_dt(sim::DT);
+    gpu.step(1);
+    gpu.wait();
+
+    let start = Instant::now();
+    let mut steps = 0;
+    let mut gpu_seconds = Some(0.0);
+    let mut batch = 1;
+    while steps < config.steps.max(1) && start.elapsed() < config.budget {
+        batch = batch.min(config.steps.max(1) - steps);
+        match gpu.time_steps(batch) {
+            Some(s) => gpu_seconds = gpu_seconds.map(|total| total + s),
+            None => {
+                gpu_seconds = None;
+                gpu.step(batch);
+                gpu.wait();
+            }
+        }
+        steps += batch;
+        batch = (batch * 2).min(GPU_BATCH);
+    }
+    match gpu_seconds {
+        Some(seconds) => sample(Backend::Gpu, "all", particles, species, steps, seconds, "timestamp"),
+        None => sample(Backend::Gpu, "all", particles, species, steps, start.elapsed().as_secs_f64(), "wall"),
+    }
+}
+
+/// Times every combination of particle count, species count, neighbour
+/// search and threading on the CPU, plus the SIMD kernel, then every particle and species count on the GPU if
+/// there is one.
+pub fn run(config: &Config) -> Report {
+    let mut samples = Vec::new();
+    for &particles in &config.particles {
+        for &species in &config.species {
+            for neighbours in Neighbours::ALL {
+                for backend in [Backend::Cpu, Backend::CpuThreaded] {
+                    let s = cpu(config, backend, particles, species, neighbours);
+                    eprintln!(
+                        "{:<8} {:>4} {:>6} particles {} species: {:.1} steps/s",
+                        s.backend, s.neighbours, particles, species, s.steps_per_second,
+                    );
+                    samples.push(s);
+                }
+            }
+            let s = cpu(config, Backend::CpuSimd, particles, species, Neighbours::All);
+            eprintln!("{:<8} {:>6} particles {} species: {:.1} steps/s", s.backend, particles, species, s.steps_per_second);
+            samples.push(s);
+        }
+    }
+
+    let mut adapter = None;
+    match pollster::block_on(gpu::headless()) {
+        Some((device, queue, info)) => {
+            adapter = Some(format!("{} ({:?})", info.name, info.backend));
+            let mut sim = GpuSim::new(device, queue, &[], &Rules::new(1), &config.params).expect("births are held to what the GPU steps");
+            for &particles in config.particles.iter().filter(|&&n| n <= gpu::MAX_PARTICLES) {
+                for &species in &config.species {
+                    let s = gpu(config, &mut sim, particles, species);
+                    eprintln!("gpu {:>6} particles {} species: {:.1} steps/s ({})", particles, species, s.steps_per_second, s.timer);
+                    samples.push(s);
+                }
+            }
+        }
+        None => eprintln!("no GPU adapter, skipping GPU benchmarks"),
+    }
+
+    Report { adapter, samples }
+}
@@ -0,0 +1,258 @@
+use rayon::prelude::*;
+
+use crate::friction::cap;
+use crate::grid::Grid;
+use crate::params::Params;
+use crate::sim::direction;
+use crate::species::Species;
+
+/// Most bonds automatic formation lets a particle have, and with the
+/// particle limit the most bonds it makes in all.
+pub const MAX_VALENCY: usize = 6;
+/// Most bond rules a rule set can have, the size of the shader's table.
+pub const MAX_BOND_RULES: usize = 16;
+
+/// A spring's rest length, stiffness and how far past its rest length it
+/// stretches before breaking.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Spring {
+    pub rest: f32,
+    /// force per unit of stretch, scaled like `Params::stiffness`
+    pub stiffness: f32,
+    /// unbreakable if left out
+    #[serde(skip_serializing_if = "unbreakable")]
+    pub strength: f32,
+}
+
+// serde can't write an infinite float to every format
+fn unbreakable(strength: &f32) -> bool {
+    strength.is_infinite()
+}
+
+impl Default for Spring {
+    fn default() -> Self {
+        Self {
+            rest: 0.5,
+            stiffness: 50.0,
+            strength: f32::INFINITY,
+        }
+    }
+}
+
+/// A spring between particles `a` and `b`. `Bond` in shader.wgsl.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Bond {
+    pub a: u32,
+    pub b: u32,
+    pub rest: f32,
+    pub stiffness: f32,
+    pub strength: f32,
+}
+
+impl Bond {
+    pub fn new(a: usize, b: usize, spring: Spring) -> Self {
+        Self {
+            a: a as u32,
+            b: b as u32,
+            rest: spring.rest,
+            stiffness: spring.stiffness,
+            strength: spring.strength,
+        }
+    }
+
+    pub fn spring(&self) -> Spring {
+        Spring { rest: self.rest, stiffness: self.stiffness, strength: self.strength }
+    }
+
+    /// The end that isn't `i`, or None if `i` isn't one.
+    pub fn other(&self, i: usize) -> Option<usize> {
+        match i as u32 {
+            i if i == self.a => Some(self.b as usize),
+            i if i == self.b => Some(self.a as usize),
+            _ => None,
+        }
+    }
+}
+
+/// Bonds a particle of species `a` and one of species `b` within `range`
+/// that aren't bonded yet and have fewer than `valency` bonds each, when
+/// each is the other's nearest such partner.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct BondRule {
+    pub a: usize,
+    pub b: usize,
+    pub range: f32,
+    pub valency: u32,
+    #[serde(flatten)]
+    pub spring: Spring,
+}
+
+impl BondRule {
+    // the species a particle of `species` bonds to under this rule
+    fn partner(&self, species: usize) -> Option<usize> {
+        if species == self.a {
+            Some(self.b)
+        } else if species == self.b {
+            Some(self.a)
+        } else {
+            None
+        }
+    }
+}
+
+/// Bonds joining each particle in `indices` to the next.
+pub fn chain(indices: &[usize], spring: Spring) -> Vec<Bond> {
+    indices.windows(2).map(|w| Bond::new(w[0], w[1], spring)).collect()
+}
+
+/// Bonds joining every pair in `indices` closer than `range`, each resting
+/// at the pair's current distance, so a sheet of particles keeps its shape.
+pub fn mesh(pos: &[[f32; 2]], indices: &[usize], range: f32, spring: Spring) -> Vec<Bond> {
+    let mut bonds = Vec::new();
+    for (k, &i) in indices.iter().enumerate() {
+        for &j in &indices[k + 1..] {
+            let len = distance(pos[i], pos[j]);
+            if len < range {
+                bonds.push(Bond::new(i, j, Spring { rest: len, ..spring }));
+            }
+        }
+    }
+    bonds
+}
+
+/// The bonds of `bonds` joining two different particles of the `count`
+/// there are.
+pub fn valid(bonds: &[Bond], count: usize) -> Vec<Bond> {
+    bonds.iter().filter(|b| b.a != b.b && (b.a as usize) < count && (b.b as usize) < count).copied().collect()
+}
+
+/// Most bonds formation makes with room for `limit` particles.
+pub fn capacity(limit: usize) -> usize {
+    limit * MAX_VALENCY / 2
+}
+
+/// Acceleration each of the particles at `pos` gets from the springs,
+/// summed in bond order. `spring_main` in shader.wgsl.
+pub fn accelerations(bonds: &[Bond], pos: &[[f32; 2]]) -> Vec<[f32; 2]> {
+    let mut a = vec![[0.0; 2]; pos.len()];
+    for bond in bonds {
+        let (i, j) = (bond.a as usize, bond.b as usize);
+        let diff = [pos[i][0] - pos[j][0], pos[i][1] - pos[j][1]];
+        let f = -bond.stiffness * ((diff[0] * diff[0] + diff[1] * diff[1]).sqrt() - bond.rest);
+        for (k, dir) in [(i, direction(diff, i, j)), (j, direction([-diff[0], -diff[1]], j, i))] {
+            a[k] = [a[k][0] + dir[0] * f, a[k][1] + dir[1] * f];
+        }
+    }
+    a
+}
+
+/// Position and velocity after the springs' acceleration `a` is added to a
+/// stepped particle, with the gain the step uses.
+pub fn kick(pos: [f32; 2], vel: [f32; 2], a: [f32; 2], species: &Species, params: &Params, dt: f32) -> ([f32; 2], [f32; 2]) {
+    let gain = params.rmax * dt / species.mass;
+    let dv = [a[0] * gain, a[1] * gain];
+    let vel = cap([vel[0] + dv[0], vel[1] + dv[1]], species.max_speed);
+    ([pos[0] + dv[0] * dt, pos[1] + dv[1] * dt], vel)
+}
+
+/// What `update` works out about each particle, kept between steps so they
+/// don't allocate it again. `Link` in shader.wgsl.
+#[derive(Clone, Debug, Default)]
+pub struct Links {
+    // the bonds each particle has, and the other ends of the first
+    // MAX_VALENCY of them, which is all a particle that can still bond has
+    degree: Vec<u32>,
+    partners: Vec<[u32; MAX_VALENCY]>,
+    // the particle and rule each particle proposes a bond with
+    proposals: Vec<Option<(usize, usize)>>,
+}
+
+/// Breaks the bonds stretched past their strength, then bonds every pair of
+/// particles that propose each other under the first rule that gives the
+/// lower one a partner, in particle order while formation has room, from
+/// the positions and species at the start of the step. As a particle makes
+/// at most one bond a step, which pairs bond doesn't depend on the order
+/// they're looked at in. `hold_main`, `link_main`, `keep_main` and
+/// `bonds_main` in shader.wgsl.
+pub fn update(bonds: &mut Vec<Bond>, links: &mut Links, rules: &[BondRule], pos: &[[f32; 2]], species: &[i32], limit: usize) {
+    bonds.retain(|b| distance(pos[b.a as usize], pos[b.b as usize]) - b.rest <= b.strength);
+    if rules.is_empty() {
+        return;
+    }
+    links.degree.clear();
+    links.degree.resize(pos.len(), 0);
+    links.partners.resize(pos.len(), [0; MAX_VALENCY]);
+    for b in bonds.iter() {
+        for (i, j) in [(b.a as usize, b.b), (b.b as usize, b.a)] {
+            if let Some(slot) = links.partners[i].get_mut(links.degree[i] as usize) {
+                *slot = j;
+            }
+            links.degree[i] += 1;
+        }
+    }
+    // partners are only looked for within the longest range
+    let grid = Grid::new(pos, rules.iter().map(|r| r.range).fold(0.0, f32::max));
+    let (degree, partners) = (&links.degree, &links.partners);
+    links.proposals.resize(pos.len(), None);
+    links.proposals.par_iter_mut().enumerate().for_each(|(i, proposal)| {
+        *proposal = propose(rules, pos, species, degree, partners, &grid, i);
+    });
+    for (i, proposal) in links.proposals.iter().enumerate() {
+        let Some((j, k)) = *proposal else { continue };
+        if i < j && links.proposals[j].is_some_and(|(back, _)| back == i) && bonds.len() < capacity(limit) {
+            bonds.push(Bond::new(i, j, rules[k].spring));
+        }
+    }
+}
+
+// the nearest particle `i` could bond to under the first rule that has one,
+// with that rule, the lower index winning a tie
+fn propose(
+    rules: &[BondRule],
+    pos: &[[f32; 2]],
+    species: &[i32],
+    degree: &[u32],
+    partners: &[[u32; MAX_VALENCY]],
+    grid: &Grid,
+    i: usize,
+) -> Option<(usize, usize)> {
+    let bonded = &partners[i][..(degree[i] as usize).min(MAX_VALENCY)];
+    rules.iter().enumerate().find_map(|(k, rule)| {
+        let partner = rule.partner(species[i] as usize)?;
+        if degree[i] >= rule.valency {
+            return None;
+        }
+        let mut nearest: Option<(f32, usize)> = None;
+        grid.for_each_near(pos[i], |j| {
+            let len = distance(pos[i], pos[j]);
+            if j != i
+                && species[j] as usize == partner
+                && len < rule.range
+                && degree[j] < rule.valency
+                && !bonded.contains(&(j as u32))
+                && nearest.is_none_or(|best| (len, j) < best)
+            {
+                nearest = Some((len, j));
+            }
+        });
+        nearest.map(|(_, j)| (j, k))
+    })
+}
+
+/// Moves bond ends to the particles' new slots, `remap` giving each old
+/// slot's new one, and drops bonds whose ends died.
+pub fn remap(bonds: &mut Vec<Bond>, remap: &[Option<usize>]) {
+    bonds.retain_mut(|b| match (remap[b.a as usize], remap[b.b as usize]) {
+        (Some(a), Some(b_)) => {
+            (b.a, b.b) = (a as u32, b_ as u32);
+            true
+        }
+        _ => false,
+    });
+}
+
+fn distance(a: [f32; 2], b: [f32; 2]) -> f32 {
+    ((a[0] - b[0]) * (a[0] - b[0]) + (a[1] - b[1]) * (a[1] - b[1])).sqrt()
+}
@@ -1,3 +1,5 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
 #[repr(C)]
 #[derive(Clone, Copy, Default, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Circle {
@@ -5,4 +7,25 @@ pub struct Circle {
     pub rad: f32,
     pub pos: [f32; 2],
     pub vel: [f32; 2],
-}
\ No newline at end of file
+}
+
+impl Circle {
+    /// Scatters `count` circles of random species over a square of half-width
+    /// `extent`. The same seed always gives the same circles.
+    pub fn scatter(count: usize, species: u32, extent: f32, seed: u64) -> Vec<Circle> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut circles = Vec::with_capacity(count);
+        for _ in 0..count {
+            circles.push(Circle {
+                pos: [
+                    (rng.gen::<f32>() - 0.5) * 2.0 * extent,
+                    (rng.gen::<f32>() - 0.5) * 2.0 * extent,
+                ],
+                vel: [(rng.gen::<f32>() - 0.5) * 2.0, (rng.gen::<f32>() - 0.5) * 5.0],
+                rad: 0.125,
+                color: (rng.gen::<f32>() * species as f32) as i32,
+            });
+        }
+        circles
+    }
+}
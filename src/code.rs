@@ -0,0 +1,368 @@
+use std::fmt;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+
+use crate::bonds::{BondRule, Spring};
+use crate::contact::Contact;
+use crate::curves::SAMPLES;
+use crate::fields::{Field, Obstacle};
+use crate::friction::Friction;
+use crate::kernel::Kernel;
+use crate::lifecycle::Lifecycle;
+use crate::params::Params;
+use crate::reactions::Reaction;
+use crate::rules::Rules;
+use crate::species::Species;
+
+// version 1 codes have no kernels, so every pair uses the default, and
+// versions before 3 have no species properties, before 4 no friction model,
+// before 5 no temperature, before 6 no contact model, before 7 no
+// reactions, before 8 no lifecycles or population limit, before 9 no bond
+// rules and before 10 no fields or obstacles
+const VERSION: u8 = 10;
+// rule strengths are stored as i16 in steps of 1/RULE_SCALE
+const RULE_SCALE: f32 = 8192.0;
+const RULE_MIN: f32 = i16::MIN as f32 / RULE_SCALE;
+const RULE_MAX: f32 = i16::MAX as f32 / RULE_SCALE;
+
+/// Everything needed to rebuild a system from scratch.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Setup {
+    #[serde(with = "seed")]
+    pub seed: u64,
+    pub rules: Rules,
+    pub params: Params,
+}
+
+// TOML integers are i64, so seeds past i64::MAX are written as strings
+mod seed {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum SeedFile {
+        Number(i64),
+        Text(String),
+    }
+
+    pub fn serialize<S: Serializer>(seed: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        match i64::try_from(*seed) {
+            Ok(n) => SeedFile::Number(n),
+            Err(_) => SeedFile::Text(seed.to_string()),
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        match SeedFile::deserialize(deserializer)? {
+            SeedFile::Number(n) => u64::try_from(n).map_err(|_| serde::de::Error::custom(format!("negative seed {}", n))),
+            SeedFile::Text(text) => text.parse().map_err(|_| serde::de::Error::custom(format!("seed {:?} is not a number", text))),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CodeError {
+    Base64(base64::DecodeError),
+    Version(u8),
+    Species(usize),
+    Rule(usize, usize, f32),
+    Curve(usize, usize, f32),
+    Params(String),
+    Properties(String),
+    Lifecycle(String),
+    Kernel(u8),
+    Friction(u8),
+    Contact(u8),
+    Reactions(String),
+    Bonds(String),
+    Fields(String),
+    Length,
+}
+
+impl fmt::Display for CodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CodeError::Base64(e) => write!(f, "code is not valid base64: {}", e),
+            CodeError::Version(v) => write!(f, "unsupported code version {}", v),
+            CodeError::Species(n) => write!(f, "{} species, codes hold at most {}", n, u8::MAX),
+            CodeError::Rule(i, j, v) => write!(f, "rule {}-{} is {}, codes hold {} to {}", i, j, v, RULE_MIN, RULE_MAX),
+            CodeError::Curve(i, j, v) => write!(f, "curve for pair {}-{} has a force of {}", i, j, v),
+            CodeError::Params(e) => write!(f, "invalid params: {}", e),
+            CodeError::Properties(e) => write!(f, "invalid species properties: {}", e),
+            CodeError::Lifecycle(e) => write!(f, "invalid lifecycle: {}", e),
+            CodeError::Kernel(k) => write!(f, "unknown kernel {}", k),
+            CodeError::Friction(m) => write!(f, "unknown friction model {}", m),
+            CodeError::Contact(c) => write!(f, "unknown contact model {}", c),
+            CodeError::Reactions(e) => write!(f, "invalid reactions: {}", e),
+            CodeError::Bonds(e) => write!(f, "invalid bond rules: {}", e),
+            CodeError::Fields(e) => write!(f, "invalid fields or obstacles: {}", e),
+            CodeError::Length => write!(f, "code is truncated or has trailing data"),
+        }
+    }
+}
+
+impl std::error::Error for CodeError {}
+
+/// Packs a setup into a URL-safe base64 string:
+/// version, species count, params, friction model, temperature, thermostat,
+/// contact model, stiffness and population limit, seed, then the rule matrix and the
+/// kernel of each pair, both row by row, the curve of each tabulated pair,
+/// each species' mass, drag, max speed and radius, and finally the reaction
+/// count and each reaction's species, range and time, each species'
+/// lifecycle, the bond rule count and each rule's species, range, valency
+/// and spring, and the field count and obstacle count, each followed by
+/// every one's kind and four numbers.
+/// Fails for more than 255 species, a rule outside -4 to 4, a curve force
+/// that isn't finite, or params, species properties or lifecycles their
+/// `validate` rejects.
+pub fn encode(setup: &Setup) -> Result<String, CodeError> {
+    let n = setup.rules.species();
+    if n > u8::MAX as usize {
+        return Err(CodeError::Species(n));
+    }
+    setup.params.validate().map_err(CodeError::Params)?;
+    let mut bytes = vec![VERSION, n as u8];
+    for v in params_fields(&setup.params) {
+        bytes.extend_from_slice(&v.to_le_bytes());
+    }
+    bytes.push(setup.params.friction().index() as u8);
+    bytes.extend_from_slice(&setup.params.temperature.to_le_bytes());
+    bytes.push(setup.params.thermostat() as u8);
+    bytes.push(setup.params.contact().index() as u8);
+    bytes.extend_from_slice(&setup.params.stiffness.to_le_bytes());
+    bytes.extend_from_slice(&setup.params.max_particles.to_le_bytes());
+    bytes.extend_from_slice(&setup.seed.to_le_bytes());
+    for i in 0..n {
+        for j in 0..n {
+            let v = setup.rules.get(i, j);
+            if !(RULE_MIN..=RULE_MAX).contains(&v) {
+                return Err(CodeError::Rule(i, j, v));
+            }
+            let q = (v * RULE_SCALE).round() as i16;
+            bytes.extend_from_slice(&q.to_le_bytes());
+        }
+    }
+    for i in 0..n {
+        for j in 0..n {
+            bytes.push(setup.rules.kernel(i, j).index() as u8);
+        }
+    }
+    for i in 0..n {
+        for j in 0..n {
+            if setup.rules.kernel(i, j) == Kernel::Tabulated {
+                for &v in setup.rules.curve(i, j) {
+                    if !v.is_finite() {
+                        return Err(CodeError::Curve(i, j, v));
+                    }
+                    bytes.extend_from_slice(&v.to_le_bytes());
+                }
+            }
+        }
+    }
+    for i in 0..n {
+        let p = setup.rules.properties(i);
+        p.validate().map_err(CodeError::Properties)?;
+        for v in [p.mass, p.drag, p.max_speed, p.radius] {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+    }
+    bytes.push(setup.rules.reactions().len() as u8);
+    for r in setup.rules.reactions() {
+        bytes.extend_from_slice(&[r.from as u8, r.with as u8, r.into as u8]);
+        bytes.extend_from_slice(&r.range.to_le_bytes());
+        bytes.extend_from_slice(&r.time.to_le_bytes());
+    }
+    for i in 0..n {
+        let l = setup.rules.lifecycle(i);
+        l.validate(n).map_err(CodeError::Lifecycle)?;
+        // no partner is stored as the species count
+        bytes.push(l.partner.unwrap_or(n) as u8);
+        for v in [l.lifespan, l.range, l.period] {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+        bytes.extend_from_slice(&l.crowd.to_le_bytes());
+        bytes.extend_from_slice(&l.lonely.to_le_bytes());
+    }
+    bytes.push(setup.rules.bond_rules().len() as u8);
+    for b in setup.rules.bond_rules() {
+        bytes.extend_from_slice(&[b.a as u8, b.b as u8, b.valency as u8]);
+        for v in [b.range, b.spring.rest, b.spring.stiffness, b.spring.strength] {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+    }
+    for parts in [
+        setup.rules.fields().iter().map(Field::to_parts).collect::<Vec<_>>(),
+        setup.rules.obstacles().iter().map(Obstacle::to_parts).collect(),
+    ] {
+        bytes.push(parts.len() as u8);
+        for (kind, v) in parts {
+            bytes.push(kind as u8);
+            for v in v {
+                bytes.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+    }
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+pub fn decode(code: &str) -> Result<Setup, CodeError> {
+    let bytes = URL_SAFE_NO_PAD.decode(code.trim()).map_err(CodeError::Base64)?;
+    let mut r = Reader(&bytes);
+
+    let version = r.take::<1>()?[0];
+    if !(1..=VERSION).contains(&version) {
+        return Err(CodeError::Version(version));
+    }
+    let n = r.take::<1>()?[0] as usize;
+
+    let mut params = Params::default();
+    params.racc = r.f32()?;
+    params.rmax = r.f32()?;
+    params.rmin = r.f32()?;
+    params.mu = r.f32()?;
+    params.ff = r.f32()?;
+    params.world_size = r.f32()?;
+    if version > 3 {
+        let f = r.take::<1>()?[0];
+        params.set_friction(Friction::from_index(f as u32).ok_or(CodeError::Friction(f))?);
+    }
+    if version > 4 {
+        params.temperature = r.f32()?;
+        params.set_thermostat(r.take::<1>()?[0] != 0);
+    }
+    if version > 5 {
+        let c = r.take::<1>()?[0];
+        params.set_contact(Contact::from_index(c as u32).ok_or(CodeError::Contact(c))?);
+        params.stiffness = r.f32()?;
+    }
+    if version > 7 {
+        params.max_particles = u32::from_le_bytes(r.take()?);
+    }
+
+    params.validate().map_err(CodeError::Params)?;
+
+    let seed = u64::from_le_bytes(r.take()?);
+
+    let mut rules = Rules::new(n);
+    for i in 0..n {
+        for j in 0..n {
+            rules.set(i, j, i16::from_le_bytes(r.take()?) as f32 / RULE_SCALE);
+        }
+    }
+    if version > 1 {
+        for i in 0..n {
+            for j in 0..n {
+                let k = r.take::<1>()?[0];
+                rules.set_kernel(i, j, Kernel::from_index(k as u32).ok_or(CodeError::Kernel(k))?);
+            }
+        }
+        for i in 0..n {
+            for j in 0..n {
+                if rules.kernel(i, j) == Kernel::Tabulated {
+                    let curve = (0..SAMPLES).map(|_| r.f32()).collect::<Result<Vec<_>, _>>()?;
+                    if let Some(&v) = curve.iter().find(|v| !v.is_finite()) {
+                        return Err(CodeError::Curve(i, j, v));
+                    }
+                    rules.set_curve(i, j, &curve);
+                }
+            }
+        }
+    }
+    if version > 2 {
+        for i in 0..n {
+            let (mass, drag, max_speed, radius) = (r.f32()?, r.f32()?, r.f32()?, r.f32()?);
+            let properties = Species { mass, drag, max_speed, radius };
+            properties.validate().map_err(CodeError::Properties)?;
+            rules.set_properties(i, properties);
+        }
+    }
+    if version > 6 {
+        let count = r.take::<1>()?[0];
+        let mut reactions = Vec::new();
+        for _ in 0..count {
+            let [from, with, into] = r.take::<3>()?;
+            let (range, time) = (r.f32()?, r.f32()?);
+            reactions.push(Reaction {
+                from: from as usize,
+                with: with as usize,
+                range,
+                time,
+                into: into as usize,
+            });
+        }
+        rules.set_reactions(reactions).map_err(CodeError::Reactions)?;
+    }
+    if version > 7 {
+        for i in 0..n {
+            let partner = r.take::<1>()?[0] as usize;
+            let (lifespan, range, period) = (r.f32()?, r.f32()?, r.f32()?);
+            let (crowd, lonely) = (u32::from_le_bytes(r.take()?), u32::from_le_bytes(r.take()?));
+            let lifecycle = Lifecycle { lifespan, range, partner: (partner < n).then_some(partner), crowd, period, lonely };
+            lifecycle.validate(n).map_err(CodeError::Lifecycle)?;
+            rules.set_lifecycle(i, lifecycle);
+        }
+    }
+    if version > 8 {
+        let count = r.take::<1>()?[0];
+        let mut bond_rules = Vec::new();
+        for _ in 0..count {
+            let [a, b, valency] = r.take::<3>()?;
+            let (range, rest, stiffness, strength) = (r.f32()?, r.f32()?, r.f32()?, r.f32()?);
+            bond_rules.push(BondRule {
+                a: a as usize,
+                b: b as usize,
+                range,
+                valency: valency as u32,
+                spring: Spring { rest, stiffness, strength },
+            });
+        }
+        rules.set_bond_rules(bond_rules).map_err(CodeError::Bonds)?;
+    }
+    if version > 9 {
+        let fields = parts(&mut r, Field::from_parts)?;
+        rules.set_fields(fields).map_err(CodeError::Fields)?;
+        let obstacles = parts(&mut r, Obstacle::from_parts)?;
+        rules.set_obstacles(obstacles).map_err(CodeError::Fields)?;
+    }
+
+    if !r.0.is_empty() {
+        return Err(CodeError::Length);
+    }
+
+    Ok(Setup { rules, params, seed })
+}
+
+// a count, then each entry's kind and four numbers
+fn parts<T>(r: &mut Reader, from_parts: fn(u32, [f32; 4]) -> Option<T>) -> Result<Vec<T>, CodeError> {
+    let count = r.take::<1>()?[0];
+    let mut list = Vec::new();
+    for _ in 0..count {
+        let kind = r.take::<1>()?[0];
+        let v = [r.f32()?, r.f32()?, r.f32()?, r.f32()?];
+        list.push(from_parts(kind as u32, v).ok_or_else(|| CodeError::Fields(format!("unknown kind {}", kind)))?);
+    }
+    Ok(list)
+}
+
+fn params_fields(p: &Params) -> [f32; 6] {
+    [p.racc, p.rmax, p.rmin, p.mu, p.ff, p.world_size]
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], CodeError> {
+        if self.0.len() < N {
+            return Err(CodeError::Length);
+        }
+        let (head, rest) = self.0.split_at(N);
+        self.0 = rest;
+        Ok(head.try_into().unwrap())
+    }
+
+    fn f32(&mut self) -> Result<f32, CodeError> {
+        Ok(f32::from_le_bytes(self.take()?))
+    }
+}
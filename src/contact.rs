@@ -0,0 +1,44 @@
+/// How overlapping particles are kept apart.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Contact {
+    /// the original nudge of `d / 2` for pairs closer than `d = 0.125`,
+    /// whatever their radii
+    #[default]
+    Push,
+    /// a force of `stiffness` times the overlap of the two discs
+    Spring,
+    /// the overlap of the two discs taken out of their positions each step,
+    /// shared so the lighter particle moves further
+    Constraint,
+}
+
+impl Contact {
+    pub const ALL: [Contact; 3] = [Contact::Push, Contact::Spring, Contact::Constraint];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Contact::Push => "push",
+            Contact::Spring => "spring",
+            Contact::Constraint => "constraint",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.name() == name)
+    }
+
+    /// Index the shader switches on, stored in `Params`.
+    pub fn index(self) -> u32 {
+        self as u32
+    }
+
+    pub fn from_index(index: u32) -> Option<Self> {
+        Self::ALL.get(index as usize).copied()
+    }
+}
+
+/// How far two discs `len` apart overlap, zero if they don't touch.
+pub fn overlap(len: f32, rad: f32, other_rad: f32) -> f32 {
+    (rad + other_rad - len).max(0.0)
+}
@@ -0,0 +1,287 @@
+use crate::backend::{Backend, SimBackend};
+use crate::bonds::{self, Bond, Links};
+use crate::circle::Circle;
+use crate::lifecycle::{self, Life};
+use crate::params::Params;
+use crate::reactions::{self, Timer};
+use crate::rules::Rules;
+use crate::sim::{self, Neighbours};
+use crate::soa::{self, Particles};
+use crate::thermal::{self, Frame};
+
+/// Particles stepped by the CPU port, double-buffered so every particle of a
+/// step reads the same previous state.
+pub struct CpuSim {
+    buffers: Buffers,
+    rules: Rules,
+    params: Params,
+    dt: f32,
+    neighbours: Neighbours,
+    threaded: bool,
+    seed: u32,
+    // steps taken with the thermal pass, which picks each step's noise
+    clock: u32,
+    // steps taken with lifecycles, which picks where each step's children go
+    life_clock: u32,
+    timers: Vec<Timer>,
+    lives: Vec<Life>,
+    // particles at the last upload, which births can always grow back to
+    start: usize,
+    bonds: Vec<Bond>,
+    // the positions and species at the start of a step and what bond
+    // formation works out from them, kept so steps don't allocate them
+    pos: Vec<[f32; 2]>,
+    species: Vec<i32>,
+    links: Links,
+}
+
+enum Buffers {
+    /// the scalar port, on one thread or on all of them
+    Circles { prev: Vec<Circle>, next: Vec<Circle> },
+    /// the SIMD kernel, which always tests every pair
+    Soa { prev: Box<Particles>, next: Box<Particles> },
+}
+
+impl CpuSim {
+    pub fn new(circles: &[Circle], rules: &Rules, params: &Params, neighbours: Neighbours, threaded: bool) -> Self {
+        Self {
+            buffers: Buffers::Circles {
+                prev: circles.to_vec(),
+                next: circles.to_vec(),
+            },
+            rules: rules.clone(),
+            params: *params,
+            dt: sim::DT,
+            neighbours,
+            threaded,
+            seed: 0,
+            clock: 0,
+            life_clock: 0,
+            timers: vec![Timer::default(); circles.len()],
+            lives: vec![Life::default(); circles.len()],
+            start: circles.len(),
+            bonds: Vec::new(),
+            pos: Vec::new(),
+            species: Vec::new(),
+            links: Links::default(),
+        }
+    }
+
+    /// Steps with the structure-of-arrays SIMD kernel instead.
+    pub fn simd(circles: &[Circle], rules: &Rules, params: &Params) -> Self {
+        let particles = Particles::from_circles(circles);
+        Self {
+            buffers: Buffers::Soa {
+                prev: Box::new(particles.clone()),
+                next: Box::new(particles),
+            },
+            rules: rules.clone(),
+            params: *params,
+            dt: sim::DT,
+            neighbours: Neighbours::All,
+            threaded: true,
+            seed: 0,
+            clock: 0,
+            life_clock: 0,
+            timers: vec![Timer::default(); circles.len()],
+            lives: vec![Life::default(); circles.len()],
+            start: circles.len(),
+            bonds: Vec::new(),
+            pos: Vec::new(),
+            species: Vec::new(),
+            links: Links::default(),
+        }
+    }
+
+    /// The CPU simulation a backend names, or None for the GPU.
+    pub fn for_backend(backend: Backend, circles: &[Circle], rules: &Rules, params: &Params, neighbours: Neighbours) -> Option<Self> {
+        match backend {
+            Backend::Gpu => None,
+            Backend::Cpu => Some(Self::new(circles, rules, params, neighbours, false)),
+            Backend::CpuThreaded => Some(Self::new(circles, rules, params, neighbours, true)),
+            Backend::CpuSimd => Some(Self::simd(circles, rules, params)),
+        }
+    }
+
+    // scales and kicks every velocity after a step, `thermal_main` in
+    // shader.wgsl
+    fn thermal_step(&mut self) {
+        self.clock = self.clock.wrapping_add(1);
+        let (rules, params, dt) = (&self.rules, &self.params, self.dt);
+        let mass = |species: usize| rules.properties(species).mass;
+        match &mut self.buffers {
+            Buffers::Circles { prev, .. } => {
+                let kinetic = thermal::kinetic(prev.iter().map(|c| (c.vel, mass(c.color as usize))));
+                let frame = Frame { seed: self.seed, clock: self.clock, scale: thermal::rescale(params, kinetic, dt) };
+                for (i, c) in prev.iter_mut().enumerate() {
+                    c.vel = thermal::kick(c.vel, i, &rules.properties(c.color as usize), params, &frame, dt);
+                }
+            }
+            Buffers::Soa { prev, .. } => {
+                let p = &mut **prev;
+                let kinetic = thermal::kinetic((0..p.len()).map(|i| ([p.vx[i], p.vy[i]], mass(p.species[i] as usize))));
+                let frame = Frame { seed: self.seed, clock: self.clock, scale: thermal::rescale(params, kinetic, dt) };
+                for i in 0..p.len() {
+                    let vel = thermal::kick([p.vx[i], p.vy[i]], i, &rules.properties(p.species[i] as usize), params, &frame, dt);
+                    [p.vx[i], p.vy[i]] = vel;
+                }
+            }
+        }
+    }
+
+    // springs, reactions, then bonds breaking and forming and births and
+    // deaths, judged from the particles at the start of the step in `prev`
+    // and applied to the stepped ones in `next`
+    fn transform(&mut self) {
+        let (pos, species) = (&mut self.pos, &mut self.species);
+        pos.clear();
+        species.clear();
+        match &self.buffers {
+            Buffers::Circles { prev, .. } => {
+                pos.extend(prev.iter().map(|c| c.pos));
+                species.extend(prev.iter().map(|c| c.color));
+            }
+            Buffers::Soa { prev, .. } => {
+                pos.extend((0..prev.len()).map(|i| [prev.x[i], prev.y[i]]));
+                species.extend(prev.species.iter().map(|&s| s as i32));
+            }
+        }
+        let springs = bonds::accelerations(&self.bonds, pos);
+        let changes = reactions::react(self.rules.reactions(), pos, species, &mut self.timers, self.dt);
+        let living = self.rules.living();
+        if living {
+            lifecycle::judge(self.rules.all_lifecycles(), pos, species, &mut self.lives, self.dt);
+        }
+        let limit = self.params.limit(self.start);
+        bonds::update(&mut self.bonds, &mut self.links, self.rules.bond_rules(), pos, species, limit);
+        let (rules, params, dt) = (&self.rules, &self.params, self.dt);
+        let remap = match &mut self.buffers {
+            Buffers::Circles { prev, next } => {
+                for (i, c) in next.iter_mut().enumerate().filter(|(i, _)| springs[*i] != [0.0; 2]) {
+                    (c.pos, c.vel) = bonds::kick(c.pos, c.vel, springs[i], &rules.properties(species[i] as usize), params, dt);
+                }
+                for (c, into) in next.iter_mut().zip(changes) {
+                    if let Some(into) = into {
+                        c.color = into as i32;
+                        c.rad = rules.properties(into).radius;
+                    }
+                }
+                if !living {
+                    return;
+                }
+                let remap = lifecycle::compact(next, &mut self.lives, &mut self.timers, limit, self.seed, self.life_clock);
+                prev.resize(next.len(), Circle::default());
+                remap
+            }
+            Buffers::Soa { prev, next } => {
+                for i in (0..next.len()).filter(|&i| springs[i] != [0.0; 2]) {
+                    let properties = rules.properties(species[i] as usize);
+                    let (pos, vel) = bonds::kick([next.x[i], next.y[i]], [next.vx[i], next.vy[i]], springs[i], &properties, params, dt);
+                    ([next.x[i], next.y[i]], [next.vx[i], next.vy[i]]) = (pos, vel);
+                }
+                for (i, into) in changes.into_iter().enumerate() {
+                    if let Some(into) = into {
+                        next.species[i] = into as u32;
+                        next.rad[i] = rules.properties(into).radius;
+                    }
+                }
+                if !living {
+                    return;
+                }
+                let mut circles = next.to_circles();
+                let remap = lifecycle::compact(&mut circles, &mut self.lives, &mut self.timers, limit, self.seed, self.life_clock);
+                **next = Particles::from_circles(&circles);
+                **prev = Particles::from_circles(&circles);
+                remap
+            }
+        };
+        self.life_clock = self.life_clock.wrapping_add(1);
+        bonds::remap(&mut self.bonds, &remap);
+    }
+
+    fn bonded(&self) -> bool {
+        !self.bonds.is_empty() || !self.rules.bond_rules().is_empty()
+    }
+}
+
+impl SimBackend for CpuSim {
+    fn upload(&mut self, circles: &[Circle]) -> Result<(), String> {
+        self.timers = vec![Timer::default(); circles.len()];
+        self.lives = vec![Life::default(); circles.len()];
+        self.start = circles.len();
+        self.bonds.clear();
+        self.buffers = match self.buffers {
+            Buffers::Circles { .. } => Buffers::Circles {
+                prev: circles.to_vec(),
+                next: circles.to_vec(),
+            },
+            Buffers::Soa { .. } => Buffers::Soa {
+                prev: Box::new(Particles::from_circles(circles)),
+                next: Box::new(Particles::from_circles(circles)),
+            },
+        };
+        Ok(())
+    }
+
+    fn set_rules(&mut self, rules: &Rules) {
+        self.rules = rules.clone();
+    }
+
+    fn set_params(&mut self, params: &Params) {
+        self.params = *params;
+    }
+
+    fn set_dt(&mut self, dt: f32) {
+        self.dt = dt;
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.seed = thermal::fold(seed);
+        self.clock = 0;
+        self.life_clock = 0;
+    }
+
+    fn step(&mut self, steps: usize) {
+        for _ in 0..steps {
+            match &mut self.buffers {
+                Buffers::Circles { prev, next } => {
+                    if self.threaded {
+                        sim::par_step_with(prev, next, &self.rules, &self.params, self.dt, self.neighbours);
+                    } else {
+                        sim::step_with(prev, next, &self.rules, &self.params, self.dt, self.neighbours);
+                    }
+                }
+                Buffers::Soa { prev, next } => soa::step(prev, next, &self.rules, &self.params, self.dt),
+            }
+            if !self.rules.reactions().is_empty() || self.rules.living() || self.bonded() {
+                self.transform();
+            }
+            match &mut self.buffers {
+                Buffers::Circles { prev, next } => std::mem::swap(prev, next),
+                Buffers::Soa { prev, next } => std::mem::swap(prev, next),
+            }
+            if self.params.thermal() {
+                self.thermal_step();
+            }
+        }
+    }
+
+    fn download(&self) -> Vec<Circle> {
+        match &self.buffers {
+            Buffers::Circles { prev, .. } => prev.clone(),
+            Buffers::Soa { prev, .. } => prev.to_circles(),
+        }
+    }
+
+    fn set_bonds(&mut self, bonds: &[Bond]) {
+        let count = match &self.buffers {
+            Buffers::Circles { prev, .. } => prev.len(),
+            Buffers::Soa { prev, .. } => prev.len(),
+        };
+        self.bonds = bonds::valid(bonds, count);
+    }
+
+    fn bonds(&self) -> Vec<Bond> {
+        self.bonds.clone()
+    }
+}
@@ -0,0 +1,129 @@
+use std::fmt;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::kernel::Kernel;
+use crate::rules::Rules;
+
+/// Points in each tabulated force curve, spread evenly over `d` from 0 to
+/// the cutoff at 1, and the width of the curves texture.
+pub const SAMPLES: usize = 64;
+
+/// Force at distance `d` from a tabulated curve, interpolating linearly
+/// between the two nearest points. `tabulated` in shader.wgsl.
+pub fn sample(curve: &[f32], d: f32) -> f32 {
+    let x = d.clamp(0.0, 1.0) * (SAMPLES - 1) as f32;
+    let k = (x as usize).min(SAMPLES - 2);
+    let t = x - k as f32;
+    curve[k] + (curve[k + 1] - curve[k]) * t
+}
+
+/// Distance of point `k` of a curve.
+pub fn distance(k: usize) -> f32 {
+    k as f32 / (SAMPLES - 1) as f32
+}
+
+#[derive(Debug)]
+pub enum CurveError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for CurveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CurveError::Io(e) => write!(f, "{}", e),
+            CurveError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for CurveError {}
+
+/// Reads force curves from a CSV file whose header is `d` followed by one
+/// `a-b` column per species pair, and whose rows give a distance on the
+/// kernels' scale, from 0 to 1, and the force there. The rows are
+/// resampled onto the curve points, and every pair read switches to
+/// `Kernel::Tabulated`. Returns the number of pairs read.
+pub fn load_csv(path: impl AsRef<Path>, rules: &mut Rules) -> Result<usize, CurveError> {
+    let text = fs::read_to_string(path).map_err(CurveError::Io)?;
+    let error = |line: usize, message: String| CurveError::Parse { line: line + 1, message };
+
+    let mut lines = text.lines().enumerate().filter(|(_, l)| !l.trim().is_empty());
+    let (_, header) = lines.next().ok_or_else(|| error(0, "empty file".to_string()))?;
+    let mut pairs = Vec::new();
+    for column in header.split(',').skip(1) {
+        let pair = column
+            .trim()
+            .split_once('-')
+            .and_then(|(a, b)| Some((a.parse::<usize>().ok()?, b.parse::<usize>().ok()?)))
+            .filter(|&(a, b)| a < rules.species() && b < rules.species())
+            .ok_or_else(|| error(0, format!("`{}` is not a species pair", column.trim())))?;
+        pairs.push(pair);
+    }
+
+    let mut points: Vec<(f32, Vec<f32>)> = Vec::new();
+    for (line, row) in lines {
+        let values: Vec<f32> = row
+            .split(',')
+            .map(|v| v.trim().parse().ok().filter(|v: &f32| v.is_finite()).ok_or_else(|| error(line, format!("bad number `{}`", v.trim()))))
+            .collect::<Result<_, _>>()?;
+        if values.len() != pairs.len() + 1 {
+            return Err(error(line, format!("expected {} columns, got {}", pairs.len() + 1, values.len())));
+        }
+        if points.last().is_some_and(|(d, _)| values[0] <= *d) {
+            return Err(error(line, "distances must increase".to_string()));
+        }
+        points.push((values[0], values[1..].to_vec()));
+    }
+    if points.is_empty() {
+        return Err(error(0, "no rows".to_string()));
+    }
+
+    for (column, &(a, b)) in pairs.iter().enumerate() {
+        let curve: Vec<f32> = (0..SAMPLES)
+            .map(|k| {
+                let d = distance(k);
+                let after = points.iter().position(|(x, _)| *x >= d).unwrap_or(points.len() - 1);
+                let (x1, y1) = (points[after].0, points[after].1[column]);
+                if after == 0 || d >= x1 {
+                    return y1;
+                }
+                let (x0, y0) = (points[after - 1].0, points[after - 1].1[column]);
+                y0 + (y1 - y0) * (d - x0) / (x1 - x0)
+            })
+            .collect();
+        rules.set_curve(a, b, &curve);
+        rules.set_kernel(a, b, Kernel::Tabulated);
+    }
+    Ok(pairs.len())
+}
+
+/// The curves of every tabulated pair in the layout `load_csv` reads.
+pub fn to_csv(rules: &Rules) -> String {
+    let n = rules.species();
+    let pairs: Vec<(usize, usize)> = (0..n * n)
+        .map(|k| (k / n, k % n))
+        .filter(|&(a, b)| rules.kernel(a, b) == Kernel::Tabulated)
+        .collect();
+
+    let mut out = String::from("d");
+    for (a, b) in &pairs {
+        write!(out, ",{}-{}", a, b).unwrap();
+    }
+    out.push('\n');
+    for k in 0..SAMPLES {
+        write!(out, "{}", distance(k)).unwrap();
+        for &(a, b) in &pairs {
+            write!(out, ",{}", rules.curve(a, b)[k]).unwrap();
+        }
+        out.push('\n');
+    }
+    out
+}
+
+pub fn save_csv(path: impl AsRef<Path>, rules: &Rules) -> io::Result<()> {
+    fs::write(path, to_csv(rules))
+}
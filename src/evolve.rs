@@ -0,0 +1,148 @@
+use std::path::PathBuf;
+use std::thread;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::circle::Circle;
+use crate::code::Setup;
+use crate::metrics::Metrics;
+use crate::params::Params;
+use crate::preset;
+use crate::rules::{Generator, Rules};
+use crate::sim;
+
+pub struct Config {
+    pub species: usize,
+    pub params: Params,
+    pub seed: u64,
+    pub particles: usize,
+    pub steps: usize,
+    pub population: usize,
+    pub generations: usize,
+    pub keep: usize,
+    pub out_dir: PathBuf,
+}
+
+#[derive(Clone)]
+pub struct Candidate {
+    pub rules: Rules,
+    pub metrics: Metrics,
+    pub score: f32,
+}
+
+/// Runs a fresh system for `steps` steps, returning the particles three
+/// quarters of the way through and at the end.
+pub fn simulate(rules: &Rules, params: &Params, seed: u64, particles: usize, steps: usize) -> (Vec<Circle>, Vec<Circle>) {
+    let extent = params.world_size * 0.8;
+    let mut circles = Circle::scatter(particles, rules.species() as u32, extent, seed);
+    sim::run(&mut circles, rules, params, sim::DT, steps - steps / 4);
+    let before = circles.clone();
+    sim::run(&mut circles, rules, params, sim::DT, steps / 4);
+    (before, circles)
+}
+
+/// Measures a fresh run over the last quarter of its steps.
+pub fn evaluate(rules: &Rules, params: &Params, seed: u64, particles: usize, steps: usize) -> Metrics {
+    let (before, after) = simulate(rules, params, seed, particles, steps);
+    Metrics::measure(&before, &after, rules.species())
+}
+
+/// Evolves rule matrices by truncation selection, uniform row crossover and
+/// point mutation, saving the best `keep` candidates to `out_dir` after
+/// every generation.
+pub fn run(config: &Config) -> Vec<Candidate> {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+
+    let mut population: Vec<Rules> = (0..config.population)
+        .map(|i| Generator::ALL[i % Generator::ALL.len()].generate(config.species, &mut rng))
+        .collect();
+    let mut ranked = Vec::new();
+
+    for generation in 0..config.generations {
+        ranked = score_all(&population, config);
+        ranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+        let best = &ranked[0];
+        println!(
+            "generation {}: best {:.4} ({} clusters, persistence {:.2}, motility {:.2}, diversity {:.2})",
+            generation, best.score, best.metrics.clusters, best.metrics.persistence, best.metrics.motility, best.metrics.diversity,
+        );
+        save_best(&ranked, config);
+
+        let elites = (config.population / 4).max(1);
+        population = ranked[..elites].iter().map(|c| c.rules.clone()).collect();
+        while population.len() < config.population {
+            let a = &ranked[rng.gen_range(0..elites)].rules;
+            let b = &ranked[rng.gen_range(0..elites)].rules;
+            let mut child = crossover(a, b, &mut rng);
+            mutate(&mut child, &mut rng);
+            population.push(child);
+        }
+    }
+
+    ranked
+}
+
+fn score_all(population: &[Rules], config: &Config) -> Vec<Candidate> {
+    par_map(population, |rules| {
+        let metrics = evaluate(rules, &config.params, config.seed, config.particles, config.steps);
+        Candidate {
+            rules: rules.clone(),
+            metrics,
+            score: metrics.score(),
+        }
+    })
+}
+
+/// Maps `f` over `items` on one thread per core, keeping the order.
+pub fn par_map<T: Sync, U: Send>(items: &[T], f: impl Fn(&T) -> U + Sync) -> Vec<U> {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk = items.len().div_ceil(threads).max(1);
+    let f = &f;
+    thread::scope(|s| {
+        let handles: Vec<_> = items
+            .chunks(chunk)
+            .map(|chunk| s.spawn(move || chunk.iter().map(f).collect::<Vec<_>>()))
+            .collect();
+        handles.into_iter().flat_map(|h| h.join().unwrap()).collect()
+    })
+}
+
+fn save_best(ranked: &[Candidate], config: &Config) {
+    for (i, candidate) in ranked.iter().take(config.keep).enumerate() {
+        let path = config.out_dir.join(format!("evolved-{}.toml", i));
+        let setup = Setup {
+            seed: config.seed,
+            rules: candidate.rules.clone(),
+            params: config.params,
+        };
+        if let Err(e) = preset::save(&path, &setup) {
+            eprintln!("can't save {}: {}", path.display(), e);
+        }
+    }
+}
+
+pub fn crossover<R: Rng>(a: &Rules, b: &Rules, rng: &mut R) -> Rules {
+    let n = a.species();
+    let mut child = a.clone();
+    for i in 0..n {
+        if rng.gen() {
+            for j in 0..n {
+                child.set(i, j, b.get(i, j));
+            }
+        }
+    }
+    child
+}
+
+pub fn mutate<R: Rng>(rules: &mut Rules, rng: &mut R) {
+    let n = rules.species();
+    for i in 0..n {
+        for j in 0..n {
+            if rng.gen::<f32>() < 0.2 {
+                let v = rules.get(i, j) + rng.gen_range(-0.3..0.3);
+                rules.set(i, j, v.clamp(-1.0, 1.0));
+            }
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::circle::Circle;
+
+// species n is written as the element with atomic number n + 1, which every
+// XYZ reader accepts and most colour differently
+const ELEMENTS: [&str; 10] = ["H", "He", "Li", "Be", "B", "C", "N", "O", "F", "Ne"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// element and position only
+    Xyz,
+    /// extended XYZ with velocities, radius and a per-frame header
+    ExtXyz,
+    /// one row per particle per frame, with a header row
+    Csv,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "xyz" => Some(Format::Xyz),
+            "extxyz" | "exyz" => Some(Format::ExtXyz),
+            "csv" => Some(Format::Csv),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension().and_then(|e| e.to_str()).and_then(Format::parse)
+    }
+}
+
+pub fn element(species: i32) -> &'static str {
+    ELEMENTS[(species.max(0) as usize).min(ELEMENTS.len() - 1)]
+}
+
+/// Writes frames one at a time in the chosen format.
+pub struct Exporter {
+    out: BufWriter<File>,
+    format: Format,
+    world_size: f32,
+    frames: usize,
+}
+
+impl Exporter {
+    pub fn create(path: impl AsRef<Path>, format: Format, world_size: f32) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        if format == Format::Csv {
+            writeln!(out, "frame,step,time,id,species,x,y,vx,vy,radius")?;
+        }
+        Ok(Self {
+            out,
+            format,
+            world_size,
+            frames: 0,
+        })
+    }
+
+    pub fn write(&mut self, step: u64, time: f32, circles: &[Circle]) -> io::Result<()> {
+        let out = &mut self.out;
+        match self.format {
+            Format::Xyz => {
+                writeln!(out, "{}", circles.len())?;
+                writeln!(out, "step {} time {}", step, time)?;
+                for c in circles {
+                    writeln!(out, "{} {} {} 0", element(c.color), c.pos[0], c.pos[1])?;
+                }
+            }
+            Format::ExtXyz => {
+                // the world is open, so the lattice only gives viewers a box
+                // to frame; it's centred on the origin like the particles
+                let l = 2.0 * self.world_size;
+                writeln!(out, "{}", circles.len())?;
+                writeln!(
+                    out,
+                    "Lattice=\"{l} 0 0 0 {l} 0 0 0 1\" Origin=\"{o} {o} -0.5\" Properties=species:S:1:pos:R:3:velo:R:3:radius:R:1:type:I:1 Time={time} Step={step} pbc=\"F F F\"",
+                    l = l,
+                    o = -self.world_size,
+                    time = time,
+                    step = step,
+                )?;
+                for c in circles {
+                    writeln!(
+                        out,
+                        "{} {} {} 0 {} {} 0 {} {}",
+                        element(c.color), c.pos[0], c.pos[1], c.vel[0], c.vel[1], c.rad, c.color,
+                    )?;
+                }
+            }
+            Format::Csv => {
+                for (id, c) in circles.iter().enumerate() {
+                    writeln!(
+                        out,
+                        "{},{},{},{},{},{},{},{},{},{}",
+                        self.frames, step, time, id, c.color, c.pos[0], c.pos[1], c.vel[0], c.vel[1], c.rad,
+                    )?;
+                }
+            }
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
@@ -0,0 +1,151 @@
+/// Most fields a rule set can have, the size of the shader's table.
+pub const MAX_FIELDS: usize = 16;
+/// Most obstacles a rule set can have, the size of the shader's table.
+pub const MAX_OBSTACLES: usize = 32;
+
+/// A force on every particle from outside the system. Fields add up, so a
+/// scenario can combine any number of them. Gravity is given as the
+/// acceleration it causes, the others as the force they exert, which moves
+/// lighter particles more.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Field {
+    /// the same acceleration `g` on every particle, whatever its mass, so a
+    /// force of `g` times the mass
+    Gravity { g: [f32; 2] },
+    /// a pull of `strength` towards `at`, falling off with the square of the
+    /// distance and softened within `radius`; a negative one pushes
+    Attractor { at: [f32; 2], strength: f32, radius: f32 },
+    /// a swirl of `strength` anticlockwise round `at`, strongest at `radius`
+    /// and falling off with the distance past it; a negative one turns
+    /// clockwise
+    Vortex { at: [f32; 2], strength: f32, radius: f32 },
+    /// a drag of `coupling` towards a flow along x whose speed grows by
+    /// `rate` per unit of y
+    Shear { rate: f32, coupling: f32 },
+}
+
+impl Field {
+    /// The index the shader switches on, and the four numbers it reads,
+    /// laid out like `Part` in shader.wgsl.
+    pub fn to_parts(&self) -> (u32, [f32; 4]) {
+        match *self {
+            Field::Gravity { g } => (0, [g[0], g[1], 0.0, 0.0]),
+            Field::Attractor { at, strength, radius } => (1, [at[0], at[1], strength, radius]),
+            Field::Vortex { at, strength, radius } => (2, [at[0], at[1], strength, radius]),
+            Field::Shear { rate, coupling } => (3, [rate, 0.0, coupling, 0.0]),
+        }
+    }
+
+    pub fn from_parts(kind: u32, v: [f32; 4]) -> Option<Self> {
+        match kind {
+            0 => Some(Field::Gravity { g: [v[0], v[1]] }),
+            1 => Some(Field::Attractor { at: [v[0], v[1]], strength: v[2], radius: v[3] }),
+            2 => Some(Field::Vortex { at: [v[0], v[1]], strength: v[2], radius: v[3] }),
+            3 => Some(Field::Shear { rate: v[0], coupling: v[2] }),
+            _ => None,
+        }
+    }
+
+    /// Force on a particle of `mass` at `pos` moving at `vel`. Only gravity
+    /// scales with the mass; the rest push every particle alike, leaving
+    /// the step to divide by it. `field` in shader.wgsl.
+    pub fn force(&self, pos: [f32; 2], vel: [f32; 2], mass: f32) -> [f32; 2] {
+        match *self {
+            Field::Gravity { g } => [g[0] * mass, g[1] * mass],
+            Field::Attractor { at, strength, radius } => {
+                let diff = [at[0] - pos[0], at[1] - pos[1]];
+                let r2 = diff[0] * diff[0] + diff[1] * diff[1] + radius * radius;
+                let k = strength / (r2 * r2.sqrt());
+                [diff[0] * k, diff[1] * k]
+            }
+            Field::Vortex { at, strength, radius } => {
+                let diff = [pos[0] - at[0], pos[1] - at[1]];
+                let k = strength / (diff[0] * diff[0] + diff[1] * diff[1] + radius * radius);
+                [-diff[1] * k, diff[0] * k]
+            }
+            Field::Shear { rate, coupling } => [coupling * (rate * pos[1] - vel[0]), -coupling * vel[1]],
+        }
+    }
+}
+
+/// Sum of the forces of every field, in order.
+pub fn force(fields: &[Field], pos: [f32; 2], vel: [f32; 2], mass: f32) -> [f32; 2] {
+    fields.iter().fold([0.0; 2], |a, field| {
+        let f = field.force(pos, vel, mass);
+        [a[0] + f[0], a[1] + f[1]]
+    })
+}
+
+/// A static shape particles can't enter.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "shape", rename_all = "kebab-case")]
+pub enum Obstacle {
+    Circle { at: [f32; 2], radius: f32 },
+    Rect { min: [f32; 2], max: [f32; 2] },
+}
+
+impl Obstacle {
+    /// Like `Field::to_parts`.
+    pub fn to_parts(&self) -> (u32, [f32; 4]) {
+        match *self {
+            Obstacle::Circle { at, radius } => (0, [at[0], at[1], radius, 0.0]),
+            Obstacle::Rect { min, max } => (1, [min[0], min[1], max[0], max[1]]),
+        }
+    }
+
+    pub fn from_parts(shape: u32, v: [f32; 4]) -> Option<Self> {
+        match shape {
+            0 => Some(Obstacle::Circle { at: [v[0], v[1]], radius: v[2] }),
+            1 => Some(Obstacle::Rect { min: [v[0], v[1]], max: [v[2], v[3]] }),
+            _ => None,
+        }
+    }
+
+    /// Which way and how far a disc of radius `rad` at `pos` has to move to
+    /// stop overlapping the obstacle, or None if it doesn't. A disc whose
+    /// centre is inside a rectangle leaves by the nearest side, the first of
+    /// left, right, bottom and top on a tie. `push` in shader.wgsl.
+    pub fn push(&self, pos: [f32; 2], rad: f32) -> Option<([f32; 2], f32)> {
+        let (normal, depth) = match *self {
+            Obstacle::Circle { at, radius } => {
+                let diff = [pos[0] - at[0], pos[1] - at[1]];
+                let len = (diff[0] * diff[0] + diff[1] * diff[1]).sqrt();
+                let normal = if len > 0.0 { [diff[0] / len, diff[1] / len] } else { [1.0, 0.0] };
+                (normal, radius + rad - len)
+            }
+            Obstacle::Rect { min, max } => {
+                let diff = [pos[0] - pos[0].clamp(min[0], max[0]), pos[1] - pos[1].clamp(min[1], max[1])];
+                let len = (diff[0] * diff[0] + diff[1] * diff[1]).sqrt();
+                if len > 0.0 {
+                    ([diff[0] / len, diff[1] / len], rad - len)
+                } else {
+                    let sides = [
+                        ([-1.0, 0.0], pos[0] - min[0]),
+                        ([1.0, 0.0], max[0] - pos[0]),
+                        ([0.0, -1.0], pos[1] - min[1]),
+                        ([0.0, 1.0], max[1] - pos[1]),
+                    ];
+                    let (normal, gap) = sides.into_iter().fold(sides[0], |best, side| if side.1 < best.1 { side } else { best });
+                    (normal, gap + rad)
+                }
+            }
+        };
+        (depth > 0.0).then_some((normal, depth))
+    }
+}
+
+/// Moves a disc of radius `rad` out of every obstacle in turn and takes the
+/// part of its velocity heading back in away. The end of `compute_main` in
+/// shader.wgsl.
+pub fn collide(obstacles: &[Obstacle], pos: [f32; 2], vel: [f32; 2], rad: f32) -> ([f32; 2], [f32; 2]) {
+    let (mut pos, mut vel) = (pos, vel);
+    for obstacle in obstacles {
+        if let Some((n, depth)) = obstacle.push(pos, rad) {
+            pos = [pos[0] + n[0] * depth, pos[1] + n[1] * depth];
+            let inward = (vel[0] * n[0] + vel[1] * n[1]).min(0.0);
+            vel = [vel[0] - n[0] * inward, vel[1] - n[1] * inward];
+        }
+    }
+    (pos, vel)
+}
@@ -0,0 +1,80 @@
+/// How particles lose speed, every model set by `Params::mu` and scaled by
+/// each species' `drag`. All of them leave a particle at rest where it is.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Friction {
+    /// no friction at all
+    None,
+    /// a force `mu * v` against the velocity
+    Linear,
+    /// a force `mu * |v|^2` against the velocity, the original law
+    #[default]
+    Quadratic,
+    /// the velocity halves `mu` times per unit of time, before the step's
+    /// forces are added
+    HalfLife,
+    /// no force, but the speed is held to `mu`, divided by the drag
+    Cap,
+}
+
+impl Friction {
+    pub const ALL: [Friction; 5] = [
+        Friction::None,
+        Friction::Linear,
+        Friction::Quadratic,
+        Friction::HalfLife,
+        Friction::Cap,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Friction::None => "none",
+            Friction::Linear => "linear",
+            Friction::Quadratic => "quadratic",
+            Friction::HalfLife => "half-life",
+            Friction::Cap => "cap",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.name() == name)
+    }
+
+    /// Index the shader switches on, stored in `Params`.
+    pub fn index(self) -> u32 {
+        self as u32
+    }
+
+    pub fn from_index(index: u32) -> Option<Self> {
+        Self::ALL.get(index as usize).copied()
+    }
+
+    /// Velocity after a step of `dt` from `vel`, `a` being the acceleration
+    /// from every other force and `gain` what turns it into a change of
+    /// velocity. `integrate` in shader.wgsl.
+    pub fn integrate(self, vel: [f32; 2], a: [f32; 2], mu: f32, drag: f32, gain: f32, dt: f32) -> [f32; 2] {
+        let k = mu * drag;
+        let speed = (vel[0] * vel[0] + vel[1] * vel[1]).sqrt();
+        let (a, keep) = match self {
+            Friction::Linear => ([a[0] - k * vel[0], a[1] - k * vel[1]], 1.0),
+            Friction::Quadratic => ([a[0] - k * speed * vel[0], a[1] - k * speed * vel[1]], 1.0),
+            Friction::HalfLife => (a, 0.5f32.powf(k * dt)),
+            Friction::None | Friction::Cap => (a, 1.0),
+        };
+        let vel = [vel[0] * keep + a[0] * gain, vel[1] * keep + a[1] * gain];
+        match self {
+            Friction::Cap => cap(vel, mu / drag),
+            _ => vel,
+        }
+    }
+}
+
+/// `vel` scaled down to `max_speed` if it's any faster.
+pub fn cap(vel: [f32; 2], max_speed: f32) -> [f32; 2] {
+    let speed = (vel[0] * vel[0] + vel[1] * vel[1]).sqrt();
+    if speed > max_speed {
+        [vel[0] * max_speed / speed, vel[1] * max_speed / speed]
+    } else {
+        vel
+    }
+}
@@ -0,0 +1,1134 @@
+use std::sync::Arc;
+
+use wgpu::util::DeviceExt;
+
+use crate::backend::SimBackend;
+use crate::bonds::{self, Bond, MAX_BOND_RULES};
+use crate::circle::Circle;
+use crate::curves::SAMPLES;
+use crate::fields::{MAX_FIELDS, MAX_OBSTACLES};
+use crate::lifecycle::{Life, Lifecycle};
+use crate::params::Params;
+use crate::reactions::{Timer, MAX_REACTIONS};
+use crate::rules::Rules;
+use crate::species::Species;
+use crate::thermal;
+use crate::NUM_COLORS;
+
+/// Largest particle count one dispatch can step: the shader runs one
+/// single-invocation workgroup per particle.
+pub const MAX_PARTICLES: usize = 65535;
+
+/// Particle slots each workgroup of the lifecycle passes covers, `SCAN` in
+/// shader.wgsl.
+const SCAN: u32 = 256;
+/// Bond slots each workgroup of `hold_main` and `keep_main` covers.
+const BOND_GROUP: u32 = 64;
+
+/// Device and queue without a window, for headless runs, benchmarks and
+/// tests. Falls back to a software adapter when there's no hardware one, and
+/// enables timestamp queries when the adapter has them.
+pub async fn headless() -> Option<(Arc<wgpu::Device>, Arc<wgpu::Queue>, wgpu::AdapterInfo)> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::all(),
+        dx12_shader_compiler: Default::default(),
+    });
+    let mut adapter = None;
+    for force_fallback_adapter in [false, true] {
+        adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                compatible_surface: None,
+                force_fallback_adapter,
+            })
+            .await;
+        if adapter.is_some() {
+            break;
+        }
+    }
+    let adapter = adapter?;
+    let features = wgpu::Features::VERTEX_WRITABLE_STORAGE | (adapter.features() & wgpu::Features::TIMESTAMP_QUERY);
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                features,
+                limits: wgpu::Limits::default(),
+                label: None,
+            },
+            None,
+        )
+        .await
+        .ok()?;
+    Some((Arc::new(device), Arc::new(queue), adapter.get_info()))
+}
+
+/// The particle buffer and everything `compute_main` needs to step it.
+pub struct GpuSim {
+    device: Arc<wgpu::Device>,
+    queue: Arc<wgpu::Queue>,
+    pub circ_buffer: wgpu::Buffer,
+    pub circ_bind_group_layout: wgpu::BindGroupLayout,
+    pub circ_bind_group: wgpu::BindGroup,
+    // copy of the particles taken before each step, which `compute_main`
+    // reads so no particle sees another's update from the same step
+    prev_buffer: wgpu::Buffer,
+    prev_bind_group_layout: wgpu::BindGroupLayout,
+    prev_bind_group: wgpu::BindGroup,
+    dt_buffer: wgpu::Buffer,
+    params_buffer: wgpu::Buffer,
+    constraints_tex: wgpu::Texture,
+    curves_tex: wgpu::Texture,
+    species_buffer: wgpu::Buffer,
+    reactions_buffer: wgpu::Buffer,
+    lifecycles_buffer: wgpu::Buffer,
+    bond_rules_buffer: wgpu::Buffer,
+    fields_buffer: wgpu::Buffer,
+    obstacles_buffer: wgpu::Buffer,
+    uniform_bind_group: wgpu::BindGroup,
+    frame_buffer: wgpu::Buffer,
+    // a reactions::Timer and a lifecycle::Life per particle slot
+    timers_buffer: wgpu::Buffer,
+    lives_buffer: wgpu::Buffer,
+    // the bond count and list, a copy taken before each step that the bond
+    // passes read while they fill the list again, and a scratch link per
+    // particle slot that the bond and lifecycle passes leave for each other
+    bonds_buffer: wgpu::Buffer,
+    held_buffer: wgpu::Buffer,
+    links_buffer: wgpu::Buffer,
+    // scratch for the lifecycle passes: each particle set aside, and the
+    // survivors and births per block of slots
+    spares_buffer: wgpu::Buffer,
+    scan_buffer: wgpu::Buffer,
+    frame_bind_group_layout: wgpu::BindGroupLayout,
+    frame_bind_group: wgpu::BindGroup,
+    // the bonds for the viewer's vs_bond
+    pub bond_bind_group_layout: wgpu::BindGroupLayout,
+    pub bond_bind_group: wgpu::BindGroup,
+    pipeline: wgpu::ComputePipeline,
+    spring_pipeline: wgpu::ComputePipeline,
+    hold_pipeline: wgpu::ComputePipeline,
+    link_pipeline: wgpu::ComputePipeline,
+    keep_pipeline: wgpu::ComputePipeline,
+    bonds_pipeline: wgpu::ComputePipeline,
+    react_pipeline: wgpu::ComputePipeline,
+    life_pipeline: wgpu::ComputePipeline,
+    scan_pipeline: wgpu::ComputePipeline,
+    blocks_pipeline: wgpu::ComputePipeline,
+    scatter_pipeline: wgpu::ComputePipeline,
+    settle_pipeline: wgpu::ComputePipeline,
+    reduce_pipeline: wgpu::ComputePipeline,
+    thermal_pipeline: wgpu::ComputePipeline,
+    // whether there are bond rules, and whether steps run the bond passes
+    // after compute_main, which stays on while bonds formed under old rules
+    // may be left
+    forming: bool,
+    bonded: bool,
+    // whether steps run the reaction pass after the bond passes
+    reacting: bool,
+    // whether steps run the lifecycle passes after the reactions
+    living: bool,
+    // particles at the last upload and Params::max_particles, which give
+    // the limit births can grow the system to
+    start: usize,
+    max_particles: u32,
+    // whether steps run the thermal passes after compute_main
+    thermal: bool,
+    timestamps: Option<Timestamps>,
+}
+
+/// What the passes of a step share: the noise seed, the step they draw for,
+/// the kinetic temperature `thermal_reduce` sums, the live particles, how
+/// many births can make and the lifecycle step births draw for.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Frame {
+    seed: u32,
+    clock: u32,
+    kinetic: f32,
+    count: u32,
+    limit: u32,
+    life_clock: u32,
+}
+
+/// A `reactions::Reaction` as the shader reads it.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuReaction {
+    from: i32,
+    with: i32,
+    into: i32,
+    range: f32,
+    time: f32,
+}
+
+/// A `bonds::BondRule` as the shader reads it.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuBondRule {
+    a: i32,
+    b: i32,
+    valency: u32,
+    range: f32,
+    rest: f32,
+    stiffness: f32,
+    strength: f32,
+}
+
+/// A `fields::Field` or `fields::Obstacle` as the shader reads it, from
+/// their `to_parts`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuPart {
+    kind: u32,
+    v: [f32; 4],
+}
+
+/// A `lifecycle::Lifecycle` as the shader reads it.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuLifecycle {
+    lifespan: f32,
+    range: f32,
+    partner: i32,
+    crowd: u32,
+    period: f32,
+    lonely: u32,
+}
+
+struct Timestamps {
+    queries: wgpu::QuerySet,
+    resolve: wgpu::Buffer,
+    read: wgpu::Buffer,
+    period: f32,
+}
+
+impl GpuSim {
+    /// Fails when the particles and their births need more than
+    /// `MAX_PARTICLES` slots.
+    pub fn new(device: Arc<wgpu::Device>, queue: Arc<wgpu::Queue>, circles: &[Circle], rules: &Rules, params: &Params) -> Result<Self, String> {
+        fits(params.limit(circles.len()))?;
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Compute shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
+        });
+
+        let dt_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("dt buffer"),
+            contents: bytemuck::cast_slice(&[crate::sim::DT]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Params buffer"),
+            contents: bytemuck::cast_slice(&[*params]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let constraints_tex = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Constraints buffer"),
+            size: wgpu::Extent3d {
+                width: NUM_COLORS,
+                height: NUM_COLORS,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba32Float,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        write_constraints(&queue, &constraints_tex, rules);
+        let constraints_tex_view = constraints_tex.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let curves_tex = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Curves texture"),
+            size: wgpu::Extent3d {
+                width: SAMPLES as u32,
+                height: NUM_COLORS * NUM_COLORS,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R32Float,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        write_curves(&queue, &curves_tex, rules);
+        let curves_tex_view = curves_tex.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let species_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Species buffer"),
+            contents: bytemuck::cast_slice(&species(rules)),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+        let reactions_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Reactions buffer"),
+            contents: &reactions(rules),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+        let lifecycles_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Lifecycles buffer"),
+            contents: bytemuck::cast_slice(&lifecycles(rules)),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+        let bond_rules_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Bond rules buffer"),
+            contents: &bond_rules(rules),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+        let fields_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Fields buffer"),
+            contents: &fields(rules),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+        let obstacles_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Obstacles buffer"),
+            contents: &obstacles(rules),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let uniform = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let texture = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let storage = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        // bindings 2 and 3 of group 0 are only used by the fragment shader
+        let uniform_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                uniform(0),
+                texture(1),
+                uniform(4),
+                texture(5),
+                storage(6, true),
+                storage(7, true),
+                storage(8, true),
+                storage(9, true),
+                storage(10, true),
+                storage(11, true),
+            ],
+            label: Some("compute uniform bind group layout"),
+        });
+        let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("compute uniform bind group"),
+            layout: &uniform_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: dt_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&constraints_tex_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::TextureView(&curves_tex_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: species_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 7,
+                    resource: reactions_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 8,
+                    resource: lifecycles_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 9,
+                    resource: bond_rules_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 10,
+                    resource: fields_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 11,
+                    resource: obstacles_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        // room for every particle births can make, the slots past the
+        // particles zeroed so they draw nothing
+        let limit = params.limit(circles.len());
+        let mut slots = circles.to_vec();
+        slots.resize(limit, Circle::default());
+        let circ_bind_group_layout = circle_layout(&device);
+        let (circ_buffer, circ_bind_group) = circle_buffer(&device, &circ_bind_group_layout, &slots);
+        let prev_bind_group_layout = prev_layout(&device);
+        let (prev_buffer, prev_bind_group) = circle_buffer(&device, &prev_bind_group_layout, &slots);
+
+        let frame_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Frame buffer"),
+            contents: bytemuck::bytes_of(&Frame {
+                seed: 0,
+                clock: 0,
+                kinetic: 0.0,
+                count: circles.len() as u32,
+                limit: limit as u32,
+                life_clock: 0,
+            }),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+        });
+        let frame_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &(0..8).map(|binding| storage(binding, binding == 7)).collect::<Vec<_>>(),
+            label: Some("frame bind group layout"),
+        });
+        let timers_buffer = state_buffer::<Timer>(&device, "Timers buffer", limit);
+        let lives_buffer = state_buffer::<Life>(&device, "Lives buffer", limit);
+        let bonds_buffer = bonds_buffer(&device, &[], bonds::capacity(limit));
+        let held_buffer = held_buffer(&device, &bonds_buffer);
+        let links_buffer = state_buffer::<[u32; 11]>(&device, "Links buffer", limit);
+        let spares_buffer = state_buffer::<(Circle, Life, Timer)>(&device, "Spares buffer", limit);
+        let scan_buffer = state_buffer::<[u32; 2]>(&device, "Scan buffer", 1 + blocks(limit));
+        let frame_bind_group = frame_bind_group(
+            &device,
+            &frame_bind_group_layout,
+            [&frame_buffer, &timers_buffer, &lives_buffer, &bonds_buffer, &links_buffer, &spares_buffer, &scan_buffer, &held_buffer],
+        );
+        let bond_bind_group_layout = bond_layout(&device);
+        let bond_bind_group = bond_bind_group(&device, &bond_bind_group_layout, &bonds_buffer);
+
+        // one layout for every pass, so each binds the same groups
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Compute pipeline layout"),
+            bind_group_layouts: &[&uniform_bind_group_layout, &circ_bind_group_layout, &prev_bind_group_layout, &frame_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let compute_pipeline = |entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point,
+            })
+        };
+        let pipeline = compute_pipeline("compute_main");
+        let spring_pipeline = compute_pipeline("spring_main");
+        let hold_pipeline = compute_pipeline("hold_main");
+        let link_pipeline = compute_pipeline("link_main");
+        let keep_pipeline = compute_pipeline("keep_main");
+        let bonds_pipeline = compute_pipeline("bonds_main");
+        let react_pipeline = compute_pipeline("react_main");
+        let life_pipeline = compute_pipeline("life_main");
+        let scan_pipeline = compute_pipeline("scan_main");
+        let blocks_pipeline = compute_pipeline("scan_blocks");
+        let scatter_pipeline = compute_pipeline("scatter_main");
+        let settle_pipeline = compute_pipeline("settle_main");
+        let reduce_pipeline = compute_pipeline("thermal_reduce");
+        let thermal_pipeline = compute_pipeline("thermal_main");
+
+        let timestamps = device.features().contains(wgpu::Features::TIMESTAMP_QUERY).then(|| Timestamps {
+            queries: device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("Step timestamps"),
+                ty: wgpu::QueryType::Timestamp,
+                count: 2,
+            }),
+            resolve: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Timestamp resolve buffer"),
+                size: 16,
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            read: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Timestamp read buffer"),
+                size: 16,
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            period: queue.get_timestamp_period(),
+        });
+
+        Ok(Self {
+            device,
+            queue,
+            circ_buffer,
+            circ_bind_group_layout,
+            circ_bind_group,
+            prev_buffer,
+            prev_bind_group_layout,
+            prev_bind_group,
+            dt_buffer,
+            params_buffer,
+            constraints_tex,
+            curves_tex,
+            species_buffer,
+            reactions_buffer,
+            lifecycles_buffer,
+            bond_rules_buffer,
+            fields_buffer,
+            obstacles_buffer,
+            uniform_bind_group,
+            frame_buffer,
+            timers_buffer,
+            lives_buffer,
+            bonds_buffer,
+            held_buffer,
+            links_buffer,
+            spares_buffer,
+            scan_buffer,
+            frame_bind_group_layout,
+            frame_bind_group,
+            bond_bind_group_layout,
+            bond_bind_group,
+            pipeline,
+            spring_pipeline,
+            hold_pipeline,
+            link_pipeline,
+            keep_pipeline,
+            bonds_pipeline,
+            react_pipeline,
+            life_pipeline,
+            scan_pipeline,
+            blocks_pipeline,
+            scatter_pipeline,
+            settle_pipeline,
+            reduce_pipeline,
+            thermal_pipeline,
+            forming: !rules.bond_rules().is_empty(),
+            bonded: !rules.bond_rules().is_empty(),
+            reacting: !rules.reactions().is_empty(),
+            living: rules.living(),
+            start: circles.len(),
+            max_particles: params.max_particles,
+            thermal: params.thermal(),
+            timestamps,
+        })
+    }
+
+    /// Particle slots in the buffer, live or not. Drawing all of them shows
+    /// just the live ones, as dead slots have a radius of zero.
+    pub fn capacity(&self) -> u32 {
+        (self.circ_buffer.size() / std::mem::size_of::<Circle>() as u64) as u32
+    }
+
+    /// Bond slots in the bonds buffer, set or not. Drawing all of them shows
+    /// just the bonds there are, as `vs_bond` puts the rest off screen.
+    pub fn bond_capacity(&self) -> u32 {
+        ((self.bonds_buffer.size() - 4) / std::mem::size_of::<Bond>() as u64) as u32
+    }
+
+    /// Records `steps` steps, each a copy of the particles into the previous
+    /// state buffer followed by a dispatch of `compute_main`, then of the
+    /// spring, hold and link passes when there are bonds or bond rules, of
+    /// `react_main` when there are reactions, of the lifecycle passes when
+    /// particles can breed or die, with `keep_main` and `bonds_main` between
+    /// them and `settle_main` as the bonds need the particles' new slots, and
+    /// of the thermal passes when there's noise or a thermostat. Steps with
+    /// bonds start by copying them aside and zeroing the links. Every slot is
+    /// dispatched, as only the GPU knows how many are live.
+    pub fn encode(&self, encoder: &mut wgpu::CommandEncoder, steps: usize) {
+        let (size, slots) = (self.circ_buffer.size(), self.capacity());
+        let blocks = blocks(slots as usize) as u32;
+        let bond_blocks = self.bond_capacity().div_ceil(BOND_GROUP);
+        for _ in 0..steps {
+            encoder.copy_buffer_to_buffer(&self.circ_buffer, 0, &self.prev_buffer, 0, size);
+            if self.bonded {
+                encoder.copy_buffer_to_buffer(&self.bonds_buffer, 0, &self.held_buffer, 0, self.bonds_buffer.size());
+                encoder.clear_buffer(&self.links_buffer, 0, None);
+            }
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Compute pass"),
+            });
+            compute_pass.set_pipeline(&self.pipeline);
+            compute_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+            compute_pass.set_bind_group(1, &self.circ_bind_group, &[]);
+            compute_pass.set_bind_group(2, &self.prev_bind_group, &[]);
+            compute_pass.set_bind_group(3, &self.frame_bind_group, &[]);
+            compute_pass.dispatch_workgroups(slots, 1, 1);
+            if self.bonded {
+                compute_pass.set_pipeline(&self.spring_pipeline);
+                compute_pass.dispatch_workgroups(slots, 1, 1);
+                compute_pass.set_pipeline(&self.hold_pipeline);
+                compute_pass.dispatch_workgroups(bond_blocks, 1, 1);
+                compute_pass.set_pipeline(&self.link_pipeline);
+                compute_pass.dispatch_workgroups(slots, 1, 1);
+            }
+            if self.reacting {
+                compute_pass.set_pipeline(&self.react_pipeline);
+                compute_pass.dispatch_workgroups(slots, 1, 1);
+            }
+            if self.living {
+                compute_pass.set_pipeline(&self.life_pipeline);
+                compute_pass.dispatch_workgroups(slots, 1, 1);
+                compute_pass.set_pipeline(&self.scan_pipeline);
+                compute_pass.dispatch_workgroups(blocks, 1, 1);
+                compute_pass.set_pipeline(&self.blocks_pipeline);
+                compute_pass.dispatch_workgroups(1, 1, 1);
+                compute_pass.set_pipeline(&self.scatter_pipeline);
+                compute_pass.dispatch_workgroups(blocks, 1, 1);
+            }
+            if self.bonded {
+                compute_pass.set_pipeline(&self.keep_pipeline);
+                compute_pass.dispatch_workgroups(bond_blocks, 1, 1);
+                compute_pass.set_pipeline(&self.bonds_pipeline);
+                compute_pass.dispatch_workgroups(slots, 1, 1);
+            }
+            if self.living {
+                compute_pass.set_pipeline(&self.settle_pipeline);
+                compute_pass.dispatch_workgroups(1, 1, 1);
+            }
+            if self.thermal {
+                compute_pass.set_pipeline(&self.reduce_pipeline);
+                compute_pass.dispatch_workgroups(1, 1, 1);
+                compute_pass.set_pipeline(&self.thermal_pipeline);
+                compute_pass.dispatch_workgroups(slots, 1, 1);
+            }
+        }
+    }
+
+    /// Runs `steps` steps and waits for them, returning the GPU time they
+    /// took in seconds, or None if the device has no timestamp queries.
+    pub fn time_steps(&self, steps: usize) -> Option<f64> {
+        let timestamps = self.timestamps.as_ref()?;
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Timed compute encoder"),
+        });
+        encoder.write_timestamp(&timestamps.queries, 0);
+        self.encode(&mut encoder, steps);
+        encoder.write_timestamp(&timestamps.queries, 1);
+        encoder.resolve_query_set(&timestamps.queries, 0..2, &timestamps.resolve, 0);
+        encoder.copy_buffer_to_buffer(&timestamps.resolve, 0, &timestamps.read, 0, 16);
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = timestamps.read.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| {});
+        self.device.poll(wgpu::Maintain::Wait);
+        let ticks: [u64; 2] = bytemuck::pod_read_unaligned(&slice.get_mapped_range());
+        timestamps.read.unmap();
+        Some(ticks[1].wrapping_sub(ticks[0]) as f64 * timestamps.period as f64 * 1e-9)
+    }
+
+    /// Blocks until all submitted work is done.
+    pub fn wait(&self) {
+        self.device.poll(wgpu::Maintain::Wait);
+    }
+
+    // new particle buffers with `slots` slots, keeping the particles and
+    // their timers, lives and bonds
+    fn reallocate(&mut self, slots: usize) {
+        let empty = vec![Circle::default(); slots];
+        let (circ_buffer, circ_bind_group) = circle_buffer(&self.device, &self.circ_bind_group_layout, &empty);
+        let (prev_buffer, prev_bind_group) = circle_buffer(&self.device, &self.prev_bind_group_layout, &empty);
+        let timers_buffer = state_buffer::<Timer>(&self.device, "Timers buffer", slots);
+        let lives_buffer = state_buffer::<Life>(&self.device, "Lives buffer", slots);
+        let links_buffer = state_buffer::<[u32; 11]>(&self.device, "Links buffer", slots);
+        let spares_buffer = state_buffer::<(Circle, Life, Timer)>(&self.device, "Spares buffer", slots);
+        let scan_buffer = state_buffer::<[u32; 2]>(&self.device, "Scan buffer", 1 + blocks(slots));
+        let bonds_buffer = bonds_buffer(&self.device, &[], bonds::capacity(slots).max(self.bond_capacity() as usize));
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Reallocation encoder"),
+        });
+        for (old, new) in [
+            (&self.circ_buffer, &circ_buffer),
+            (&self.timers_buffer, &timers_buffer),
+            (&self.lives_buffer, &lives_buffer),
+            (&self.bonds_buffer, &bonds_buffer),
+        ] {
+            encoder.copy_buffer_to_buffer(old, 0, new, 0, old.size().min(new.size()));
+        }
+        self.queue.submit(std::iter::once(encoder.finish()));
+        (self.circ_buffer, self.circ_bind_group) = (circ_buffer, circ_bind_group);
+        (self.prev_buffer, self.prev_bind_group) = (prev_buffer, prev_bind_group);
+        (self.timers_buffer, self.lives_buffer) = (timers_buffer, lives_buffer);
+        (self.bonds_buffer, self.links_buffer) = (bonds_buffer, links_buffer);
+        (self.spares_buffer, self.scan_buffer) = (spares_buffer, scan_buffer);
+        self.rebind();
+    }
+
+    // the bind groups over the frame and bonds buffers, after one of them
+    // is replaced, with a new copy of the bonds to match
+    fn rebind(&mut self) {
+        self.held_buffer = held_buffer(&self.device, &self.bonds_buffer);
+        self.frame_bind_group = frame_bind_group(
+            &self.device,
+            &self.frame_bind_group_layout,
+            [
+                &self.frame_buffer,
+                &self.timers_buffer,
+                &self.lives_buffer,
+                &self.bonds_buffer,
+                &self.links_buffer,
+                &self.spares_buffer,
+                &self.scan_buffer,
+                &self.held_buffer,
+            ],
+        );
+        self.bond_bind_group = bond_bind_group(&self.device, &self.bond_bind_group_layout, &self.bonds_buffer);
+    }
+
+    // waits for the steps submitted so far and copies `size` bytes of
+    // `buffer` from `offset` out
+    fn read(&self, buffer: &wgpu::Buffer, offset: u64, size: u64) -> Vec<u8> {
+        let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Read buffer"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Read encoder"),
+        });
+        encoder.copy_buffer_to_buffer(buffer, offset, &staging, 0, size);
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = staging.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| {});
+        self.device.poll(wgpu::Maintain::Wait);
+        let bytes = slice.get_mapped_range().to_vec();
+        staging.unmap();
+        bytes
+    }
+}
+
+impl SimBackend for GpuSim {
+    // grows the buffers if the particles and their births don't fit, and
+    // restarts every reaction timer and lifecycle and drops every bond
+    fn upload(&mut self, circles: &[Circle]) -> Result<(), String> {
+        let limit = circles.len().max((self.max_particles as usize).min(MAX_PARTICLES));
+        fits(limit)?;
+        if limit > self.capacity() as usize {
+            self.reallocate(limit);
+        }
+        let mut slots = circles.to_vec();
+        slots.resize(self.capacity() as usize, Circle::default());
+        self.queue.write_buffer(&self.circ_buffer, 0, bytemuck::cast_slice(&slots));
+        self.queue.write_buffer(&self.timers_buffer, 0, &vec![0; self.timers_buffer.size() as usize]);
+        self.queue.write_buffer(&self.lives_buffer, 0, &vec![0; self.lives_buffer.size() as usize]);
+        self.queue.write_buffer(&self.bonds_buffer, 0, bytemuck::bytes_of(&0u32));
+        self.bonded = self.forming;
+        self.start = circles.len();
+        self.queue.write_buffer(&self.frame_buffer, 12, bytemuck::cast_slice(&[circles.len() as u32, limit as u32]));
+        Ok(())
+    }
+
+    fn set_rules(&mut self, rules: &Rules) {
+        write_constraints(&self.queue, &self.constraints_tex, rules);
+        write_curves(&self.queue, &self.curves_tex, rules);
+        self.queue.write_buffer(&self.species_buffer, 0, bytemuck::cast_slice(&species(rules)));
+        self.queue.write_buffer(&self.reactions_buffer, 0, &reactions(rules));
+        self.reacting = !rules.reactions().is_empty();
+        self.queue.write_buffer(&self.lifecycles_buffer, 0, bytemuck::cast_slice(&lifecycles(rules)));
+        self.living = rules.living();
+        self.queue.write_buffer(&self.bond_rules_buffer, 0, &bond_rules(rules));
+        self.forming = !rules.bond_rules().is_empty();
+        self.bonded |= self.forming;
+        self.queue.write_buffer(&self.fields_buffer, 0, &fields(rules));
+        self.queue.write_buffer(&self.obstacles_buffer, 0, &obstacles(rules));
+    }
+
+    fn set_params(&mut self, params: &Params) {
+        self.queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[*params]));
+        self.thermal = params.thermal();
+        if params.max_particles != self.max_particles {
+            self.max_particles = params.max_particles;
+            let limit = params.limit(self.start);
+            if limit > self.capacity() as usize {
+                self.reallocate(limit);
+            }
+            self.queue.write_buffer(&self.frame_buffer, 16, bytemuck::bytes_of(&(limit as u32)));
+        }
+    }
+
+    fn set_dt(&mut self, dt: f32) {
+        self.queue.write_buffer(&self.dt_buffer, 0, bytemuck::cast_slice(&[dt]));
+    }
+
+    // the seed and both clocks, leaving the count alone
+    fn set_seed(&mut self, seed: u64) {
+        self.queue.write_buffer(&self.frame_buffer, 0, bytemuck::cast_slice(&[thermal::fold(seed), 0]));
+        self.queue.write_buffer(&self.frame_buffer, 20, bytemuck::bytes_of(&0u32));
+    }
+
+    fn step(&mut self, steps: usize) {
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Compute encoder"),
+        });
+        self.encode(&mut encoder, steps);
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    // every slot along with the frame, which says how many are live
+    fn download(&self) -> Vec<Circle> {
+        let size = self.circ_buffer.size();
+        let frame = std::mem::size_of::<Frame>() as u64;
+        let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Circle download buffer"),
+            size: size + frame,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Download encoder"),
+        });
+        encoder.copy_buffer_to_buffer(&self.circ_buffer, 0, &staging, 0, size);
+        encoder.copy_buffer_to_buffer(&self.frame_buffer, 0, &staging, size, frame);
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = staging.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| {});
+        self.device.poll(wgpu::Maintain::Wait);
+        let bytes = slice.get_mapped_range();
+        let count = bytemuck::pod_read_unaligned::<Frame>(&bytes[size as usize..]).count as usize;
+        let mut circles: Vec<Circle> = bytemuck::cast_slice(&bytes[..size as usize]).to_vec();
+        drop(bytes);
+        staging.unmap();
+        circles.truncate(count);
+        circles
+    }
+
+    // growing the bonds buffer if they don't fit
+    fn set_bonds(&mut self, bonds: &[Bond]) {
+        let frame: Frame = bytemuck::pod_read_unaligned(&self.read(&self.frame_buffer, 0, std::mem::size_of::<Frame>() as u64));
+        let bonds = bonds::valid(bonds, frame.count as usize);
+        if bonds.len() > self.bond_capacity() as usize {
+            self.bonds_buffer = bonds_buffer(&self.device, &bonds, bonds.len());
+            self.rebind();
+        } else {
+            self.queue.write_buffer(&self.bonds_buffer, 0, &bond_bytes(&bonds));
+        }
+        self.bonded |= !bonds.is_empty();
+    }
+
+    fn bonds(&self) -> Vec<Bond> {
+        let count: u32 = bytemuck::pod_read_unaligned(&self.read(&self.bonds_buffer, 0, 4));
+        if count == 0 {
+            return Vec::new();
+        }
+        let size = count as u64 * std::mem::size_of::<Bond>() as u64;
+        self.read(&self.bonds_buffer, 4, size)
+            .chunks_exact(std::mem::size_of::<Bond>())
+            .map(|bytes| {
+                let bond: Bond = bytemuck::pod_read_unaligned(bytes);
+                let strength = if bond.strength == f32::MAX { f32::INFINITY } else { bond.strength };
+                Bond { strength, ..bond }
+            })
+            .collect()
+    }
+
+    fn gpu(&self) -> Option<&GpuSim> {
+        Some(self)
+    }
+}
+
+/// Layout of the bonds buffer as the viewer's `vs_bond` reads it.
+pub fn bond_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+        label: Some("bond bind group layout"),
+    })
+}
+
+/// A bonds buffer laid out like `Bonds` in shader.wgsl, holding `bonds` with
+/// room for `slots` in all.
+pub fn bonds_buffer(device: &wgpu::Device, bonds: &[Bond], slots: usize) -> wgpu::Buffer {
+    let mut contents = bond_bytes(bonds);
+    contents.resize(4 + slots.max(bonds.len()).max(1) * std::mem::size_of::<Bond>(), 0);
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Bonds buffer"),
+        contents: &contents,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST,
+    })
+}
+
+// the buffer each step copies `bonds` into
+fn held_buffer(device: &wgpu::Device, bonds: &wgpu::Buffer) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Held bonds buffer"),
+        size: bonds.size(),
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+/// The bond count followed by the bonds, unbreakable ones stored with the
+/// largest finite strength, which every shader compares safely.
+pub fn bond_bytes(bonds: &[Bond]) -> Vec<u8> {
+    let list: Vec<Bond> = bonds.iter().map(|b| Bond { strength: b.strength.min(f32::MAX), ..*b }).collect();
+    let mut bytes = (bonds.len() as u32).to_le_bytes().to_vec();
+    bytes.extend_from_slice(bytemuck::cast_slice(&list));
+    bytes
+}
+
+pub fn bond_bind_group(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, buffer: &wgpu::Buffer) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: buffer.as_entire_binding(),
+        }],
+        label: Some("bond bind group"),
+    })
+}
+
+/// Layout of the particle storage buffer, shared by `compute_main` and the
+/// viewer's vertex shader.
+pub fn circle_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::COMPUTE | wgpu::ShaderStages::VERTEX,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+        label: Some("circle bind group layout"),
+    })
+}
+
+// the previous state `compute_main` reads from
+fn prev_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+        label: Some("previous circle bind group layout"),
+    })
+}
+
+pub fn circle_buffer(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, circles: &[Circle]) -> (wgpu::Buffer, wgpu::BindGroup) {
+    // a zero-sized binding isn't allowed, so keep room for one particle
+    let mut contents = bytemuck::cast_slice(circles).to_vec();
+    contents.resize(contents.len().max(std::mem::size_of::<Circle>()), 0);
+    let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Circle Buffer"),
+        contents: &contents,
+        usage: wgpu::BufferUsages::INDEX
+            | wgpu::BufferUsages::STORAGE
+            | wgpu::BufferUsages::COPY_SRC
+            | wgpu::BufferUsages::COPY_DST,
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: buffer.as_entire_binding(),
+        }],
+        label: Some("circ bind group"),
+    });
+    (buffer, bind_group)
+}
+
+pub fn write_constraints(queue: &wgpu::Queue, constraints_tex: &wgpu::Texture, rules: &Rules) {
+    let constraints_tex_size = wgpu::Extent3d {
+        width: NUM_COLORS,
+        height: NUM_COLORS,
+        depth_or_array_layers: 1,
+    };
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture: constraints_tex,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        bytemuck::cast_slice(&rules.texels(NUM_COLORS as usize)),
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(16 * NUM_COLORS),
+            rows_per_image: Some(NUM_COLORS),
+        },
+        constraints_tex_size,
+    );
+}
+
+pub fn write_curves(queue: &wgpu::Queue, curves_tex: &wgpu::Texture, rules: &Rules) {
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture: curves_tex,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        bytemuck::cast_slice(&rules.curve_texels(NUM_COLORS as usize)),
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * SAMPLES as u32),
+            rows_per_image: Some(NUM_COLORS * NUM_COLORS),
+        },
+        wgpu::Extent3d {
+            width: SAMPLES as u32,
+            height: NUM_COLORS * NUM_COLORS,
+            depth_or_array_layers: 1,
+        },
+    );
+}
+
+// every species' properties, padded to NUM_COLORS so colours past the rules
+// read defaults
+fn species(rules: &Rules) -> Vec<Species> {
+    let mut species = rules.all_properties().to_vec();
+    species.resize(NUM_COLORS as usize, Species::default());
+    species
+}
+
+// the reaction count followed by MAX_REACTIONS entries, laid out like
+// `Reactions` in shader.wgsl
+fn reactions(rules: &Rules) -> Vec<u8> {
+    let mut list = [GpuReaction::default(); MAX_REACTIONS];
+    for (slot, r) in list.iter_mut().zip(rules.reactions()) {
+        *slot = GpuReaction {
+            from: r.from as i32,
+            with: r.with as i32,
+            into: r.into as i32,
+            range: r.range,
+            time: r.time,
+        };
+    }
+    let mut bytes = (rules.reactions().len() as u32).to_le_bytes().to_vec();
+    bytes.extend_from_slice(bytemuck::cast_slice(&list));
+    bytes
+}
+
+// the bond rule count followed by MAX_BOND_RULES entries, laid out like
+// `BondRules` in shader.wgsl
+fn bond_rules(rules: &Rules) -> Vec<u8> {
+    let mut list = [GpuBondRule::default(); MAX_BOND_RULES];
+    for (slot, b) in list.iter_mut().zip(rules.bond_rules()) {
+        *slot = GpuBondRule {
+            a: b.a as i32,
+            b: b.b as i32,
+            valency: b.valency,
+            range: b.range,
+            rest: b.spring.rest,
+            stiffness: b.spring.stiffness,
+            strength: b.spring.strength.min(f32::MAX),
+        };
+    }
+    let mut bytes = (rules.bond_rules().len() as u32).to_le_bytes().to_vec();
+    bytes.extend_from_slice(bytemuck::cast_slice(&list));
+    bytes
+}
+
+// the field count followed by MAX_FIELDS entries, laid out like `Fields` in
+// shader.wgsl
+fn fields(rules: &Rules) -> Vec<u8> {
+    parts(rules.fields().iter().map(|f| f.to_parts()), MAX_FIELDS)
+}
+
+// the same for the obstacles and `Obstacles`
+fn obstacles(rules: &Rules) -> Vec<u8> {
+    parts(rules.obstacles().iter().map(|o| o.to_parts()), MAX_OBSTACLES)
+}
+
+fn parts(parts: impl Iterator<Item = (u32, [f32; 4])>, slots: usize) -> Vec<u8> {
+    let mut list = vec![GpuPart::default(); slots];
+    let mut count = 0u32;
+    for (slot, (kind, v)) in list.iter_mut().zip(parts) {
+        *slot = GpuPart { kind, v };
+        count += 1;
+    }
+    let mut bytes = count.to_le_bytes().to_vec();
+    bytes.extend_from_slice(bytemuck::cast_slice(&list));
+    bytes
+}
+
+// every species' lifecycle, padded to NUM_COLORS like the properties
+fn lifecycles(rules: &Rules) -> Vec<GpuLifecycle> {
+    let mut lifecycles = rules.all_lifecycles().to_vec();
+    lifecycles.resize(NUM_COLORS as usize, Lifecycle::default());
+    lifecycles
+        .iter()
+        .map(|l| GpuLifecycle {
+            // an infinite lifespan isn't safe to compare in every shader
+            lifespan: l.lifespan.min(f32::MAX),
+            range: l.range,
+            partner: l.partner.map_or(-1, |p| p as i32),
+            crowd: l.crowd,
+            period: l.period,
+            lonely: l.lonely,
+        })
+        .collect()
+}
+
+// zeroed state for `slots` particle slots, with room for one so the binding
+// isn't empty
+fn state_buffer<T>(device: &wgpu::Device, label: &str, slots: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size: (slots.max(1) * std::mem::size_of::<T>()) as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+// more slots than `MAX_PARTICLES` would dispatch past wgpu's limit
+fn fits(slots: usize) -> Result<(), String> {
+    if slots > MAX_PARTICLES {
+        return Err(format!("{} particle slots, the GPU steps at most {}", slots, MAX_PARTICLES));
+    }
+    Ok(())
+}
+
+// blocks of SCAN slots the lifecycle passes cover `slots` with
+fn blocks(slots: usize) -> usize {
+    slots.div_ceil(SCAN as usize)
+}
+
+fn frame_bind_group(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, buffers: [&wgpu::Buffer; 8]) -> wgpu::BindGroup {
+    let entries: Vec<wgpu::BindGroupEntry> = buffers
+        .iter()
+        .enumerate()
+        .map(|(binding, buffer)| wgpu::BindGroupEntry {
+            binding: binding as u32,
+            resource: buffer.as_entire_binding(),
+        })
+        .collect();
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &entries,
+        label: Some("frame bind group"),
+    })
+}
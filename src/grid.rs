@@ -0,0 +1,88 @@
+/// Uniform bucket grid over a set of points, for finding everything within
+/// one cell width of a position without testing every pair.
+pub struct Grid {
+    cell: f32,
+    origin: [f32; 2],
+    cols: usize,
+    rows: usize,
+    starts: Vec<usize>,
+    items: Vec<usize>,
+}
+
+impl Grid {
+    pub fn new(points: &[[f32; 2]], cell: f32) -> Self {
+        let mut min = [f32::MAX; 2];
+        let mut max = [f32::MIN; 2];
+        for p in points.iter().filter(|p| p[0].is_finite() && p[1].is_finite()) {
+            for k in 0..2 {
+                min[k] = min[k].min(p[k]);
+                max[k] = max[k].max(p[k]);
+            }
+        }
+        if min[0] > max[0] {
+            min = [0.0; 2];
+            max = [0.0; 2];
+        }
+
+        // widen the cells if the points are spread so far apart that the
+        // grid would be mostly empty; a wider cell still covers `cell`
+        let limit = (4 * points.len()).max(64) as f32;
+        let cell = cell.max((max[0] - min[0]).max(max[1] - min[1]) / limit.sqrt());
+
+        let cols = ((max[0] - min[0]) / cell) as usize + 1;
+        let rows = ((max[1] - min[1]) / cell) as usize + 1;
+        let mut grid = Self {
+            cell,
+            origin: min,
+            cols,
+            rows,
+            starts: vec![0; cols * rows + 1],
+            items: vec![0; points.len()],
+        };
+
+        // counting sort of point indices by cell
+        let cells: Vec<Option<usize>> = points.iter().map(|&p| grid.cell_index(p)).collect();
+        for c in cells.iter().flatten() {
+            grid.starts[c + 1] += 1;
+        }
+        for c in 0..cols * rows {
+            grid.starts[c + 1] += grid.starts[c];
+        }
+        let mut fill = grid.starts.clone();
+        for (i, c) in cells.iter().enumerate() {
+            if let Some(c) = c {
+                grid.items[fill[*c]] = i;
+                fill[*c] += 1;
+            }
+        }
+        grid.items.truncate(grid.starts[cols * rows]);
+        grid
+    }
+
+    fn cell_coords(&self, p: [f32; 2]) -> Option<(usize, usize)> {
+        if !(p[0].is_finite() && p[1].is_finite()) {
+            return None;
+        }
+        let x = ((p[0] - self.origin[0]) / self.cell).floor().clamp(0.0, (self.cols - 1) as f32);
+        let y = ((p[1] - self.origin[1]) / self.cell).floor().clamp(0.0, (self.rows - 1) as f32);
+        Some((x as usize, y as usize))
+    }
+
+    fn cell_index(&self, p: [f32; 2]) -> Option<usize> {
+        self.cell_coords(p).map(|(x, y)| y * self.cols + x)
+    }
+
+    /// Calls `f` with every point in the 3x3 block of cells around `p`. That
+    /// covers everything within `cell` of `p`, plus some points further out.
+    pub fn for_each_near(&self, p: [f32; 2], mut f: impl FnMut(usize)) {
+        let Some((cx, cy)) = self.cell_coords(p) else { return };
+        for y in cy.saturating_sub(1)..=(cy + 1).min(self.rows - 1) {
+            let row = y * self.cols;
+            let lo = self.starts[row + cx.saturating_sub(1)];
+            let hi = self.starts[row + (cx + 1).min(self.cols - 1) + 1];
+            for &i in &self.items[lo..hi] {
+                f(i);
+            }
+        }
+    }
+}
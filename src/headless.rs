@@ -0,0 +1,170 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::args::Args;
+use crate::{RDF_BINS, RDF_SAMPLE_EVERY};
+use physics::backend::{Backend, SimBackend};
+use physics::bench;
+use physics::circle::Circle;
+use physics::code::{self, Setup};
+use physics::evolve;
+use physics::export::{Exporter, Format};
+use physics::novelty;
+use physics::rdf::Rdf;
+use physics::sim;
+use physics::trajectory::{self, Recorder};
+
+/// Runs the headless mode picked on the command line, if any. Returns false
+/// when the viewer should start instead.
+pub fn run(args: &Args, setup: &Setup) -> bool {
+    let result = if let Some(dir) = &args.evolve {
+        evolve(args, setup, dir);
+        Ok(())
+    } else if let Some(dir) = &args.novelty {
+        novelty(args, setup, dir);
+        Ok(())
+    } else if let Some(path) = &args.rdf {
+        rdf(args, setup, path)
+    } else if let Some(path) = &args.record {
+        record(args, setup, path)
+    } else if let Some(path) = &args.export {
+        export(args, setup, path)
+    } else if let Some(path) = &args.bench {
+        bench(args, setup, path)
+    } else {
+        return false;
+    };
+
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    true
+}
+
+// headless runs default to the single-threaded CPU port; `--backend` can
+// pick any other
+fn backend(args: &Args, setup: &Setup) -> io::Result<Box<dyn SimBackend>> {
+    let circles = Circle::scatter(args.particles, setup.rules.species() as u32, setup.params.world_size * 0.8, setup.seed);
+    let backend = args.backend.unwrap_or(Backend::Cpu);
+    let mut sim = backend
+        .create(&circles, &setup.rules, &setup.params, args.neighbours)
+        .map_err(|e| io::Error::new(io::ErrorKind::Unsupported, e))?;
+    sim.set_seed(setup.seed);
+    Ok(sim)
+}
+
+fn evolve(args: &Args, setup: &Setup, dir: &str) {
+    evolve::run(&evolve::Config {
+        species: setup.rules.species(),
+        params: setup.params,
+        seed: setup.seed,
+        particles: args.particles,
+        steps: args.steps.max(4),
+        population: args.population.max(2),
+        generations: args.generations,
+        keep: 4,
+        out_dir: dir.into(),
+    });
+}
+
+fn novelty(args: &Args, setup: &Setup, dir: &str) {
+    novelty::run(&novelty::Config {
+        species: setup.rules.species(),
+        params: setup.params,
+        seed: setup.seed,
+        particles: args.particles,
+        steps: args.steps.max(4),
+        population: args.population.max(2),
+        generations: args.generations,
+        threshold: args.threshold,
+        out_dir: dir.into(),
+    });
+}
+
+fn rdf(args: &Args, setup: &Setup, path: &str) -> io::Result<()> {
+    let mut sim = backend(args, setup)?;
+    let window = args.window.min(args.steps);
+    sim.step(args.steps - window);
+    let mut rdf = Rdf::new(setup.rules.species(), RDF_BINS, 2.0 * setup.params.rmax, setup.params.world_size);
+    for _ in 0..window / RDF_SAMPLE_EVERY as usize {
+        sim.step(RDF_SAMPLE_EVERY as usize);
+        rdf.add(&sim.download());
+    }
+    rdf.write_csv(path).map_err(|e| with_path(e, path))
+}
+
+fn record(args: &Args, setup: &Setup, path: &str) -> io::Result<()> {
+    let every = args.every.max(1);
+    let header = trajectory::Header {
+        every,
+        world_size: setup.params.world_size,
+        code: code::encode(setup).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?,
+    };
+    let mut recorder = Recorder::create(path, &header).map_err(|e| with_path(e, path))?;
+    for_each_frame(args, setup, |step, circles| recorder.record(step, step as f64 * sim::DT as f64, circles))?;
+    recorder.finish()
+}
+
+/// Exports either a recording given with `--from` or a fresh run.
+fn export(args: &Args, setup: &Setup, path: &str) -> io::Result<()> {
+    let format = match &args.format {
+        Some(name) => Format::parse(name),
+        None => Format::from_path(Path::new(path)),
+    };
+    let format = format.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "pick an export format with --format xyz|extxyz|csv")
+    })?;
+
+    match &args.from {
+        Some(from) => {
+            let (header, frames) = trajectory::load(from).map_err(|e| with_path(e, from))?;
+            let mut exporter = Exporter::create(path, format, header.world_size).map_err(|e| with_path(e, path))?;
+            for frame in &frames {
+                exporter.write(frame.step, frame.time as f32, &frame.circles)?;
+            }
+            exporter.finish()
+        }
+        None => {
+            let mut exporter = Exporter::create(path, format, setup.params.world_size).map_err(|e| with_path(e, path))?;
+            for_each_frame(args, setup, |step, circles| exporter.write(step, step as f32 * sim::DT, circles))?;
+            exporter.finish()
+        }
+    }
+}
+
+/// Writes the benchmark report as JSON to `path`, or stdout for `-`.
+fn bench(args: &Args, setup: &Setup, path: &str) -> io::Result<()> {
+    let report = bench::run(&bench::Config {
+        particles: bench::PARTICLES.to_vec(),
+        species: bench::SPECIES.to_vec(),
+        params: setup.params,
+        seed: setup.seed,
+        steps: args.steps,
+        budget: Duration::from_secs(2),
+    });
+    let json = serde_json::to_string_pretty(&report).map_err(io::Error::other)?;
+    if path == "-" {
+        println!("{}", json);
+        Ok(())
+    } else {
+        fs::write(path, json + "\n").map_err(|e| with_path(e, path))
+    }
+}
+
+// runs `--steps` steps, handing every `--every`th one to `f`
+fn for_each_frame(args: &Args, setup: &Setup, mut f: impl FnMut(u64, &[Circle]) -> io::Result<()>) -> io::Result<()> {
+    let every = args.every.max(1);
+    let mut sim = backend(args, setup)?;
+    for step in (0..=args.steps as u64).step_by(every as usize) {
+        f(step, &sim.download())?;
+        sim.step(every as usize);
+    }
+    Ok(())
+}
+
+fn with_path(e: io::Error, path: &str) -> io::Error {
+    io::Error::new(e.kind(), format!("{}: {}", path, e))
+}
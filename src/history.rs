@@ -0,0 +1,56 @@
+use std::collections::VecDeque;
+
+/// Bounded undo/redo stack. The entry under the cursor is the current one;
+/// pushing drops everything after it, and the oldest entries fall off the
+/// front once `cap` is reached.
+pub struct History<T> {
+    entries: VecDeque<T>,
+    cursor: usize,
+    cap: usize,
+}
+
+impl<T> History<T> {
+    pub fn new(initial: T, cap: usize) -> Self {
+        let mut entries = VecDeque::with_capacity(cap);
+        entries.push_back(initial);
+        Self {
+            entries,
+            cursor: 0,
+            cap: cap.max(1),
+        }
+    }
+
+    pub fn push(&mut self, entry: T) {
+        self.entries.truncate(self.cursor + 1);
+        self.entries.push_back(entry);
+        while self.entries.len() > self.cap {
+            self.entries.pop_front();
+        }
+        self.cursor = self.entries.len() - 1;
+    }
+
+    pub fn undo(&mut self) -> Option<&T> {
+        if self.cursor == 0 {
+            return None;
+        }
+        self.cursor -= 1;
+        self.entries.get(self.cursor)
+    }
+
+    pub fn redo(&mut self) -> Option<&T> {
+        if self.cursor + 1 >= self.entries.len() {
+            return None;
+        }
+        self.cursor += 1;
+        self.entries.get(self.cursor)
+    }
+
+    pub fn current(&self) -> &T {
+        &self.entries[self.cursor]
+    }
+
+    /// Position of the current entry and the number of entries kept.
+    pub fn position(&self) -> (usize, usize) {
+        (self.cursor + 1, self.entries.len())
+    }
+}
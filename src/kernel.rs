@@ -0,0 +1,93 @@
+use std::f32::consts::TAU;
+
+use crate::params::Params;
+
+/// Shape of the force between a pair of particles against their distance.
+/// Every analytic shape but `LennardJones` repels linearly up to `rmin` and
+/// scales the rest by the pair's rule, and every shape is cut off at 1.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Kernel {
+    /// a triangle peaking halfway between `rmin` and 1, the original law
+    #[default]
+    Triangle,
+    /// a raised-cosine bump over the same band as the triangle
+    Cosine,
+    /// an `(rmin/d)^12` core and an `(rmin/d)^6` tail, the core capped so
+    /// overlapping particles don't fly apart
+    LennardJones,
+    /// a Gaussian centred on the triangle's peak
+    Gaussian,
+    /// `(rmin/d)^2` from `rmin`
+    InverseSquare,
+    /// the pair's curve from `Rules::curve`, taken as the force itself
+    Tabulated,
+}
+
+impl Kernel {
+    pub const ALL: [Kernel; 6] = [
+        Kernel::Triangle,
+        Kernel::Cosine,
+        Kernel::LennardJones,
+        Kernel::Gaussian,
+        Kernel::InverseSquare,
+        Kernel::Tabulated,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Kernel::Triangle => "triangle",
+            Kernel::Cosine => "cosine",
+            Kernel::LennardJones => "lennard-jones",
+            Kernel::Gaussian => "gaussian",
+            Kernel::InverseSquare => "inverse-square",
+            Kernel::Tabulated => "tabulated",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.name() == name)
+    }
+
+    /// Index the shader switches on, stored in the constraints texture.
+    pub fn index(self) -> u32 {
+        self as u32
+    }
+
+    pub fn from_index(index: u32) -> Option<Self> {
+        Self::ALL.get(index as usize).copied()
+    }
+
+    /// Force between two particles `d` apart, in units of `rmax`, along the
+    /// direction away from the other one, `acc` being their rule scaled by
+    /// `ff`. `kernel` in shader.wgsl. `Tabulated` has no shape of its own
+    /// and comes out as zero; `Rules::force` looks its curve up.
+    pub fn force(self, d: f32, acc: f32, params: &Params) -> f32 {
+        let rmin = params.rmin;
+        let repel = params.racc * (1.0 - d / rmin);
+        match self {
+            _ if d >= 1.0 => 0.0,
+            Kernel::LennardJones => {
+                let r = rmin / d.max(1e-6);
+                let s = (r * r * r * r * r * r).min(2.0);
+                params.racc * s * (s - 1.0) + acc * s
+            }
+            Kernel::Tabulated => 0.0,
+            _ if d < rmin => repel,
+            Kernel::Triangle => acc * (1.0 - (2.0 * d - 1.0 - rmin).abs() / (1.0 - rmin)),
+            Kernel::Cosine => acc * 0.5 * (1.0 - (TAU * (d - rmin) / (1.0 - rmin)).cos()),
+            Kernel::Gaussian => {
+                let x = (d - (1.0 + rmin) / 2.0) / ((1.0 - rmin) / 4.0);
+                acc * (-x * x).exp()
+            }
+            Kernel::InverseSquare => acc * (rmin / d) * (rmin / d),
+        }
+    }
+
+    /// The force at `samples` even steps of `d` from 0 to 1, for plots.
+    pub fn curve(self, acc: f32, params: &Params, samples: usize) -> Vec<f32> {
+        (0..samples)
+            .map(|k| self.force(k as f32 / (samples - 1) as f32, acc, params))
+            .collect()
+    }
+}
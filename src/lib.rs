@@ -0,0 +1,41 @@
+pub const NUM_COLORS: u32 = 6;
+pub const COLORS: [[u8; 4]; NUM_COLORS as usize] = [
+    [255, 0, 0, 255],   // RED
+    [255, 128, 0, 255],   // ORANGE
+    [255, 255, 0, 255],   // YELLOW
+    [0, 255, 0, 255],   // GREEN
+    [0, 0, 255, 255],   // BLUE
+    [255, 0, 255, 255],   // PURPLE
+
+];
+
+pub mod backend;
+pub mod bench;
+pub mod bonds;
+pub mod circle;
+pub mod code;
+pub mod contact;
+pub mod curves;
+pub mod cpu;
+pub mod evolve;
+pub mod export;
+pub mod fields;
+pub mod friction;
+pub mod gpu;
+pub mod grid;
+pub mod history;
+pub mod kernel;
+pub mod lifecycle;
+pub mod metrics;
+pub mod novelty;
+pub mod params;
+pub mod preset;
+pub mod reactions;
+pub mod rdf;
+pub mod rules;
+pub mod sim;
+pub mod soa;
+pub mod species;
+pub mod thermal;
+pub mod thumbnail;
+pub mod trajectory;
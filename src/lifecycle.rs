@@ -0,0 +1,143 @@
+use rayon::prelude::*;
+
+use crate::circle::Circle;
+use crate::grid::Grid;
+use crate::reactions::Timer;
+use crate::thermal;
+
+/// How particles of a species age, breed and die. The default lives forever
+/// and never breeds.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Lifecycle {
+    /// seconds a particle lives, forever if left out
+    #[serde(skip_serializing_if = "forever")]
+    pub lifespan: f32,
+    /// distance neighbours are counted within
+    pub range: f32,
+    /// species whose crowding makes this one breed, none if left out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partner: Option<usize>,
+    /// partners in range a particle needs to breed
+    pub crowd: u32,
+    /// seconds between a particle's births, counted from its own
+    pub period: f32,
+    /// fewest neighbours of any species in range a particle survives with
+    pub lonely: u32,
+}
+
+// serde can't write an infinite float to every format
+fn forever(lifespan: &f32) -> bool {
+    lifespan.is_infinite()
+}
+
+impl Default for Lifecycle {
+    fn default() -> Self {
+        Self {
+            lifespan: f32::INFINITY,
+            range: 1.0,
+            partner: None,
+            crowd: 1,
+            period: 1.0,
+            lonely: 0,
+        }
+    }
+}
+
+impl Lifecycle {
+    /// Whether particles of the species can neither breed nor die.
+    pub fn is_static(&self) -> bool {
+        self.lifespan.is_infinite() && self.partner.is_none() && self.lonely == 0
+    }
+
+    /// Checks the lifecycle for one of `species` species: a positive
+    /// `lifespan`, a positive, finite `range`, a `period` that isn't
+    /// negative and a `partner` that exists.
+    pub fn validate(&self, species: usize) -> Result<(), String> {
+        let partner = self.partner.is_none_or(|p| p < species);
+        if self.lifespan > 0.0 && self.range > 0.0 && self.range.is_finite() && self.period >= 0.0 && partner {
+            return Ok(());
+        }
+        Err(format!("lifecycle out of range: {:?}", self))
+    }
+}
+
+/// A particle's age and time since it was born or last bred, with the flags
+/// `judge` leaves for `compact`. `Life` in shader.wgsl.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Life {
+    pub age: f32,
+    pub since: f32,
+    pub alive: u32,
+    pub breeds: u32,
+}
+
+/// Ages every particle by a step of `dt` and flags whether it survives the
+/// step and whether it breeds, from the positions and species at the start
+/// of the step. `life_main` in shader.wgsl.
+pub fn judge(lifecycles: &[Lifecycle], pos: &[[f32; 2]], species: &[i32], lives: &mut [Life], dt: f32) {
+    // neighbours are only counted within the longest range
+    let grid = Grid::new(pos, lifecycles.iter().map(|l| l.range).fold(0.0, f32::max));
+    lives.par_iter_mut().enumerate().for_each(|(i, life)| {
+        let rule = lifecycles[species[i] as usize];
+        let (mut neighbours, mut partners) = (0, 0);
+        grid.for_each_near(pos[i], |j| {
+            let diff = [pos[i][0] - pos[j][0], pos[i][1] - pos[j][1]];
+            if j != i && (diff[0] * diff[0] + diff[1] * diff[1]).sqrt() < rule.range {
+                neighbours += 1;
+                partners += (Some(species[j] as usize) == rule.partner) as u32;
+            }
+        });
+        life.age += dt;
+        life.since += dt;
+        let alive = life.age < rule.lifespan && neighbours >= rule.lonely;
+        let breeds = alive && rule.partner.is_some() && partners >= rule.crowd && life.since >= rule.period;
+        if breeds {
+            life.since = 0.0;
+        }
+        life.alive = alive as u32;
+        life.breeds = breeds as u32;
+    });
+}
+
+/// Drops the particles `judge` found dead, keeping the order of the rest,
+/// then gives each one that breeds a child of its species next to it, while
+/// there are fewer than `limit`. Children are scattered by a draw for their
+/// slot at `clock`, which should move on every lifecycle step. Returns the
+/// slot each particle moved to, None for the dead. `scan_main`,
+/// `scan_blocks` and `scatter_main` in shader.wgsl.
+pub fn compact(circles: &mut Vec<Circle>, lives: &mut Vec<Life>, timers: &mut Vec<Timer>, limit: usize, seed: u32, clock: u32) -> Vec<Option<usize>> {
+    let mut remap = vec![None; circles.len()];
+    let mut kept = 0;
+    for i in 0..circles.len() {
+        if lives[i].alive != 0 {
+            remap[i] = Some(kept);
+            circles[kept] = circles[i];
+            lives[kept] = lives[i];
+            timers[kept] = timers[i];
+            kept += 1;
+        }
+    }
+    circles.truncate(kept);
+    lives.truncate(kept);
+    timers.truncate(kept);
+    for k in 0..kept {
+        if circles.len() >= limit {
+            break;
+        }
+        if lives[k].breeds == 0 {
+            continue;
+        }
+        let parent = circles[k];
+        // a stream apart from the thermal noise's
+        let g = thermal::gaussian(!seed, clock, circles.len() as u32);
+        circles.push(Circle {
+            pos: [parent.pos[0] + g[0] * parent.rad, parent.pos[1] + g[1] * parent.rad],
+            ..parent
+        });
+        lives.push(Life::default());
+        timers.push(Timer::default());
+    }
+    remap
+}
@@ -1,27 +1,42 @@
 const CIRCLES: usize = 3000;
 
-const NUM_COLORS: u32 = 6;
-const COLORS: [[u8; 4]; NUM_COLORS as usize] = [
-    [255, 0, 0, 255],   // RED
-    [255, 128, 0, 255],   // ORANGE
-    [255, 255, 0, 255],   // YELLOW
-    [0, 255, 0, 255],   // GREEN
-    [0, 0, 255, 255],   // BLUE
-    [255, 0, 255, 255],   // PURPLE
-
-];
 /*const CONSTRAINTS: [[[f32; 4]; NUM_COLORS as usize]; NUM_COLORS as usize] = [
     [[1.0, 0.0, 0.0, 0.0], [0.2, 0.0, 0.0, 0.0], [-0.2, 0.0, 0.0, 0.0]],
     [[-0.2, 0.0, 0.0, 0.0], [1.0, 0.0, 0.0, 0.0], [0.2, 0.0, 0.0, 0.0]],
     [[0.2, 0.0, 0.0, 0.0], [-0.2, 0.0, 0.0, 0.0], [1.0, 0.0, 0.0, 0.0]]
 ];*/
 
+const HISTORY_LEN: usize = 100;
+
+const RDF_BINS: usize = 64;
+// download the particles for g(r) every this many frames
+const RDF_SAMPLE_EVERY: u64 = 10;
+// and show the average once this many samples are in
+const RDF_WINDOW: u64 = 20;
+
+// species counts kept for the population chart, one a second
+const POPULATION_SAMPLES: usize = 120;
+
+// record every this many compute steps from the viewer
+const RECORD_EVERY: u32 = 5;
+// fraction of a recording jumped over by one scrub key press
+const SCRUB_STEP: f32 = 0.05;
+const PARAM_STEP: f32 = 1.1;
+// where raising a parameter from zero starts
+const PARAM_FLOOR: f32 = 0.01;
+
 const ZOOM: f32 = 20.0;
 const CAMERA_MOVE_SPEED: f32 = 20.0;
 const CAMERA_ZOOM_SPEED: f32 = 2.0;
+// the curve editor's box in clip space and the force at its top edge
+const EDITOR_MIN: [f32; 2] = [-0.95, -0.35];
+const EDITOR_MAX: [f32; 2] = [-0.15, 0.35];
+const EDITOR_RANGE: f32 = 2.0;
 
+mod args;
 mod camera;
-mod circle;
+mod headless;
+mod overlay;
 
 use winit::{
     event::*,
@@ -32,10 +47,30 @@ use winit::{
 use wgpu::util::DeviceExt;
 
 use rand::random;
+use std::sync::Arc;
 use std::time::Instant;
 
+use args::Args;
 use camera::Camera;
-use circle::Circle;
+use overlay::Overlay;
+use physics::backend::{Backend, SimBackend};
+use physics::circle::Circle;
+use physics::code::{self, Setup};
+use physics::curves;
+use physics::fields::{self, Obstacle};
+use physics::cpu::CpuSim;
+use physics::history::History;
+use physics::contact::Contact;
+use physics::friction::Friction;
+use physics::kernel::Kernel;
+use physics::metrics::{self, Population};
+use physics::params::Params;
+use physics::rdf::Rdf;
+use physics::rules::{Generator, Rules, PRESETS};
+use physics::sim::Neighbours;
+use physics::trajectory::{self, Recorder, Replay};
+use physics::gpu::{self, GpuSim};
+use physics::{preset, sim, COLORS, NUM_COLORS};
 
 #[repr(C)]
 #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
@@ -56,36 +91,147 @@ struct State {
     pause: bool,
 
     surface: wgpu::Surface,
-    device: wgpu::Device,
-    queue: wgpu::Queue,
+    device: Arc<wgpu::Device>,
+    queue: Arc<wgpu::Queue>,
     config: wgpu::SurfaceConfiguration,
     size: winit::dpi::PhysicalSize<u32>,
     window: Window,
     render_pipeline: wgpu::RenderPipeline,
+    // draws bonds as lines under the particles
+    bond_pipeline: wgpu::RenderPipeline,
     vertex_buffer: wgpu::Buffer,
     
     camera: Camera,
     last_frame: Instant,
+    // frames since the title's frame rate was last updated
+    fps_frames: u32,
+    fps_since: Instant,
+    // particles of each species, refreshed with the frame rate, and their
+    // history for the population chart
+    counts: Vec<usize>,
+    population: Population,
+    show_population: bool,
     camera_buffer: wgpu::Buffer,
     size_buffer: wgpu::Buffer,
     render_uniform_bind_group: wgpu::BindGroup,
 
-    dt_buffer: wgpu::Buffer,
-    compute_uniform_bind_group: wgpu::BindGroup,
+    params: Params,
+    sim: Box<dyn SimBackend>,
+    // particles are copied here for drawing when the simulation isn't on
+    // the GPU
     circ_buffer: wgpu::Buffer,
-    constraints_tex: wgpu::Texture,
+    circ_bind_group_layout: wgpu::BindGroupLayout,
     circ_bind_group: wgpu::BindGroup,
+    count: u32,
+    // and the bonds, with how many there are
+    bond_buffer: wgpu::Buffer,
+    bond_bind_group_layout: wgpu::BindGroupLayout,
+    bond_bind_group: wgpu::BindGroup,
+    bond_count: u32,
 
-    compute_pipeline: wgpu::ComputePipeline,
+    species: u32,
+    seed: u64,
+    rules: Rules,
+    preset: usize,
+    generator: usize,
+    history: History<Setup>,
+    selected_param: usize,
+
+    overlay: Overlay,
+    frame: u64,
+    show_rdf: bool,
+    show_kernels: bool,
+    // species pair whose force curve is being drawn, whether the mouse button
+    // is held and the last curve point it set
+    editing: Option<(usize, usize)>,
+    drawing: bool,
+    drawn: Option<(usize, f32)>,
+    cursor: [f32; 2],
+    rdf: Rdf,
+    rdf_shown: Option<Rdf>,
+
+    step: u64,
+    // simulated seconds, which steps shortened to fit the frame rate make
+    // more than `step` times sim::DT
+    time: f64,
+    recorder: Option<Recorder>,
+    replay: Option<Replay>,
 
     keys: [bool; 256],
 }
 
 fn main() {
+    let args = Args::parse().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    });
+
+    let mut setup = match (&args.code, &args.preset) {
+        (Some(code), _) => code::decode(code).unwrap_or_else(|e| {
+            eprintln!("bad --code: {}", e);
+            std::process::exit(2);
+        }),
+        (None, Some(path)) => preset::load(path).unwrap_or_else(|e| {
+            eprintln!("can't load preset {}: {}", path, e);
+            std::process::exit(2);
+        }),
+        (None, None) => Setup {
+            rules: Rules::random(NUM_COLORS as usize, 0.5, &mut rand::thread_rng()),
+            params: Params::default(),
+            seed: random(),
+        },
+    };
+    if let Some(seed) = args.seed {
+        setup.seed = seed;
+    }
+    if let Some(kernel) = args.kernel {
+        setup.rules.set_all_kernels(kernel);
+    }
+    if let Some(friction) = args.friction {
+        setup.params.set_friction(friction);
+    }
+    if let Some(contact) = args.contact {
+        setup.params.set_contact(contact);
+    }
+    if let Some(path) = &args.curves {
+        match curves::load_csv(path, &mut setup.rules) {
+            Ok(pairs) => println!("{} tabulated pairs from {}", pairs, path),
+            Err(e) => {
+                eprintln!("can't load curves {}: {}", path, e);
+                std::process::exit(2);
+            }
+        }
+    }
+    let species = setup.rules.species();
+    if species == 0 || species > NUM_COLORS as usize {
+        eprintln!("species count must be between 1 and {}, got {}", NUM_COLORS, species);
+        std::process::exit(2);
+    }
+
+    if headless::run(&args, &setup) {
+        return;
+    }
+
+    let replay = args.replay.as_ref().map(|path| {
+        let (header, frames) = trajectory::load(path).unwrap_or_else(|e| {
+            eprintln!("can't load {}: {}", path, e);
+            std::process::exit(1);
+        });
+        if frames.is_empty() {
+            eprintln!("{} has no frames", path);
+            std::process::exit(1);
+        }
+        match code::decode(&header.code) {
+            Ok(recorded) => setup = recorded,
+            Err(e) => eprintln!("recording has a bad share code, using the current rules: {}", e),
+        }
+        Replay::new(header, frames)
+    });
+
     // set up context and build window
     let event_loop = EventLoop::new();
     let window = WindowBuilder::new().build(&event_loop).unwrap();
-    let mut state = pollster::block_on(State::new(window));
+    let mut state = pollster::block_on(State::new(window, setup, replay, args.backend.unwrap_or(Backend::Gpu), args.neighbours));
 
     event_loop.run(move |event, _, control_flow| match event {
         Event::WindowEvent {
@@ -97,6 +243,34 @@ fn main() {
                 Some(VirtualKeyCode::Escape) => *control_flow = ControlFlow::Exit,
                 Some(VirtualKeyCode::Space) if matches!(input.state, ElementState::Pressed) => state.pause = !state.pause,
                 Some(VirtualKeyCode::R) if matches!(input.state, ElementState::Pressed) => state.randomize_constraints(),
+                Some(VirtualKeyCode::P) if matches!(input.state, ElementState::Pressed) => state.next_preset(),
+                Some(VirtualKeyCode::G) if matches!(input.state, ElementState::Pressed) => state.next_generator(),
+                Some(VirtualKeyCode::C) if matches!(input.state, ElementState::Pressed) => state.print_code(),
+                Some(VirtualKeyCode::Z) if matches!(input.state, ElementState::Pressed) => state.undo(),
+                Some(VirtualKeyCode::Y) if matches!(input.state, ElementState::Pressed) => state.redo(),
+                Some(VirtualKeyCode::B) if matches!(input.state, ElementState::Pressed) => state.bookmark(),
+                Some(VirtualKeyCode::F) if matches!(input.state, ElementState::Pressed) => state.toggle_rdf(),
+                Some(VirtualKeyCode::K) if matches!(input.state, ElementState::Pressed) => state.save_rdf(),
+                Some(VirtualKeyCode::N) if matches!(input.state, ElementState::Pressed) => state.next_kernel(),
+                Some(VirtualKeyCode::M) if matches!(input.state, ElementState::Pressed) => state.next_friction(),
+                Some(VirtualKeyCode::H) if matches!(input.state, ElementState::Pressed) => state.toggle_thermostat(),
+                Some(VirtualKeyCode::X) if matches!(input.state, ElementState::Pressed) => state.next_contact(),
+                Some(VirtualKeyCode::V) if matches!(input.state, ElementState::Pressed) => state.toggle_kernels(),
+                Some(VirtualKeyCode::L) if matches!(input.state, ElementState::Pressed) => state.toggle_population(),
+                Some(VirtualKeyCode::E) if matches!(input.state, ElementState::Pressed) => state.toggle_editor(),
+                Some(VirtualKeyCode::LBracket) if matches!(input.state, ElementState::Pressed) => state.edit_next_pair(-1),
+                Some(VirtualKeyCode::RBracket) if matches!(input.state, ElementState::Pressed) => state.edit_next_pair(1),
+                Some(VirtualKeyCode::O) if matches!(input.state, ElementState::Pressed) => state.save_curves(),
+                Some(VirtualKeyCode::T) if matches!(input.state, ElementState::Pressed) => state.toggle_recording(),
+                Some(VirtualKeyCode::Left) if matches!(input.state, ElementState::Pressed) => state.seek(|r| r.step(-1)),
+                Some(VirtualKeyCode::Right) if matches!(input.state, ElementState::Pressed) => state.seek(|r| r.step(1)),
+                Some(VirtualKeyCode::Comma) if matches!(input.state, ElementState::Pressed) => state.seek(|r| r.scrub(-SCRUB_STEP)),
+                Some(VirtualKeyCode::Period) if matches!(input.state, ElementState::Pressed) => state.seek(|r| r.scrub(SCRUB_STEP)),
+                Some(VirtualKeyCode::Back) if matches!(input.state, ElementState::Pressed) => state.seek(|r| r.reverse()),
+                Some(VirtualKeyCode::Minus) if matches!(input.state, ElementState::Pressed) => state.scale_param(1.0 / PARAM_STEP),
+                Some(VirtualKeyCode::Equals) if matches!(input.state, ElementState::Pressed) => state.scale_param(PARAM_STEP),
+                Some(k @ (VirtualKeyCode::Key1 | VirtualKeyCode::Key2 | VirtualKeyCode::Key3 | VirtualKeyCode::Key4 | VirtualKeyCode::Key5 | VirtualKeyCode::Key6 | VirtualKeyCode::Key7 | VirtualKeyCode::Key8))
+                    if matches!(input.state, ElementState::Pressed) => state.select_param(k as usize - VirtualKeyCode::Key1 as usize),
                 Some(k) => state.keys[k as usize] = match input.state {
                     ElementState::Pressed => true,
                     ElementState::Released => false,
@@ -132,7 +306,7 @@ fn main() {
 
 impl State {
     // Creating some of the wgpu types requires async code
-    async fn new(window: Window) -> Self {
+    async fn new(window: Window, setup: Setup, replay: Option<Replay>, backend: Backend, neighbours: Neighbours) -> Self {
         let size = window.inner_size();
 
         // The instance is a handle to our GPU
@@ -174,6 +348,7 @@ impl State {
             )
             .await
             .unwrap();
+        let (device, queue) = (Arc::new(device), Arc::new(queue));
 
         let surface_caps = surface.get_capabilities(&adapter);
         // Shader code in this tutorial assumes an sRGB surface texture. Using a different
@@ -207,28 +382,21 @@ impl State {
             scale: 1.0 / ZOOM,
         };
 
-        let mut circles = Vec::with_capacity(CIRCLES);
-        for _ in 0..CIRCLES {
-            circles.push(Circle {
-                pos: [
-                    (random::<f32>() - 0.5) * 2.0 * ZOOM,
-                    (random::<f32>() - 0.5) * 2.0 * ZOOM,
-                ],
-                vel: [(random::<f32>() - 0.5) * 2.0, (random::<f32>() - 0.5) * 5.0],
-                rad: 0.125,
-                color: (random::<f32>() * NUM_COLORS as f32) as i32,
-            });
-        }
-
-        let mut constraints: [[[f32; 4]; NUM_COLORS as usize]; NUM_COLORS as usize] = std::default::Default::default();
-        for i in 0..NUM_COLORS as usize {
-            let mut attractions: [[f32; 4]; NUM_COLORS as usize] = std::default::Default::default();
-            for j in 0..NUM_COLORS as usize {
-                attractions[j] = [(random::<f32>() - 0.5), 0.0, 0.0, 0.0];
+        let history = History::new(setup.clone(), HISTORY_LEN);
+        let Setup { rules, params, seed } = setup;
+        let species = rules.species() as u32;
+        let circles = match &replay {
+            Some(replay) => {
+                let mut circles = replay.current().circles.clone();
+                circles.resize(replay.max_particles(), Circle::default());
+                circles
             }
-            constraints[i] = attractions;
-        }
-        //let constraints = CONSTRAINTS;
+            None => Circle::scatter(CIRCLES, species, ZOOM, seed),
+        };
+        let count = match &replay {
+            Some(replay) => replay.current().circles.len() as u32,
+            None => circles.len() as u32,
+        };
 
         let camera_buffer = device.create_buffer_init(
             &wgpu::util::BufferInitDescriptor {
@@ -244,13 +412,6 @@ impl State {
                 usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             }
         );
-        let dt_buffer = device.create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: Some("dt buffer"),
-                contents: bytemuck::cast_slice(&[0.0f32]),
-                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            }
-        );
 
         let colors_tex_size = wgpu::Extent3d { 
             width: NUM_COLORS,
@@ -286,40 +447,6 @@ impl State {
         );
         let colors_tex_view = colors_tex.create_view(&wgpu::TextureViewDescriptor::default());
 
-        let constraints_tex_size = wgpu::Extent3d { 
-            width: NUM_COLORS,
-            height: NUM_COLORS,
-            depth_or_array_layers: 1,
-        };
-        let constraints_tex = device.create_texture(
-            &wgpu::TextureDescriptor {
-                label: Some("Constraints buffer"),
-                size: constraints_tex_size,
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba32Float,
-                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-                view_formats: &[],
-            }
-        );
-        queue.write_texture(
-            wgpu::ImageCopyTexture {
-                texture: &constraints_tex,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            }, 
-            bytemuck::cast_slice(&constraints), 
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(16 * NUM_COLORS),
-                rows_per_image: Some(NUM_COLORS),
-            }, 
-            constraints_tex_size,
-        );
-        let constraints_tex_view = constraints_tex.create_view(&wgpu::TextureViewDescriptor::default());
-
         let data_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
             address_mode_u: wgpu::AddressMode::Repeat,
             address_mode_v: wgpu::AddressMode::Repeat,
@@ -329,14 +456,6 @@ impl State {
             .. Default::default()
         });
 
-        let circ_buffer = device.create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: Some("Circle Buffer"),
-                contents: bytemuck::cast_slice(&circles),
-                usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
-            }
-        );
-
         let render_uniform_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             entries: &[
                 wgpu::BindGroupLayoutEntry {
@@ -379,63 +498,11 @@ impl State {
             label: Some("uniform_bind_group_layout"),
         });
 
-        let compute_uniform_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Texture { 
-                        sample_type: wgpu::TextureSampleType::Float { filterable: false }, 
-                        view_dimension: wgpu::TextureViewDimension::D2, 
-                        multisampled: false,
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 2,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Texture { 
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true }, 
-                        view_dimension: wgpu::TextureViewDimension::D1, 
-                        multisampled: false,
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 3,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                    count: None,
-                },
-            ],
-            label: Some("compute uniform bind group layout"),
-        });
-
-        let circ_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::COMPUTE | wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer { 
-                        ty: wgpu::BufferBindingType::Storage { read_only: false }, 
-                        has_dynamic_offset: false, 
-                        min_binding_size: None, 
-                    },
-                    count: None,
-                }
-            ],
-            label: Some("circle bind group layout"),
-        });
+        let circ_bind_group_layout = gpu::circle_layout(&device);
+        let (circ_buffer, circ_bind_group) = gpu::circle_buffer(&device, &circ_bind_group_layout, &circles);
+        let bond_bind_group_layout = gpu::bond_layout(&device);
+        let bond_buffer = gpu::bonds_buffer(&device, &[], 0);
+        let bond_bind_group = gpu::bond_bind_group(&device, &bond_bind_group_layout, &bond_buffer);
 
         let render_uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             layout: &render_uniform_bind_group_layout,
@@ -460,40 +527,6 @@ impl State {
             label: Some("uniform_bind_group"),
         });
 
-        let circ_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &circ_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: circ_buffer.as_entire_binding(),
-                }
-            ],
-            label: Some("circ bind group"),
-        });
-
-        let compute_uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("compute uniform bind group"),
-            layout: &compute_uniform_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: dt_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::TextureView(&constraints_tex_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: wgpu::BindingResource::TextureView(&colors_tex_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 3,
-                    resource: wgpu::BindingResource::Sampler(&data_sampler),
-                },
-            ]
-        });
-
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
@@ -541,6 +574,37 @@ impl State {
             multiview: None, 
         });
 
+        let bond_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Bond Pipeline Layout"),
+            bind_group_layouts: &[&render_uniform_bind_group_layout, &circ_bind_group_layout, &bond_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let bond_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Bond Pipeline"),
+            layout: Some(&bond_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_bond",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_bond",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::LineList,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
         let vertex_buffer = device.create_buffer_init(
             &wgpu::util::BufferInitDescriptor {
                 label: Some("Vertex Buffer"),
@@ -549,20 +613,17 @@ impl State {
             }
         );
 
-        let compute_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("Compute pipeline descriptinator"),
-            bind_group_layouts: &[
-                &compute_uniform_bind_group_layout,
-                &circ_bind_group_layout,
-            ],
-            push_constant_ranges: &[],
-        });
-        let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-            label: Some("Compute pipeline"),
-            layout: Some(&compute_pipeline_layout),
-            module: &shader,
-            entry_point: &"compute_main",
-        });
+        let mut sim: Box<dyn SimBackend> = match backend {
+            Backend::Gpu => Box::new(GpuSim::new(device.clone(), queue.clone(), &circles, &rules, &params).unwrap_or_else(|e| {
+                eprintln!("can't start the gpu backend: {}", e);
+                std::process::exit(1);
+            })),
+            _ => Box::new(CpuSim::for_backend(backend, &circles, &rules, &params, neighbours).expect("CPU backend")),
+        };
+        sim.set_seed(seed);
+
+        let overlay = Overlay::new(&device, config.format);
+        let rdf = Rdf::new(species as usize, RDF_BINS, 2.0 * params.rmax, params.world_size);
 
         Self {
             pause: true,
@@ -574,20 +635,54 @@ impl State {
             config,
             size,
             render_pipeline,
+            bond_pipeline,
             vertex_buffer,
             
             camera,
             last_frame: Instant::now(),
+            fps_frames: 0,
+            fps_since: Instant::now(),
+            counts: Vec::new(),
+            population: Population::new(POPULATION_SAMPLES),
+            show_population: false,
             camera_buffer,
             size_buffer,
             render_uniform_bind_group,
             
-            dt_buffer,
-            compute_uniform_bind_group,
+            params,
+            sim,
             circ_buffer,
-            constraints_tex,
+            circ_bind_group_layout,
             circ_bind_group,
-            compute_pipeline,
+            count,
+            bond_buffer,
+            bond_bind_group_layout,
+            bond_bind_group,
+            bond_count: 0,
+
+            species,
+            seed,
+            rules,
+            preset: 0,
+            generator: 0,
+            history,
+            selected_param: 0,
+
+            overlay,
+            frame: 0,
+            show_rdf: false,
+            show_kernels: false,
+            editing: None,
+            drawing: false,
+            drawn: None,
+            cursor: [0.0; 2],
+            rdf,
+            rdf_shown: None,
+
+            step: 0,
+            time: 0.0,
+            recorder: None,
+            replay,
 
             keys: [false; 256],
         }
@@ -606,14 +701,37 @@ impl State {
         }
     }
 
-    fn input(&mut self, event: &WindowEvent) {}
+    fn input(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = [
+                    position.x as f32 / self.size.width as f32 * 2.0 - 1.0,
+                    1.0 - position.y as f32 / self.size.height as f32 * 2.0,
+                ];
+                if self.drawing {
+                    self.draw_curve();
+                }
+            }
+            WindowEvent::MouseInput { state: ElementState::Pressed, button: MouseButton::Left, .. } if self.editing.is_some() => {
+                self.drawing = true;
+                self.draw_curve();
+            }
+            WindowEvent::MouseInput { state: ElementState::Released, button: MouseButton::Left, .. } if self.drawing => {
+                self.drawing = false;
+                if self.drawn.take().is_some() {
+                    self.history.push(self.setup());
+                }
+            }
+            _ => {}
+        }
+    }
 
     fn update(&mut self) {
-        let dt = f32::min(0.005, self.last_frame.elapsed().as_secs_f32());
+        let elapsed = self.last_frame.elapsed().as_secs_f32();
+        let dt = f32::min(sim::DT, elapsed);
 
         self.queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&self.camera.transform()));
         self.queue.write_buffer(&self.size_buffer, 0, bytemuck::cast_slice(&[self.size.width, self.size.height]));
-        self.queue.write_buffer(&self.dt_buffer, 0, bytemuck::cast_slice(&[dt]));
 
         if self.keys[VirtualKeyCode::W as usize] { self.camera.pos[1] -= CAMERA_MOVE_SPEED * dt}
         if self.keys[VirtualKeyCode::A as usize] { self.camera.pos[0] += CAMERA_MOVE_SPEED * dt}
@@ -623,30 +741,87 @@ impl State {
         if self.keys[VirtualKeyCode::Down as usize] { self.camera.scale *= 1.0 - CAMERA_ZOOM_SPEED * dt}
         self.camera.scale = self.camera.scale.clamp(0.0, 1.0);
 
-        //println!("{}", self.last_frame.elapsed().as_secs_f32().recip());
         self.last_frame = Instant::now();
+        self.fps_frames += 1;
+        let since = self.fps_since.elapsed().as_secs_f32();
+        if since >= 1.0 {
+            self.counts = metrics::species_counts(&self.download_circles(), self.species as usize);
+            self.population.record(self.counts.clone());
+            let counts: Vec<String> = self.counts.iter().map(|n| n.to_string()).collect();
+            self.window.set_title(&format!("physics - {:.0} fps - {}", self.fps_frames as f32 / since, counts.join(" / ")));
+            self.fps_frames = 0;
+            self.fps_since = Instant::now();
+        }
+
+        if let Some(replay) = &mut self.replay {
+            // as far as a live run would get this frame
+            if !self.pause && !replay.advance(dt) {
+                self.pause = true;
+            }
+            self.show_replay_frame();
+            return;
+        }
 
         if self.pause { return; }
 
-        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-            label: Some("Compute encoder"),
-        });
-        
-        {
-            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
-                label: Some("Compute pass"),
-            });
-            compute_pass.set_pipeline(&self.compute_pipeline);
-            compute_pass.set_bind_group(0, &self.compute_uniform_bind_group, &[]);
-            compute_pass.set_bind_group(1, &self.circ_bind_group, &[]);
-            compute_pass.dispatch_workgroups(CIRCLES as u32, 1, 1);
+        self.sim.set_params(&self.params);
+        self.sim.set_dt(dt);
+        self.sim.step(1);
+        self.copy_for_drawing();
+
+        self.step += 1;
+        self.time += dt as f64;
+        if self.recorder.is_some() && self.step % RECORD_EVERY as u64 == 0 {
+            let circles = self.download_circles();
+            let (step, time) = (self.step, self.time);
+            if let Some(Err(e)) = self.recorder.as_mut().map(|r| r.record(step, time, &circles)) {
+                eprintln!("recording failed, stopping: {}", e);
+                self.recorder = None;
+            }
         }
 
-        self.queue.submit(std::iter::once(encoder.finish()));
+        self.frame += 1;
+        if self.show_rdf && self.frame % RDF_SAMPLE_EVERY == 0 {
+            // shells sized for params since edited would mix two systems
+            if self.rdf.r_max() != 2.0 * self.params.rmax || self.rdf.world_size() != self.params.world_size {
+                self.rdf = Rdf::new(self.species as usize, RDF_BINS, 2.0 * self.params.rmax, self.params.world_size);
+                self.rdf_shown = None;
+            }
+            let circles = self.download_circles();
+            self.rdf.add(&circles);
+            if self.rdf.frames() >= RDF_WINDOW {
+                let fresh = Rdf::new(self.species as usize, RDF_BINS, 2.0 * self.params.rmax, self.params.world_size);
+                self.rdf_shown = Some(std::mem::replace(&mut self.rdf, fresh));
+            }
+        }
 
         //wgpu::util::DownloadBuffer::read_buffer(&self.device, &self.queue, &self.circ_buffer.slice(..), |r| {if let Ok(buf) = r {println!("{:?}", bytemuck::from_bytes::<[Circle; CIRCLES]>(&buf));}});
     }
 
+    // births and deaths change the count, and can outgrow the buffer, as
+    // bonds forming can theirs
+    fn copy_for_drawing(&mut self) {
+        if self.sim.gpu().is_some() {
+            return;
+        }
+        let circles = self.sim.download();
+        if std::mem::size_of_val(circles.as_slice()) as u64 > self.circ_buffer.size() {
+            (self.circ_buffer, self.circ_bind_group) = gpu::circle_buffer(&self.device, &self.circ_bind_group_layout, &circles);
+        } else {
+            self.queue.write_buffer(&self.circ_buffer, 0, bytemuck::cast_slice(&circles));
+        }
+        self.count = circles.len() as u32;
+        let bonds = self.sim.bonds();
+        let bytes = gpu::bond_bytes(&bonds);
+        if bytes.len() as u64 > self.bond_buffer.size() {
+            self.bond_buffer = gpu::bonds_buffer(&self.device, &bonds, bonds.len());
+            self.bond_bind_group = gpu::bond_bind_group(&self.device, &self.bond_bind_group_layout, &self.bond_buffer);
+        } else {
+            self.queue.write_buffer(&self.bond_buffer, 0, &bytes);
+        }
+        self.bond_count = bonds.len() as u32;
+    }
+
     fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
         let output = self.surface.get_current_texture()?;
         let view = output
@@ -657,6 +832,25 @@ impl State {
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("Render Encoder"),
             });
+
+        self.overlay.clear();
+        if !self.rules.fields().is_empty() || !self.rules.obstacles().is_empty() {
+            self.plot_environment();
+        }
+        self.plot_counts();
+        if self.show_rdf {
+            self.plot_rdf();
+        }
+        if self.show_kernels {
+            self.plot_kernels();
+        }
+        if self.show_population {
+            self.plot_population();
+        }
+        if self.editing.is_some() {
+            self.plot_editor();
+        }
+
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
@@ -675,11 +869,30 @@ impl State {
                 })],
                 depth_stencil_attachment: None,
             });
-            render_pass.set_pipeline(&self.render_pipeline);
             render_pass.set_bind_group(0, &self.render_uniform_bind_group, &[]);
-            render_pass.set_bind_group(1, &self.circ_bind_group, &[]);
+            let circ_bind_group = match self.sim.gpu() {
+                Some(gpu) => &gpu.circ_bind_group,
+                None => &self.circ_bind_group,
+            };
+            render_pass.set_bind_group(1, circ_bind_group, &[]);
+            let (bond_bind_group, bonds) = match self.sim.gpu() {
+                Some(gpu) => (&gpu.bond_bind_group, gpu.bond_capacity()),
+                None => (&self.bond_bind_group, self.bond_count),
+            };
+            if bonds > 0 {
+                render_pass.set_pipeline(&self.bond_pipeline);
+                render_pass.set_bind_group(2, bond_bind_group, &[]);
+                render_pass.draw(0..2, 0..bonds);
+            }
+            render_pass.set_pipeline(&self.render_pipeline);
             render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            render_pass.draw(0..6, 0..CIRCLES as u32);
+            let instances = match self.sim.gpu() {
+                Some(gpu) => gpu.capacity(),
+                None => self.count,
+            };
+            render_pass.draw(0..6, 0..instances);
+
+            self.overlay.draw(&self.device, &self.queue, &mut render_pass);
         }
 
         // submit will accept anything that implements IntoIter
@@ -689,37 +902,465 @@ impl State {
         Ok(())
     }
 
+    fn download_circles(&self) -> Vec<Circle> {
+        self.sim.download()
+    }
+
+    fn toggle_recording(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            let frames = recorder.frames();
+            match recorder.finish() {
+                Ok(()) => println!("recording stopped after {} frames", frames),
+                Err(e) => eprintln!("can't finish recording: {}", e),
+            }
+            return;
+        }
+        if self.replay.is_some() {
+            return;
+        }
+
+        let stamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let path = format!("recordings/run-{}.traj", stamp);
+        let code = match code::encode(&self.setup()) {
+            Ok(code) => code,
+            Err(e) => {
+                eprintln!("can't record to {}: {}", path, e);
+                return;
+            }
+        };
+        let header = trajectory::Header {
+            every: RECORD_EVERY,
+            world_size: self.params.world_size,
+            code,
+        };
+        match Recorder::create(&path, &header) {
+            Ok(recorder) => {
+                println!("recording to {}", path);
+                self.recorder = Some(recorder);
+            }
+            Err(e) => eprintln!("can't record to {}: {}", path, e),
+        }
+    }
+
+    fn seek(&mut self, f: impl FnOnce(&mut Replay)) {
+        if let Some(replay) = &mut self.replay {
+            f(replay);
+            println!(
+                "frame {}/{} (step {}){}",
+                replay.index() + 1,
+                replay.frame_count(),
+                replay.current().step,
+                if replay.reversed() { " reversed" } else { "" },
+            );
+        }
+    }
+
+    fn show_replay_frame(&mut self) {
+        let Some(replay) = &self.replay else { return };
+        let circles = &replay.current().circles;
+        if let Err(e) = self.sim.upload(circles) {
+            eprintln!("can't show frame {}: {}", replay.index() + 1, e);
+            return;
+        }
+        self.count = circles.len() as u32;
+        self.copy_for_drawing();
+    }
+
+    fn toggle_rdf(&mut self) {
+        self.show_rdf = !self.show_rdf;
+        println!("g(r) overlay {}", if self.show_rdf { "on" } else { "off" });
+    }
+
+    fn save_rdf(&self) {
+        let Some(rdf) = &self.rdf_shown else {
+            println!("no g(r) yet, turn the overlay on with F and let it run");
+            return;
+        };
+        let stamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let path = format!("rdf-{}.csv", stamp);
+        match rdf.write_csv(&path) {
+            Ok(()) => println!("saved {}", path),
+            Err(e) => eprintln!("can't save {}: {}", path, e),
+        }
+    }
+
+    // g(r) of each species around itself in its own colour and of everything
+    // in white, with the kernel's rmin and rmax marked in grey
+    fn plot_rdf(&mut self) {
+        let (min, max) = ([0.35, -0.95], [0.95, -0.45]);
+        let grey = [0.5, 0.5, 0.5, 1.0];
+        let y_max = 5.0;
+
+        self.overlay.rect(min, max, grey);
+        let ideal = min[1] + (max[1] - min[1]) / y_max;
+        self.overlay.line([min[0], ideal], [max[0], ideal], [0.3, 0.3, 0.3, 1.0]);
+
+        let Some(rdf) = &self.rdf_shown else { return };
+        for r in [self.params.rmin * self.params.rmax, self.params.rmax] {
+            let x = min[0] + r / rdf.r_max() * (max[0] - min[0]);
+            self.overlay.line([x, min[1]], [x, max[1]], grey);
+        }
+        for (a, &color) in COLORS.iter().enumerate().take(self.species as usize) {
+            self.overlay.plot(min, max, &rdf.pair(a, a), y_max, overlay::rgba(color));
+        }
+        self.overlay.plot(min, max, &rdf.total(), y_max, [1.0; 4]);
+    }
+
+    // a bar along the top split between the species by their share of the
+    // particles, each part outlined in the species' colour
+    fn plot_counts(&mut self) {
+        let (min, max) = ([-0.95, 0.92], [0.95, 0.96]);
+        let total = self.counts.iter().sum::<usize>();
+        if total == 0 {
+            return;
+        }
+        let mut x = min[0];
+        for (&n, &color) in self.counts.iter().zip(COLORS.iter()) {
+            let width = n as f32 / total as f32 * (max[0] - min[0]);
+            if n > 0 {
+                self.overlay.rect([x, min[1]], [x + width, max[1]], overlay::rgba(color));
+            }
+            x += width;
+        }
+    }
+
+    // where a point in the world is drawn, as vs_main places particles
+    fn to_clip(&self, pos: [f32; 2]) -> [f32; 2] {
+        let (t, s) = (self.camera.pos, self.camera.scale);
+        let aspect = self.size.height as f32 / self.size.width as f32;
+        [s * (pos[0] * aspect + t[0]), s * (pos[1] + t[1])]
+    }
+
+    fn to_world(&self, clip: [f32; 2]) -> [f32; 2] {
+        let (t, s) = (self.camera.pos, self.camera.scale);
+        let aspect = self.size.height as f32 / self.size.width as f32;
+        [(clip[0] / s - t[0]) / aspect, clip[1] / s - t[1]]
+    }
+
+    // faint arrows on a grid across the window along the force the fields
+    // put on a unit mass at rest, the longest a grid cell long, and the
+    // obstacles' outlines
+    fn plot_environment(&mut self) {
+        const CELLS: usize = 16;
+        const SEGMENTS: usize = 32;
+        let cell = 2.0 / CELLS as f32;
+        let aspect = self.size.height as f32 / self.size.width as f32;
+        let arrows: Vec<([f32; 2], [f32; 2])> = (0..CELLS * CELLS)
+            .map(|k| {
+                let clip = [-1.0 + (k % CELLS) as f32 * cell + cell / 2.0, -1.0 + (k / CELLS) as f32 * cell + cell / 2.0];
+                let f = fields::force(self.rules.fields(), self.to_world(clip), [0.0, 0.0], 1.0);
+                (clip, [f[0] * aspect, f[1]])
+            })
+            .collect();
+        let longest = arrows.iter().map(|(_, f)| (f[0] * f[0] + f[1] * f[1]).sqrt()).fold(0.0, f32::max);
+        if longest > 0.0 {
+            let k = 0.8 * cell / longest;
+            let faint = [0.4, 0.4, 0.6, 0.5];
+            for (from, f) in arrows {
+                let to = [from[0] + f[0] * k, from[1] + f[1] * k];
+                self.overlay.line(from, to, faint);
+                // a short tick back along the arrow from its tip, turned a
+                // little, for the head
+                let head = [(from[0] - to[0]) * 0.3, (from[1] - to[1]) * 0.3];
+                self.overlay.line(to, [to[0] + head[0] - head[1] * 0.5, to[1] + head[1] + head[0] * 0.5], faint);
+            }
+        }
+        let grey = [0.7, 0.7, 0.7, 1.0];
+        for &obstacle in self.rules.obstacles() {
+            match obstacle {
+                Obstacle::Circle { at, radius } => {
+                    let point = |k: usize| {
+                        let angle = k as f32 / SEGMENTS as f32 * std::f32::consts::TAU;
+                        self.to_clip([at[0] + radius * angle.cos(), at[1] + radius * angle.sin()])
+                    };
+                    let points: Vec<[f32; 2]> = (0..=SEGMENTS).map(point).collect();
+                    for w in points.windows(2) {
+                        self.overlay.line(w[0], w[1], grey);
+                    }
+                }
+                Obstacle::Rect { min, max } => {
+                    let (min, max) = (self.to_clip(min), self.to_clip(max));
+                    self.overlay.rect(min, max, grey);
+                }
+            }
+        }
+    }
+
+    fn toggle_population(&mut self) {
+        self.show_population = !self.show_population;
+        println!("population chart {}", if self.show_population { "on" } else { "off" });
+    }
+
+    // each species' count over the last POPULATION_SAMPLES seconds in its
+    // colour, the top of the box a little over the largest count
+    fn plot_population(&mut self) {
+        let (min, max) = ([0.35, 0.35], [0.95, 0.85]);
+        self.overlay.rect(min, max, [0.5, 0.5, 0.5, 1.0]);
+        let y_max = (self.population.peak() as f32 * 1.1).max(1.0);
+        for (a, &color) in COLORS.iter().enumerate().take(self.species as usize) {
+            self.overlay.plot(min, max, &self.population.series(a), y_max, overlay::rgba(color));
+        }
+    }
+
+    fn toggle_kernels(&mut self) {
+        self.show_kernels = !self.show_kernels;
+        println!("kernel plot {}", if self.show_kernels { "on" } else { "off" });
+    }
+
+    // force against distance out to the cutoff: the unit-rule shape of every
+    // kernel in use in grey, and each species' force on its own kind in its
+    // colour
+    fn plot_kernels(&mut self) {
+        const SAMPLES: usize = 100;
+        let (min, max) = ([-0.95, -0.95], [-0.35, -0.45]);
+        let grey = [0.5, 0.5, 0.5, 1.0];
+        let y_max = 2.0;
+        // forces are signed, so zero sits in the middle of the box
+        let shifted = |ys: Vec<f32>| ys.into_iter().map(|y| y + y_max).collect::<Vec<_>>();
+
+        self.overlay.rect(min, max, grey);
+        let zero = (min[1] + max[1]) / 2.0;
+        self.overlay.line([min[0], zero], [max[0], zero], [0.3, 0.3, 0.3, 1.0]);
+        for d in [self.params.rmin, 1.0] {
+            let x = min[0] + d * (max[0] - min[0]);
+            self.overlay.line([x, min[1]], [x, max[1]], grey);
+        }
+
+        let n = self.species as usize;
+        let mut used: Vec<Kernel> = (0..n * n).map(|k| self.rules.kernel(k / n, k % n)).collect();
+        used.sort_by_key(|k| k.index());
+        used.dedup();
+        for kernel in used.into_iter().filter(|&k| k != Kernel::Tabulated) {
+            self.overlay.plot(min, max, &shifted(kernel.curve(1.0, &self.params, SAMPLES)), 2.0 * y_max, grey);
+        }
+        for (a, &color) in COLORS.iter().enumerate().take(n) {
+            let curve = (0..SAMPLES)
+                .map(|k| self.rules.force(a, a, k as f32 / (SAMPLES - 1) as f32, &self.params))
+                .collect();
+            self.overlay.plot(min, max, &shifted(curve), 2.0 * y_max, overlay::rgba(color));
+        }
+    }
+
+    fn toggle_editor(&mut self) {
+        self.editing = match self.editing {
+            Some(_) => None,
+            None => Some((0, 0)),
+        };
+        match self.editing {
+            Some((a, b)) => println!("editing the {}-{} force curve, [ and ] pick the pair", a, b),
+            None => println!("curve editor off"),
+        }
+    }
+
+    fn edit_next_pair(&mut self, by: isize) {
+        let Some((a, b)) = self.editing else { return };
+        let n = self.species as usize;
+        let k = (a * n + b) as isize + by;
+        let k = k.rem_euclid((n * n) as isize) as usize;
+        self.editing = Some((k / n, k % n));
+        println!("editing the {}-{} force curve ({})", k / n, k % n, self.rules.kernel(k / n, k % n).name());
+    }
+
+    // sets the curve point under the cursor, and the points between it and
+    // the last one set while dragging; the pair switches to its tabulated
+    // curve, starting from the shape it had
+    fn draw_curve(&mut self) {
+        let Some((a, b)) = self.editing else { return };
+        let [x, y] = self.cursor;
+        if !(EDITOR_MIN[0]..=EDITOR_MAX[0]).contains(&x) || !(EDITOR_MIN[1]..=EDITOR_MAX[1]).contains(&y) {
+            return;
+        }
+        let t = (x - EDITOR_MIN[0]) / (EDITOR_MAX[0] - EDITOR_MIN[0]);
+        let k = (t * (curves::SAMPLES - 1) as f32).round() as usize;
+        let force = ((y - EDITOR_MIN[1]) / (EDITOR_MAX[1] - EDITOR_MIN[1]) * 2.0 - 1.0) * EDITOR_RANGE;
+
+        let mut curve: Vec<f32> = match self.rules.kernel(a, b) {
+            Kernel::Tabulated => self.rules.curve(a, b).to_vec(),
+            _ => (0..curves::SAMPLES)
+                .map(|k| self.rules.force(a, b, curves::distance(k), &self.params))
+                .collect(),
+        };
+        let (from, from_force) = self.drawn.unwrap_or((k, force));
+        let (lo, hi) = (from.min(k), from.max(k));
+        for (i, point) in curve.iter_mut().enumerate().take(hi + 1).skip(lo) {
+            let s = if hi == lo { 1.0 } else { (i as f32 - from as f32) / (k as f32 - from as f32) };
+            *point = from_force + (force - from_force) * s;
+        }
+        self.rules.set_curve(a, b, &curve);
+        self.rules.set_kernel(a, b, Kernel::Tabulated);
+        self.sim.set_rules(&self.rules);
+        self.drawn = Some((k, force));
+    }
+
+    // the edited pair's force curve, zero across the middle and rmin and 1
+    // marked in grey
+    fn plot_editor(&mut self) {
+        let Some((a, b)) = self.editing else { return };
+        let grey = [0.5, 0.5, 0.5, 1.0];
+        self.overlay.rect(EDITOR_MIN, EDITOR_MAX, grey);
+        let zero = (EDITOR_MIN[1] + EDITOR_MAX[1]) / 2.0;
+        self.overlay.line([EDITOR_MIN[0], zero], [EDITOR_MAX[0], zero], [0.3, 0.3, 0.3, 1.0]);
+        for d in [self.params.rmin, 1.0] {
+            let x = EDITOR_MIN[0] + d * (EDITOR_MAX[0] - EDITOR_MIN[0]);
+            self.overlay.line([x, EDITOR_MIN[1]], [x, EDITOR_MAX[1]], grey);
+        }
+        let curve: Vec<f32> = (0..curves::SAMPLES)
+            .map(|k| self.rules.force(a, b, curves::distance(k), &self.params) + EDITOR_RANGE)
+            .collect();
+        self.overlay.plot(EDITOR_MIN, EDITOR_MAX, &curve, 2.0 * EDITOR_RANGE, overlay::rgba(COLORS[a]));
+    }
+
+    fn save_curves(&self) {
+        let stamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let path = format!("curves-{}.csv", stamp);
+        match curves::save_csv(&path, &self.rules) {
+            Ok(()) => println!("saved {}", path),
+            Err(e) => eprintln!("can't save {}: {}", path, e),
+        }
+    }
+
+    // one shape for every pair, the next one after the current global shape
+    fn next_kernel(&mut self) {
+        // tabulated is left to the curve editor
+        let analytic = &Kernel::ALL[..Kernel::ALL.len() - 1];
+        let current = self.rules.global_kernel().map_or(0, |k| k.index() as usize + 1);
+        let kernel = analytic[current % analytic.len()];
+        println!("kernel: {}", kernel.name());
+        let mut rules = self.rules.clone();
+        rules.set_all_kernels(kernel);
+        self.set_rules(rules);
+    }
+
+    fn next_friction(&mut self) {
+        let current = self.params.friction().index() as usize;
+        let friction = Friction::ALL[(current + 1) % Friction::ALL.len()];
+        println!("friction: {}", friction.name());
+        self.params.set_friction(friction);
+        self.history.push(self.setup());
+    }
+
+    fn next_contact(&mut self) {
+        let current = self.params.contact().index() as usize;
+        let contact = Contact::ALL[(current + 1) % Contact::ALL.len()];
+        println!("contact: {}", contact.name());
+        self.params.set_contact(contact);
+        self.history.push(self.setup());
+    }
+
+    fn toggle_thermostat(&mut self) {
+        self.params.set_thermostat(!self.params.thermostat());
+        println!("thermostat {} at temperature {}", if self.params.thermostat() { "on" } else { "off" }, self.params.temperature);
+        self.history.push(self.setup());
+    }
+
     fn randomize_constraints(&mut self) {
         println!("r pressed");
-        let mut constraints: [[[f32; 4]; NUM_COLORS as usize]; NUM_COLORS as usize] = std::default::Default::default();
-        for i in 0..NUM_COLORS as usize {
-            let mut attractions: [[f32; 4]; NUM_COLORS as usize] = std::default::Default::default();
-            for j in 0..NUM_COLORS as usize {
-                attractions[j] = [(random::<f32>() - 0.5) * 2.0, 0.0, 0.0, 0.0];
+        self.set_matrix(Rules::random(self.species as usize, 1.0, &mut rand::thread_rng()));
+    }
+
+    fn next_preset(&mut self) {
+        let preset = &PRESETS[self.preset];
+        println!("preset: {}", preset.name);
+        self.set_matrix(preset.build(self.species as usize));
+        self.preset = (self.preset + 1) % PRESETS.len();
+    }
+
+    fn next_generator(&mut self) {
+        let generator = Generator::ALL[self.generator];
+        println!("generator: {}", generator.name());
+        self.set_matrix(generator.generate(self.species as usize, &mut rand::thread_rng()));
+        self.generator = (self.generator + 1) % Generator::ALL.len();
+    }
+
+    // a new rule matrix with the kernels of the current one
+    fn set_matrix(&mut self, mut rules: Rules) {
+        rules.copy_physics(&self.rules);
+        self.set_rules(rules);
+    }
+
+    fn setup(&self) -> Setup {
+        Setup {
+            seed: self.seed,
+            rules: self.rules.clone(),
+            params: self.params,
+        }
+    }
+
+    fn print_code(&self) {
+        match code::encode(&self.setup()) {
+            Ok(code) => println!("{}", code),
+            Err(e) => eprintln!("can't make a code for this setup: {}", e),
+        }
+    }
+
+    fn set_rules(&mut self, rules: Rules) {
+        self.sim.set_rules(&rules);
+        self.rules = rules;
+        self.history.push(self.setup());
+    }
+
+    fn select_param(&mut self, i: usize) {
+        self.selected_param = i;
+        println!("{} = {}", Params::NAMES[i], self.params.field_mut(i));
+    }
+
+    fn scale_param(&mut self, factor: f32) {
+        let i = self.selected_param;
+        let v = self.params.field_mut(i);
+        // the temperature starts at zero, which scaling alone can't leave
+        *v = if *v == 0.0 && factor > 1.0 { PARAM_FLOOR } else { *v * factor };
+        println!("{} = {}", Params::NAMES[i], self.params.field_mut(i));
+        self.history.push(self.setup());
+    }
+
+    fn undo(&mut self) {
+        match self.history.undo() {
+            Some(setup) => {
+                let setup = setup.clone();
+                self.restore(setup);
             }
-            constraints[i] = attractions;
+            None => println!("nothing to undo"),
         }
+    }
 
-        let constraints_tex_size = wgpu::Extent3d { 
-            width: NUM_COLORS,
-            height: NUM_COLORS,
-            depth_or_array_layers: 1,
-        };
-        self.queue.write_texture(
-            wgpu::ImageCopyTexture {
-                texture: &self.constraints_tex,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            }, 
-            bytemuck::cast_slice(&constraints), 
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(16 * NUM_COLORS),
-                rows_per_image: Some(NUM_COLORS),
-            }, 
-            constraints_tex_size,
-        );
+    fn redo(&mut self) {
+        match self.history.redo() {
+            Some(setup) => {
+                let setup = setup.clone();
+                self.restore(setup);
+            }
+            None => println!("nothing to redo"),
+        }
+    }
+
+    // applies a history entry without recording it again
+    fn restore(&mut self, setup: Setup) {
+        self.sim.set_rules(&setup.rules);
+        self.rules = setup.rules;
+        self.params = setup.params;
+        let (at, len) = self.history.position();
+        println!("history {}/{}", at, len);
+    }
+
+    fn bookmark(&self) {
+        let stamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let path = format!("presets/bookmark-{}.toml", stamp);
+        match preset::save(&path, self.history.current()) {
+            Ok(()) => println!("saved {}", path),
+            Err(e) => eprintln!("can't save {}: {}", path, e),
+        }
     }
 }
 
@@ -0,0 +1,199 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::circle::Circle;
+use crate::grid::Grid;
+use crate::sim::{length, sub};
+
+// particles closer than this are linked into the same cluster
+pub const LINK_DISTANCE: f32 = 1.0;
+// smaller groups are counted as loose particles rather than clusters
+pub const MIN_CLUSTER: usize = 8;
+
+/// Connected components of the "closer than `link`" graph.
+pub struct Clusters {
+    pub labels: Vec<usize>,
+    pub sizes: Vec<usize>,
+}
+
+impl Clusters {
+    pub fn find(circles: &[Circle], link: f32) -> Self {
+        let positions: Vec<[f32; 2]> = circles.iter().map(|c| c.pos).collect();
+        let grid = Grid::new(&positions, link);
+
+        let mut parent: Vec<usize> = (0..circles.len()).collect();
+        for (i, c) in circles.iter().enumerate() {
+            grid.for_each_near(c.pos, |j| {
+                if j > i && length(sub(c.pos, circles[j].pos)) < link {
+                    let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                    parent[a] = b;
+                }
+            });
+        }
+
+        let mut ids = HashMap::new();
+        let mut labels = Vec::with_capacity(circles.len());
+        let mut sizes = Vec::new();
+        for i in 0..circles.len() {
+            let r = root(&mut parent, i);
+            let id = *ids.entry(r).or_insert_with(|| {
+                sizes.push(0);
+                sizes.len() - 1
+            });
+            sizes[id] += 1;
+            labels.push(id);
+        }
+
+        Self { labels, sizes }
+    }
+
+    pub fn count(&self, min_size: usize) -> usize {
+        self.sizes.iter().filter(|&&s| s >= min_size).count()
+    }
+}
+
+fn root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// Summary of how structured a run looks, measured between two snapshots of
+/// the same particles.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Metrics {
+    /// clusters of at least `MIN_CLUSTER` particles in the later snapshot
+    pub clusters: usize,
+    /// fraction of clustered particles still together with their cluster
+    pub persistence: f32,
+    /// mean particle speed
+    pub motility: f32,
+    /// mean normalised species entropy inside clusters, 0 for single-species
+    /// clusters and 1 for evenly mixed ones
+    pub diversity: f32,
+}
+
+impl Metrics {
+    pub fn measure(before: &[Circle], after: &[Circle], species: usize) -> Self {
+        if after.iter().any(|c| !c.pos[0].is_finite() || !c.pos[1].is_finite()) {
+            return Self::default();
+        }
+
+        let old = Clusters::find(before, LINK_DISTANCE);
+        let new = Clusters::find(after, LINK_DISTANCE);
+
+        // for each old cluster, how many of its members share the most common
+        // new cluster
+        let mut overlap: HashMap<(usize, usize), usize> = HashMap::new();
+        for (i, &label) in old.labels.iter().enumerate() {
+            let to = new.labels[i];
+            if old.sizes[label] >= MIN_CLUSTER && new.sizes[to] >= MIN_CLUSTER {
+                *overlap.entry((label, to)).or_default() += 1;
+            }
+        }
+        let mut kept = vec![0; old.sizes.len()];
+        for (&(from, _), &n) in overlap.iter() {
+            kept[from] = kept[from].max(n);
+        }
+        let clustered: usize = old.sizes.iter().filter(|&&s| s >= MIN_CLUSTER).sum();
+        let persistence = if clustered > 0 {
+            kept.iter().sum::<usize>() as f32 / clustered as f32
+        } else {
+            0.0
+        };
+
+        let motility = after.iter().map(|c| length(c.vel)).sum::<f32>() / after.len().max(1) as f32;
+
+        let mut counts = vec![vec![0usize; species]; new.sizes.len()];
+        for (c, &label) in after.iter().zip(new.labels.iter()) {
+            counts[label][c.color as usize] += 1;
+        }
+        let mut entropy = 0.0;
+        let mut members = 0;
+        for (label, species_counts) in counts.iter().enumerate() {
+            let size = new.sizes[label];
+            if size < MIN_CLUSTER { continue; }
+            let h: f32 = species_counts
+                .iter()
+                .filter(|&&n| n > 0)
+                .map(|&n| {
+                    let p = n as f32 / size as f32;
+                    -p * p.ln()
+                })
+                .sum();
+            entropy += h * size as f32;
+            members += size;
+        }
+        let diversity = if members > 0 && species > 1 {
+            entropy / members as f32 / (species as f32).ln()
+        } else {
+            0.0
+        };
+
+        Self {
+            clusters: new.count(MIN_CLUSTER),
+            persistence,
+            motility,
+            diversity,
+        }
+    }
+
+    /// Single fitness value: several lasting, moving, mixed clusters score
+    /// highest. Each factor is zero when its property is missing entirely.
+    pub fn score(&self) -> f32 {
+        (1.0 + self.clusters as f32).ln()
+            * self.persistence
+            * (self.motility / (self.motility + 0.5))
+            * (0.5 + self.diversity)
+    }
+}
+
+/// Particles of each of `species` species.
+pub fn species_counts(circles: &[Circle], species: usize) -> Vec<usize> {
+    let mut counts = vec![0; species];
+    for c in circles {
+        if let Some(n) = counts.get_mut(c.color as usize) {
+            *n += 1;
+        }
+    }
+    counts
+}
+
+/// Counts of each species over time, oldest first, keeping the last
+/// `capacity` samples.
+pub struct Population {
+    samples: VecDeque<Vec<usize>>,
+    capacity: usize,
+}
+
+impl Population {
+    pub fn new(capacity: usize) -> Self {
+        Self { samples: VecDeque::with_capacity(capacity), capacity }
+    }
+
+    pub fn record(&mut self, counts: Vec<usize>) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(counts);
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// One species' count in every sample, zero where it had none.
+    pub fn series(&self, species: usize) -> Vec<f32> {
+        self.samples.iter().map(|s| s.get(species).copied().unwrap_or(0) as f32).collect()
+    }
+
+    /// Largest count of any species in any sample.
+    pub fn peak(&self) -> usize {
+        self.samples.iter().flatten().copied().max().unwrap_or(0)
+    }
+}
@@ -0,0 +1,166 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::circle::Circle;
+use crate::code::{self, Setup};
+use crate::evolve::{crossover, mutate, par_map, simulate};
+use crate::metrics::Clusters;
+use crate::params::Params;
+use crate::preset;
+use crate::rdf::radial_distribution;
+use crate::rules::{Generator, Rules};
+use crate::sim::length;
+use crate::thumbnail;
+
+const RDF_BINS: usize = 16;
+const SIZE_BINS: usize = 8;
+// distance to the k nearest neighbours in behaviour space
+const K_NEAREST: usize = 8;
+const THUMBNAIL_SIZE: u32 = 160;
+
+pub struct Config {
+    pub species: usize,
+    pub params: Params,
+    pub seed: u64,
+    pub particles: usize,
+    pub steps: usize,
+    pub population: usize,
+    pub generations: usize,
+    /// minimum novelty for a candidate to join the archive
+    pub threshold: f32,
+    pub out_dir: PathBuf,
+}
+
+pub struct Entry {
+    pub rules: Rules,
+    pub behaviour: Vec<f32>,
+    pub circles: Vec<Circle>,
+}
+
+/// Behaviour descriptor of a finished run: log-scaled g(r) out to twice the
+/// interaction range, the fraction of particles in clusters of size 1, 2-3,
+/// 4-7, ... and the mean speed.
+pub fn describe(circles: &[Circle], params: &Params) -> Vec<f32> {
+    let mut behaviour: Vec<f32> = radial_distribution(circles, RDF_BINS, 2.0 * params.rmax, params.world_size)
+        .into_iter()
+        .map(|g| (1.0 + g).ln())
+        .collect();
+
+    let clusters = Clusters::find(circles, params.rmax * 0.5);
+    let mut sizes = [0.0; SIZE_BINS];
+    for &size in &clusters.sizes {
+        let bin = (usize::BITS - size.leading_zeros() - 1) as usize;
+        sizes[bin.min(SIZE_BINS - 1)] += size as f32 / circles.len().max(1) as f32;
+    }
+    behaviour.extend_from_slice(&sizes);
+
+    let speed = circles.iter().map(|c| length(c.vel)).sum::<f32>() / circles.len().max(1) as f32;
+    behaviour.push(if speed.is_finite() { speed } else { 0.0 });
+    behaviour
+}
+
+fn distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum::<f32>().sqrt()
+}
+
+/// Mean distance from `behaviour` to its nearest neighbours among `others`,
+/// which is expected to contain `behaviour` itself.
+pub fn novelty<'a>(behaviour: &[f32], others: impl Iterator<Item = &'a [f32]>) -> f32 {
+    let mut d: Vec<f32> = others.map(|o| distance(behaviour, o)).collect();
+    d.sort_by(f32::total_cmp);
+    // the first distance is the candidate to itself
+    let nearest = &d[1.min(d.len())..d.len().min(K_NEAREST + 1)];
+    if nearest.is_empty() {
+        return f32::MAX;
+    }
+    nearest.iter().sum::<f32>() / nearest.len() as f32
+}
+
+/// Breeds from the most novel candidates each generation, archiving any
+/// whose behaviour is at least `threshold` from its neighbours, and exports
+/// the archive as a gallery.
+pub fn run(config: &Config) -> Vec<Entry> {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let mut population: Vec<Rules> = (0..config.population)
+        .map(|i| Generator::ALL[i % Generator::ALL.len()].generate(config.species, &mut rng))
+        .collect();
+    let mut archive: Vec<Entry> = Vec::new();
+
+    for generation in 0..config.generations {
+        let runs: Vec<Entry> = par_map(&population, |rules| {
+            let (_, circles) = simulate(rules, &config.params, config.seed, config.particles, config.steps);
+            Entry {
+                rules: rules.clone(),
+                behaviour: describe(&circles, &config.params),
+                circles,
+            }
+        });
+
+        let novelty_scores: Vec<f32> = runs
+            .iter()
+            .map(|entry| {
+                let others = archive.iter().chain(runs.iter()).map(|e| e.behaviour.as_slice());
+                novelty(&entry.behaviour, others)
+            })
+            .collect();
+        let mut scored: Vec<(f32, Entry)> = novelty_scores.into_iter().zip(runs).collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        let parents: Vec<Rules> = scored
+            .iter()
+            .take((config.population / 4).max(1))
+            .map(|(_, e)| e.rules.clone())
+            .collect();
+
+        let before = archive.len();
+        for (n, entry) in scored {
+            if n >= config.threshold {
+                archive.push(entry);
+            }
+        }
+        println!("generation {}: archive {} (+{})", generation, archive.len(), archive.len() - before);
+
+        population = parents.clone();
+        while population.len() < config.population {
+            let a = &parents[rng.gen_range(0..parents.len())];
+            let b = &parents[rng.gen_range(0..parents.len())];
+            let mut child = crossover(a, b, &mut rng);
+            mutate(&mut child, &mut rng);
+            population.push(child);
+        }
+    }
+
+    if let Err(e) = export(&archive, config) {
+        eprintln!("can't export gallery to {}: {}", config.out_dir.display(), e);
+    }
+    archive
+}
+
+/// Writes `novel-N.toml` and `novel-N.png` for every archived entry plus an
+/// `index.html` laying the thumbnails out with their share codes.
+pub fn export(archive: &[Entry], config: &Config) -> io::Result<()> {
+    fs::create_dir_all(&config.out_dir)?;
+    let mut html = String::from("<!doctype html>\n<title>particle life gallery</title>\n<body style=\"background:#111;color:#ccc;font-family:monospace\">\n");
+    for (i, entry) in archive.iter().enumerate() {
+        let setup = Setup {
+            seed: config.seed,
+            rules: entry.rules.clone(),
+            params: config.params,
+        };
+        preset::save(config.out_dir.join(format!("novel-{}.toml", i)), &setup)?;
+        let pixels = thumbnail::render(&entry.circles, THUMBNAIL_SIZE, config.params.world_size);
+        thumbnail::save_png(config.out_dir.join(format!("novel-{}.png", i)), THUMBNAIL_SIZE, &pixels)?;
+        // mutation keeps rules in -1..1, so the code always fits
+        let code = code::encode(&setup).unwrap_or_default();
+        let _ = writeln!(
+            html,
+            "<figure style=\"display:inline-block\"><a href=\"novel-{i}.toml\"><img src=\"novel-{i}.png\"></a><figcaption>{i}<br><small>{}</small></figcaption></figure>",
+            code,
+        );
+    }
+    fs::write(config.out_dir.join("index.html"), html)
+}
@@ -0,0 +1,130 @@
+use wgpu::util::DeviceExt;
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct LineVertex {
+    position: [f32; 2],
+    color: [f32; 4],
+}
+
+/// Screen-space line drawing for plots and markers on top of the particles.
+/// Coordinates are clip space, (-1, -1) bottom left to (1, 1) top right.
+/// Lines are collected every frame and uploaded in one go by `draw`.
+pub struct Overlay {
+    pipeline: wgpu::RenderPipeline,
+    buffer: wgpu::Buffer,
+    capacity: usize,
+    vertices: Vec<LineVertex>,
+}
+
+impl Overlay {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Overlay shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("overlay.wgsl").into()),
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Overlay pipeline layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Overlay pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<LineVertex>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x4],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::LineList,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let capacity = 1024;
+        let buffer = Self::create_buffer(device, capacity);
+
+        Self {
+            pipeline,
+            buffer,
+            capacity,
+            vertices: Vec::new(),
+        }
+    }
+
+    fn create_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Overlay vertex buffer"),
+            contents: bytemuck::cast_slice(&vec![LineVertex { position: [0.0; 2], color: [0.0; 4] }; capacity]),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        })
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    pub fn line(&mut self, a: [f32; 2], b: [f32; 2], color: [f32; 4]) {
+        self.vertices.push(LineVertex { position: a, color });
+        self.vertices.push(LineVertex { position: b, color });
+    }
+
+    pub fn rect(&mut self, min: [f32; 2], max: [f32; 2], color: [f32; 4]) {
+        self.line(min, [max[0], min[1]], color);
+        self.line([max[0], min[1]], max, color);
+        self.line(max, [min[0], max[1]], color);
+        self.line([min[0], max[1]], min, color);
+    }
+
+    /// Polyline of `ys` spread evenly across the box, scaled so `y_max` meets
+    /// the top edge. Values outside [0, y_max] are clipped to the box.
+    pub fn plot(&mut self, min: [f32; 2], max: [f32; 2], ys: &[f32], y_max: f32, color: [f32; 4]) {
+        let point = |k: usize, y: f32| {
+            let t = k as f32 / (ys.len().max(2) - 1) as f32;
+            let y = if y.is_finite() { (y / y_max).clamp(0.0, 1.0) } else { 0.0 };
+            [min[0] + t * (max[0] - min[0]), min[1] + y * (max[1] - min[1])]
+        };
+        for k in 1..ys.len() {
+            self.line(point(k - 1, ys[k - 1]), point(k, ys[k]), color);
+        }
+    }
+
+    pub fn draw<'a>(&'a mut self, device: &wgpu::Device, queue: &wgpu::Queue, render_pass: &mut wgpu::RenderPass<'a>) {
+        if self.vertices.is_empty() {
+            return;
+        }
+        if self.vertices.len() > self.capacity {
+            self.capacity = self.vertices.len().next_power_of_two();
+            self.buffer = Self::create_buffer(device, self.capacity);
+        }
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&self.vertices));
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_vertex_buffer(0, self.buffer.slice(..));
+        render_pass.draw(0..self.vertices.len() as u32, 0..1);
+    }
+}
+
+/// Species colour from the palette as a linear RGBA float.
+pub fn rgba(color: [u8; 4]) -> [f32; 4] {
+    color.map(|c| c as f32 / 255.0)
+}
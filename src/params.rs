@@ -0,0 +1,171 @@
+use crate::contact::Contact;
+use crate::friction::Friction;
+use crate::gpu;
+
+/// Physics constants shared with `compute_main`. Distances inside the kernel
+/// are measured in units of `rmax`, so `rmin` is a fraction of it too.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Params {
+    pub racc: f32,
+    pub rmax: f32,
+    pub rmin: f32,
+    pub mu: f32,
+    pub ff: f32,
+    pub world_size: f32,
+    // a `Friction` index, so the struct stays plain data for the shader
+    #[serde(with = "friction_name")]
+    friction: u32,
+    /// strength of the random kicks, zero for none
+    pub temperature: f32,
+    // whether velocities are rescaled so the kinetic temperature is held at
+    // `temperature`, as a u32 for the shader
+    #[serde(with = "flag")]
+    thermostat: u32,
+    // a `Contact` index
+    #[serde(with = "contact_name")]
+    contact: u32,
+    /// force per unit of overlap under `Contact::Spring`
+    pub stiffness: f32,
+    /// most particles births can grow the system to, which never stops it
+    /// keeping as many as it started with, at most `gpu::MAX_PARTICLES`
+    pub max_particles: u32,
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Self {
+            racc: 1.0,
+            rmax: 2.0,
+            rmin: 0.6,
+            mu: 5.0,
+            ff: 1.0,
+            world_size: 25.0,
+            friction: Friction::default().index(),
+            temperature: 0.0,
+            thermostat: 0,
+            contact: Contact::default().index(),
+            stiffness: 100.0,
+            max_particles: 0,
+        }
+    }
+}
+
+impl Params {
+    pub fn friction(&self) -> Friction {
+        Friction::from_index(self.friction).unwrap_or_default()
+    }
+
+    pub fn set_friction(&mut self, friction: Friction) {
+        self.friction = friction.index();
+    }
+
+    pub fn thermostat(&self) -> bool {
+        self.thermostat != 0
+    }
+
+    pub fn set_thermostat(&mut self, on: bool) {
+        self.thermostat = on as u32;
+    }
+
+    pub fn contact(&self) -> Contact {
+        Contact::from_index(self.contact).unwrap_or_default()
+    }
+
+    pub fn set_contact(&mut self, contact: Contact) {
+        self.contact = contact.index();
+    }
+
+    /// Whether steps need the thermal pass of `thermal::kick`.
+    pub fn thermal(&self) -> bool {
+        self.temperature > 0.0 || self.thermostat()
+    }
+
+    /// Most particles a system that started with `count` can grow to, births
+    /// never taking it past what the GPU can step.
+    pub fn limit(&self, count: usize) -> usize {
+        count.max((self.max_particles as usize).min(gpu::MAX_PARTICLES))
+    }
+
+    /// Checks the values the kernel divides by or that would make steps blow
+    /// up: everything finite, a positive `rmax` and `world_size`, `rmin` in
+    /// 0..1, no negative friction, temperature or stiffness and no more
+    /// `max_particles` than the GPU can step.
+    pub fn validate(&self) -> Result<(), String> {
+        let values = [self.racc, self.rmax, self.rmin, self.mu, self.ff, self.world_size, self.temperature, self.stiffness];
+        if let Some(i) = values.iter().position(|v| !v.is_finite()) {
+            return Err(format!("{} is {}", Self::NAMES[i], values[i]));
+        }
+        if !(self.rmax > 0.0 && self.world_size > 0.0) {
+            return Err(format!("rmax {} and world_size {} must be positive", self.rmax, self.world_size));
+        }
+        if !(0.0..1.0).contains(&self.rmin) {
+            return Err(format!("rmin {} must be at least 0 and below 1", self.rmin));
+        }
+        if self.mu < 0.0 || self.temperature < 0.0 || self.stiffness < 0.0 {
+            return Err(format!("mu {}, temperature {} and stiffness {} can't be negative", self.mu, self.temperature, self.stiffness));
+        }
+        if self.max_particles as usize > gpu::MAX_PARTICLES {
+            return Err(format!("max_particles {} is over {}", self.max_particles, gpu::MAX_PARTICLES));
+        }
+        Ok(())
+    }
+
+    pub const NAMES: [&'static str; 8] = ["racc", "rmax", "rmin", "mu", "ff", "world_size", "temperature", "stiffness"];
+
+    /// Field by its index in `NAMES`, for editing parameters from the keyboard.
+    pub fn field_mut(&mut self, i: usize) -> &mut f32 {
+        match i {
+            0 => &mut self.racc,
+            1 => &mut self.rmax,
+            2 => &mut self.rmin,
+            3 => &mut self.mu,
+            4 => &mut self.ff,
+            5 => &mut self.world_size,
+            6 => &mut self.temperature,
+            _ => &mut self.stiffness,
+        }
+    }
+}
+
+// friction models are written by name
+mod friction_name {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::friction::Friction;
+
+    pub fn serialize<S: Serializer>(index: &u32, serializer: S) -> Result<S::Ok, S::Error> {
+        Friction::from_index(*index).unwrap_or_default().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+        Friction::deserialize(deserializer).map(Friction::index)
+    }
+}
+
+mod contact_name {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::contact::Contact;
+
+    pub fn serialize<S: Serializer>(index: &u32, serializer: S) -> Result<S::Ok, S::Error> {
+        Contact::from_index(*index).unwrap_or_default().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+        Contact::deserialize(deserializer).map(Contact::index)
+    }
+}
+
+mod flag {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(flag: &u32, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bool(*flag != 0)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+        bool::deserialize(deserializer).map(u32::from)
+    }
+}
@@ -0,0 +1,38 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::code::Setup;
+
+#[derive(Debug)]
+pub enum PresetError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+}
+
+impl fmt::Display for PresetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PresetError::Io(e) => write!(f, "{}", e),
+            PresetError::Parse(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for PresetError {}
+
+/// Reads a TOML preset file holding `seed`, `rules` and a `[params]` table.
+pub fn load(path: impl AsRef<Path>) -> Result<Setup, PresetError> {
+    let text = fs::read_to_string(path).map_err(PresetError::Io)?;
+    toml::from_str(&text).map_err(PresetError::Parse)
+}
+
+pub fn save(path: impl AsRef<Path>, setup: &Setup) -> io::Result<()> {
+    let path = path.as_ref();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let text = toml::to_string(setup).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    fs::write(path, text)
+}
@@ -0,0 +1,153 @@
+use std::f32::consts::PI;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::circle::Circle;
+use crate::grid::Grid;
+use crate::sim::{length, sub};
+
+/// Per-species-pair radial distribution functions g(r), accumulated over any
+/// number of snapshots. The reference density is taken over a disc of radius
+/// `world_size`, so an ideal gas filling the world gives g(r) = 1.
+#[derive(Clone)]
+pub struct Rdf {
+    species: usize,
+    bins: usize,
+    r_max: f32,
+    world_size: f32,
+    // counts[(a * species + b) * bins + k]: b particles in shell k around a
+    counts: Vec<u64>,
+    // particles of each species, summed over snapshots
+    population: Vec<u64>,
+    frames: u64,
+}
+
+impl Rdf {
+    pub fn new(species: usize, bins: usize, r_max: f32, world_size: f32) -> Self {
+        Self {
+            species,
+            bins,
+            r_max,
+            world_size,
+            counts: vec![0; species * species * bins],
+            population: vec![0; species],
+            frames: 0,
+        }
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn r_max(&self) -> f32 {
+        self.r_max
+    }
+
+    pub fn world_size(&self) -> f32 {
+        self.world_size
+    }
+
+    /// Centre of shell `k`.
+    pub fn radius(&self, k: usize) -> f32 {
+        (k as f32 + 0.5) * self.r_max / self.bins as f32
+    }
+
+    pub fn add(&mut self, circles: &[Circle]) {
+        let (n, bins, r_max) = (self.species, self.bins, self.r_max);
+        let dr = r_max / bins as f32;
+        let species = |c: &Circle| (c.color.max(0) as usize).min(n - 1);
+
+        let positions: Vec<[f32; 2]> = circles.iter().map(|c| c.pos).collect();
+        let grid = Grid::new(&positions, r_max);
+        for (i, c) in circles.iter().enumerate() {
+            let a = species(c);
+            grid.for_each_near(c.pos, |j| {
+                if j == i { return; }
+                let r = length(sub(c.pos, circles[j].pos));
+                if r < r_max {
+                    let b = species(&circles[j]);
+                    self.counts[(a * n + b) * bins + ((r / dr) as usize).min(bins - 1)] += 1;
+                }
+            });
+        }
+
+        for c in circles {
+            self.population[species(c)] += 1;
+        }
+        self.frames += 1;
+    }
+
+    fn shell_area(&self, k: usize) -> f32 {
+        let dr = self.r_max / self.bins as f32;
+        let r0 = k as f32 * dr;
+        PI * ((r0 + dr).powi(2) - r0 * r0)
+    }
+
+    /// g(r) of species `b` around species `a`. Around a particle of its own
+    /// species there are only `nb - 1` others.
+    pub fn pair(&self, a: usize, b: usize) -> Vec<f32> {
+        let world = PI * self.world_size * self.world_size;
+        let frames = self.frames.max(1) as f32;
+        let na = self.population[a] as f32 / frames;
+        let nb = self.population[b] as f32 / frames - if a == b { 1.0 } else { 0.0 };
+        (0..self.bins)
+            .map(|k| {
+                let count = self.counts[(a * self.species + b) * self.bins + k] as f32 / frames;
+                count / (na * nb / world * self.shell_area(k)).max(f32::MIN_POSITIVE)
+            })
+            .collect()
+    }
+
+    /// g(r) over all particles regardless of species.
+    pub fn total(&self) -> Vec<f32> {
+        let world = PI * self.world_size * self.world_size;
+        let frames = self.frames.max(1) as f32;
+        let n = self.population.iter().sum::<u64>() as f32 / frames;
+        (0..self.bins)
+            .map(|k| {
+                let count: u64 = (0..self.species * self.species)
+                    .map(|p| self.counts[p * self.bins + k])
+                    .sum();
+                count as f32 / frames / (n * (n - 1.0) / world * self.shell_area(k)).max(f32::MIN_POSITIVE)
+            })
+            .collect()
+    }
+
+    /// One row per shell: `r`, the total g(r), then every ordered pair as
+    /// `g_a_b`.
+    pub fn to_csv(&self) -> String {
+        let pairs: Vec<(usize, usize)> = (0..self.species)
+            .flat_map(|a| (0..self.species).map(move |b| (a, b)))
+            .collect();
+        let curves: Vec<Vec<f32>> = pairs.iter().map(|&(a, b)| self.pair(a, b)).collect();
+        let total = self.total();
+
+        let mut csv = String::from("r,g");
+        for (a, b) in &pairs {
+            let _ = write!(csv, ",g_{}_{}", a, b);
+        }
+        csv.push('\n');
+        for k in 0..self.bins {
+            let _ = write!(csv, "{},{}", self.radius(k), total[k]);
+            for curve in &curves {
+                let _ = write!(csv, ",{}", curve[k]);
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+
+    pub fn write_csv(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_csv())
+    }
+}
+
+/// g(r) over all particles of a single snapshot.
+pub fn radial_distribution(circles: &[Circle], bins: usize, r_max: f32, world_size: f32) -> Vec<f32> {
+    let species = circles.iter().map(|c| c.color.max(0) as usize + 1).max().unwrap_or(1);
+    let mut rdf = Rdf::new(species, bins, r_max, world_size);
+    rdf.add(circles);
+    rdf.total()
+}
@@ -0,0 +1,69 @@
+use rayon::prelude::*;
+
+use crate::grid::Grid;
+
+/// Most reactions a rule set can have, the size of the shader's table.
+pub const MAX_REACTIONS: usize = 32;
+
+/// A particle of species `from` that stays within `range` of one of species
+/// `with` for `time` becomes species `into`.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Reaction {
+    pub from: usize,
+    pub with: usize,
+    pub range: f32,
+    pub time: f32,
+    pub into: usize,
+}
+
+/// How long a particle has been in reach of a partner for `reaction`.
+/// `Timer` in shader.wgsl.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Timer {
+    pub reaction: u32,
+    pub elapsed: f32,
+}
+
+/// Advances every particle's timer by a step of `dt`, given the positions
+/// and species at the start of the step, and returns the species each
+/// particle whose reaction completed becomes. A particle only times the
+/// first reaction in the table it's in reach for, and starts again when
+/// that changes or it leaves reach. `react_main` in shader.wgsl.
+pub fn react(reactions: &[Reaction], pos: &[[f32; 2]], species: &[i32], timers: &mut [Timer], dt: f32) -> Vec<Option<usize>> {
+    if reactions.is_empty() {
+        timers.iter_mut().for_each(|t| t.elapsed = 0.0);
+        return vec![None; timers.len()];
+    }
+    // partners are only looked for within the longest range
+    let grid = Grid::new(pos, reactions.iter().map(|r| r.range).fold(0.0, f32::max));
+    let in_reach = |i: usize, r: &Reaction| {
+        let mut found = false;
+        grid.for_each_near(pos[i], |j| {
+            let diff = [pos[i][0] - pos[j][0], pos[i][1] - pos[j][1]];
+            found |= j != i && species[j] == r.with as i32 && (diff[0] * diff[0] + diff[1] * diff[1]).sqrt() < r.range;
+        });
+        found
+    };
+    timers
+        .par_iter_mut()
+        .enumerate()
+        .map(|(i, timer)| {
+            let found = reactions.iter().position(|r| species[i] == r.from as i32 && in_reach(i, r));
+            let Some(k) = found else {
+                timer.elapsed = 0.0;
+                return None;
+            };
+            if timer.reaction != k as u32 {
+                *timer = Timer { reaction: k as u32, elapsed: 0.0 };
+            }
+            timer.elapsed += dt;
+            if timer.elapsed < reactions[k].time {
+                return None;
+            }
+            timer.elapsed = 0.0;
+            Some(reactions[k].into)
+        })
+        .collect()
+}
+
@@ -0,0 +1,662 @@
+use rand::Rng;
+
+use crate::bonds::{BondRule, MAX_BOND_RULES, MAX_VALENCY};
+use crate::curves::{self, SAMPLES};
+use crate::fields::{Field, Obstacle, MAX_FIELDS, MAX_OBSTACLES};
+use crate::kernel::Kernel;
+use crate::lifecycle::Lifecycle;
+use crate::params::Params;
+use crate::reactions::{Reaction, MAX_REACTIONS};
+use crate::species::Species;
+
+/// Square matrix of attraction strengths, `get(i, j)` being how strongly
+/// species `i` is pulled towards species `j`, and of the force kernel each
+/// pair uses, with a force curve for pairs using `Kernel::Tabulated`. Also
+/// holds each species' physical properties and lifecycle, the reactions and
+/// bond rules between them, and the fields and obstacles around them.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(try_from = "RulesFile", into = "RulesFile")]
+pub struct Rules {
+    species: usize,
+    m: Vec<f32>,
+    kernels: Vec<Kernel>,
+    // SAMPLES points per pair, zero until a curve is loaded or drawn
+    curves: Vec<f32>,
+    properties: Vec<Species>,
+    lifecycles: Vec<Lifecycle>,
+    reactions: Vec<Reaction>,
+    bond_rules: Vec<BondRule>,
+    fields: Vec<Field>,
+    obstacles: Vec<Obstacle>,
+}
+
+impl Rules {
+    pub fn new(species: usize) -> Self {
+        Self {
+            species,
+            m: vec![0.0; species * species],
+            kernels: vec![Kernel::default(); species * species],
+            curves: vec![0.0; species * species * SAMPLES],
+            properties: vec![Species::default(); species],
+            lifecycles: vec![Lifecycle::default(); species],
+            reactions: Vec::new(),
+            bond_rules: Vec::new(),
+            fields: Vec::new(),
+            obstacles: Vec::new(),
+        }
+    }
+
+    pub fn random<R: Rng>(species: usize, scale: f32, rng: &mut R) -> Self {
+        let mut rules = Self::new(species);
+        for v in rules.m.iter_mut() {
+            *v = (rng.gen::<f32>() - 0.5) * 2.0 * scale;
+        }
+        rules
+    }
+
+    pub fn species(&self) -> usize {
+        self.species
+    }
+
+    pub fn get(&self, i: usize, j: usize) -> f32 {
+        self.m[i * self.species + j]
+    }
+
+    pub fn set(&mut self, i: usize, j: usize, v: f32) {
+        self.m[i * self.species + j] = v;
+    }
+
+    pub fn kernel(&self, i: usize, j: usize) -> Kernel {
+        self.kernels[i * self.species + j]
+    }
+
+    pub fn set_kernel(&mut self, i: usize, j: usize, kernel: Kernel) {
+        self.kernels[i * self.species + j] = kernel;
+    }
+
+    /// Uses one kernel for every pair.
+    pub fn set_all_kernels(&mut self, kernel: Kernel) {
+        self.kernels.fill(kernel);
+    }
+
+    /// The kernel every pair uses, or None if they differ.
+    pub fn global_kernel(&self) -> Option<Kernel> {
+        let first = *self.kernels.first()?;
+        self.kernels.iter().all(|&k| k == first).then_some(first)
+    }
+
+    pub fn curve(&self, i: usize, j: usize) -> &[f32] {
+        let at = (i * self.species + j) * SAMPLES;
+        &self.curves[at..at + SAMPLES]
+    }
+
+    pub fn set_curve(&mut self, i: usize, j: usize, curve: &[f32]) {
+        let at = (i * self.species + j) * SAMPLES;
+        self.curves[at..at + SAMPLES].copy_from_slice(curve);
+    }
+
+    pub fn properties(&self, i: usize) -> Species {
+        self.properties[i]
+    }
+
+    pub fn set_properties(&mut self, i: usize, properties: Species) {
+        self.properties[i] = properties;
+    }
+
+    /// Every species' properties, for the species buffer.
+    pub fn all_properties(&self) -> &[Species] {
+        &self.properties
+    }
+
+    pub fn lifecycle(&self, i: usize) -> Lifecycle {
+        self.lifecycles[i]
+    }
+
+    pub fn set_lifecycle(&mut self, i: usize, lifecycle: Lifecycle) {
+        self.lifecycles[i] = lifecycle;
+    }
+
+    /// Every species' lifecycle, for the lifecycle buffer.
+    pub fn all_lifecycles(&self) -> &[Lifecycle] {
+        &self.lifecycles
+    }
+
+    /// Whether any species can breed or die, so steps need the lifecycle
+    /// pass.
+    pub fn living(&self) -> bool {
+        self.lifecycles.iter().any(|l| !l.is_static())
+    }
+
+    pub fn reactions(&self) -> &[Reaction] {
+        &self.reactions
+    }
+
+    /// Replaces the reaction table, which must only name species these rules
+    /// have and hold at most `MAX_REACTIONS` entries.
+    pub fn set_reactions(&mut self, reactions: Vec<Reaction>) -> Result<(), String> {
+        if reactions.len() > MAX_REACTIONS {
+            return Err(format!("{} reactions, at most {} allowed", reactions.len(), MAX_REACTIONS));
+        }
+        if let Some(r) = reactions.iter().find(|r| r.from.max(r.with).max(r.into) >= self.species) {
+            return Err(format!("reaction {:?} names a species past the {} there are", r, self.species));
+        }
+        if let Some(r) = reactions.iter().find(|r| !(r.range > 0.0 && r.time >= 0.0)) {
+            return Err(format!("reaction {:?} needs a positive range and a time of at least 0", r));
+        }
+        self.reactions = reactions;
+        Ok(())
+    }
+
+    pub fn bond_rules(&self) -> &[BondRule] {
+        &self.bond_rules
+    }
+
+    /// Replaces the rules bonds form by, which must only name species these
+    /// rules have and hold at most `MAX_BOND_RULES` entries.
+    pub fn set_bond_rules(&mut self, bond_rules: Vec<BondRule>) -> Result<(), String> {
+        if bond_rules.len() > MAX_BOND_RULES {
+            return Err(format!("{} bond rules, at most {} allowed", bond_rules.len(), MAX_BOND_RULES));
+        }
+        if let Some(b) = bond_rules.iter().find(|b| b.a.max(b.b) >= self.species) {
+            return Err(format!("bond rule {:?} names a species past the {} there are", b, self.species));
+        }
+        if let Some(b) = bond_rules.iter().find(|b| {
+            !(b.range > 0.0 && (1..=MAX_VALENCY as u32).contains(&b.valency) && b.spring.rest >= 0.0 && b.spring.stiffness >= 0.0 && b.spring.strength >= 0.0)
+        }) {
+            return Err(format!("bond rule {:?} needs a positive range, a valency of 1 to {} and no negative spring", b, MAX_VALENCY));
+        }
+        self.bond_rules = bond_rules;
+        Ok(())
+    }
+
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// Replaces the external fields, at most `MAX_FIELDS` of them.
+    pub fn set_fields(&mut self, fields: Vec<Field>) -> Result<(), String> {
+        if fields.len() > MAX_FIELDS {
+            return Err(format!("{} fields, at most {} allowed", fields.len(), MAX_FIELDS));
+        }
+        let valid = |f: &Field| {
+            f.to_parts().1.iter().all(|v| v.is_finite())
+                && match *f {
+                    Field::Attractor { radius, .. } | Field::Vortex { radius, .. } => radius > 0.0,
+                    Field::Shear { coupling, .. } => coupling >= 0.0,
+                    Field::Gravity { .. } => true,
+                }
+        };
+        if let Some(f) = fields.iter().find(|f| !valid(f)) {
+            return Err(format!("field {:?} needs finite numbers, a positive radius and no negative coupling", f));
+        }
+        self.fields = fields;
+        Ok(())
+    }
+
+    pub fn obstacles(&self) -> &[Obstacle] {
+        &self.obstacles
+    }
+
+    /// Replaces the obstacles, at most `MAX_OBSTACLES` of them.
+    pub fn set_obstacles(&mut self, obstacles: Vec<Obstacle>) -> Result<(), String> {
+        if obstacles.len() > MAX_OBSTACLES {
+            return Err(format!("{} obstacles, at most {} allowed", obstacles.len(), MAX_OBSTACLES));
+        }
+        let valid = |o: &Obstacle| match *o {
+            Obstacle::Circle { radius, .. } => radius > 0.0,
+            Obstacle::Rect { min, max } => min[0] < max[0] && min[1] < max[1],
+        };
+        if let Some(o) = obstacles.iter().find(|o| !valid(o)) {
+            return Err(format!("obstacle {:?} needs a positive radius or a min below its max", o));
+        }
+        self.obstacles = obstacles;
+        Ok(())
+    }
+
+    /// Takes the kernels, curves, species properties, lifecycles, reactions
+    /// and bond rules of the species `other` also has, and all its fields and
+    /// obstacles, so a fresh matrix keeps the physics picked for the old one.
+    /// Lifecycles whose partner is gone stop breeding.
+    pub fn copy_physics(&mut self, other: &Rules) {
+        let n = self.species;
+        self.reactions = other.reactions.iter().filter(|r| r.from.max(r.with).max(r.into) < n).copied().collect();
+        self.bond_rules = other.bond_rules.iter().filter(|b| b.a.max(b.b) < n).copied().collect();
+        self.fields.clone_from(&other.fields);
+        self.obstacles.clone_from(&other.obstacles);
+        for i in 0..self.species.min(other.species) {
+            self.properties[i] = other.properties[i];
+            self.lifecycles[i] = other.lifecycles[i];
+            self.lifecycles[i].partner = other.lifecycles[i].partner.filter(|&p| p < n);
+            for j in 0..self.species.min(other.species) {
+                self.set_kernel(i, j, other.kernel(i, j));
+                self.set_curve(i, j, other.curve(i, j));
+            }
+        }
+    }
+
+    /// Force species `i` feels from species `j` at distance `d`, along the
+    /// direction away from it.
+    pub fn force(&self, i: usize, j: usize, d: f32, params: &Params) -> f32 {
+        match self.kernel(i, j) {
+            Kernel::Tabulated if d < 1.0 => curves::sample(self.curve(i, j), d),
+            kernel => kernel.force(d, params.ff * self.get(i, j), params),
+        }
+    }
+
+    /// The curves laid out for the curves texture, one row of SAMPLES
+    /// texels per pair, pair `(i, j)` in row `i * size + j`.
+    pub fn curve_texels(&self, size: usize) -> Vec<f32> {
+        let mut texels = vec![0.0; size * size * SAMPLES];
+        for i in 0..self.species.min(size) {
+            for j in 0..self.species.min(size) {
+                let at = (i * size + j) * SAMPLES;
+                texels[at..at + SAMPLES].copy_from_slice(self.curve(i, j));
+            }
+        }
+        texels
+    }
+
+    /// Lays the matrix out as `size` x `size` RGBA texels for the constraints
+    /// texture, attraction in the red channel, the kernel's index in the
+    /// green one and zero padding elsewhere.
+    /// The shader looks rules up with `textureLoad(constraints, vec2(i, j))`,
+    /// so `get(i, j)` goes in column `i` of row `j`.
+    pub fn texels(&self, size: usize) -> Vec<[f32; 4]> {
+        let mut texels = vec![[0.0; 4]; size * size];
+        for i in 0..self.species.min(size) {
+            for j in 0..self.species.min(size) {
+                texels[j * size + i][0] = self.get(i, j);
+                texels[j * size + i][1] = self.kernel(i, j).index() as f32;
+            }
+        }
+        texels
+    }
+}
+
+// on disk a plain matrix means every pair uses the default kernel
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+enum RulesFile {
+    Matrix(Vec<Vec<f32>>),
+    Physics {
+        matrix: Vec<Vec<f32>>,
+        #[serde(default)]
+        kernels: KernelsFile,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        curves: Vec<CurveFile>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        species: Vec<Species>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        lifecycles: Vec<Lifecycle>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        reactions: Vec<Reaction>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        bonds: Vec<BondRule>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        fields: Vec<Field>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        obstacles: Vec<Obstacle>,
+    },
+}
+
+// the curve of one tabulated pair
+#[derive(serde::Serialize, serde::Deserialize)]
+struct CurveFile {
+    pair: [usize; 2],
+    force: Vec<f32>,
+}
+
+// one kernel for every pair, or a matrix of them
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+enum KernelsFile {
+    Global(Kernel),
+    Pairs(Vec<Vec<Kernel>>),
+}
+
+impl Default for KernelsFile {
+    fn default() -> Self {
+        KernelsFile::Global(Kernel::default())
+    }
+}
+
+impl From<Rules> for RulesFile {
+    fn from(rules: Rules) -> Self {
+        let n = rules.species;
+        // chunks can't split the empty matrix into rows of none
+        if n == 0 {
+            return RulesFile::Matrix(Vec::new());
+        }
+        let matrix = rules.m.chunks(n).map(|row| row.to_vec()).collect();
+        let curves = (0..n * n)
+            .map(|k| [k / n, k % n])
+            .filter(|&[a, b]| rules.kernel(a, b) == Kernel::Tabulated)
+            .map(|pair| CurveFile {
+                pair,
+                force: rules.curve(pair[0], pair[1]).to_vec(),
+            })
+            .collect();
+        let species = if rules.properties.iter().all(|p| *p == Species::default()) {
+            Vec::new()
+        } else {
+            rules.properties.clone()
+        };
+        let lifecycles = if rules.lifecycles.iter().all(|l| *l == Lifecycle::default()) {
+            Vec::new()
+        } else {
+            rules.lifecycles.clone()
+        };
+        let kernels = match rules.global_kernel() {
+            Some(kernel) => KernelsFile::Global(kernel),
+            None => KernelsFile::Pairs(rules.kernels.chunks(n).map(|row| row.to_vec()).collect()),
+        };
+        match kernels {
+            KernelsFile::Global(Kernel::Triangle) if species.is_empty()
+                && lifecycles.is_empty()
+                && rules.reactions.is_empty()
+                && rules.bond_rules.is_empty()
+                && rules.fields.is_empty()
+                && rules.obstacles.is_empty() =>
+            {
+                RulesFile::Matrix(matrix)
+            }
+            kernels => RulesFile::Physics {
+                matrix,
+                kernels,
+                curves,
+                species,
+                lifecycles,
+                reactions: rules.reactions,
+                bonds: rules.bond_rules,
+                fields: rules.fields,
+                obstacles: rules.obstacles,
+            },
+        }
+    }
+}
+
+impl TryFrom<RulesFile> for Rules {
+    type Error = String;
+
+    fn try_from(file: RulesFile) -> Result<Self, String> {
+        let (rows, kernels, curves, properties, lifecycles, reactions, bonds, fields, obstacles) = match file {
+            RulesFile::Matrix(rows) => {
+                (rows, KernelsFile::default(), Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new())
+            }
+            RulesFile::Physics { matrix, kernels, curves, species, lifecycles, reactions, bonds, fields, obstacles } => {
+                (matrix, kernels, curves, species, lifecycles, reactions, bonds, fields, obstacles)
+            }
+        };
+        let species = rows.len();
+        if let Some(row) = rows.iter().find(|row| row.len() != species) {
+            return Err(format!("rule matrix is not square: {} rows but a row of {}", species, row.len()));
+        }
+        let kernels = match kernels {
+            KernelsFile::Global(kernel) => vec![kernel; species * species],
+            KernelsFile::Pairs(pairs) => {
+                if pairs.len() != species || pairs.iter().any(|row| row.len() != species) {
+                    return Err(format!("kernel matrix doesn't match the {} x {} rule matrix", species, species));
+                }
+                pairs.concat()
+            }
+        };
+        let properties = match properties.len() {
+            0 => vec![Species::default(); species],
+            n if n == species => properties,
+            n => return Err(format!("{} species properties for {} species", n, species)),
+        };
+        for p in &properties {
+            p.validate()?;
+        }
+        let lifecycles = match lifecycles.len() {
+            0 => vec![Lifecycle::default(); species],
+            n if n == species => lifecycles,
+            n => return Err(format!("{} lifecycles for {} species", n, species)),
+        };
+        for l in &lifecycles {
+            l.validate(species)?;
+        }
+        let mut rules = Self {
+            species,
+            m: rows.concat(),
+            kernels,
+            curves: vec![0.0; species * species * SAMPLES],
+            properties,
+            lifecycles,
+            reactions: Vec::new(),
+            bond_rules: Vec::new(),
+            fields: Vec::new(),
+            obstacles: Vec::new(),
+        };
+        rules.set_reactions(reactions)?;
+        rules.set_bond_rules(bonds)?;
+        rules.set_fields(fields)?;
+        rules.set_obstacles(obstacles)?;
+        for curve in curves {
+            let [a, b] = curve.pair;
+            if a >= species || b >= species {
+                return Err(format!("curve for pair {}-{} but only {} species", a, b, species));
+            }
+            if curve.force.len() != SAMPLES {
+                return Err(format!("curve for pair {}-{} has {} points, not {}", a, b, curve.force.len(), SAMPLES));
+            }
+            if let Some(f) = curve.force.iter().find(|f| !f.is_finite()) {
+                return Err(format!("curve for pair {}-{} has a force of {}", a, b, f));
+            }
+            rules.set_curve(a, b, &curve.force);
+        }
+        Ok(rules)
+    }
+}
+
+/// Structured matrix generators. Each one fixes the shape of the matrix and
+/// randomises the strengths, so repeated use gives variations on a theme.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Generator {
+    Random,
+    Symmetric,
+    Antisymmetric,
+    Chase,
+    Snake,
+    Banded,
+    Sparse,
+}
+
+impl Generator {
+    pub const ALL: [Generator; 7] = [
+        Generator::Random,
+        Generator::Symmetric,
+        Generator::Antisymmetric,
+        Generator::Chase,
+        Generator::Snake,
+        Generator::Banded,
+        Generator::Sparse,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Generator::Random => "random",
+            Generator::Symmetric => "symmetric",
+            Generator::Antisymmetric => "antisymmetric",
+            Generator::Chase => "chase",
+            Generator::Snake => "snake",
+            Generator::Banded => "banded",
+            Generator::Sparse => "sparse",
+        }
+    }
+
+    pub fn generate<R: Rng>(self, species: usize, rng: &mut R) -> Rules {
+        let n = species;
+        let mut rules = Rules::new(n);
+        let mut uniform = |lo: f32, hi: f32| lo + rng.gen::<f32>() * (hi - lo);
+
+        match self {
+            Generator::Random => {
+                for i in 0..n {
+                    for j in 0..n {
+                        rules.set(i, j, uniform(-1.0, 1.0));
+                    }
+                }
+            }
+            Generator::Symmetric => {
+                for i in 0..n {
+                    for j in i..n {
+                        let v = uniform(-1.0, 1.0);
+                        rules.set(i, j, v);
+                        rules.set(j, i, v);
+                    }
+                }
+            }
+            Generator::Antisymmetric => {
+                for i in 0..n {
+                    for j in i + 1..n {
+                        let v = uniform(-1.0, 1.0);
+                        rules.set(i, j, v);
+                        rules.set(j, i, -v);
+                    }
+                }
+            }
+            // rock-paper-scissors: every species chases the next one round
+            // the cycle and flees the previous one
+            Generator::Chase => {
+                let chase = uniform(0.5, 1.0);
+                let flee = uniform(0.5, 1.0);
+                for i in 0..n {
+                    rules.set(i, i, uniform(0.0, 0.5));
+                    // with two species the next one round the cycle is also
+                    // the previous one, so the second pass would undo the first
+                    if n > 2 || (n == 2 && i == 0) {
+                        rules.set(i, (i + 1) % n, chase);
+                        rules.set((i + 1) % n, i, -flee);
+                    }
+                }
+            }
+            // open chain where each species sticks to itself and follows
+            // the next one along
+            Generator::Snake => {
+                for i in 0..n {
+                    for j in 0..n {
+                        rules.set(i, j, uniform(-0.3, 0.0));
+                    }
+                }
+                for i in 0..n {
+                    rules.set(i, i, uniform(0.5, 1.0));
+                    if i + 1 < n {
+                        rules.set(i, i + 1, uniform(0.3, 0.8));
+                    }
+                }
+            }
+            Generator::Banded => {
+                for i in 0..n {
+                    for j in 0..n {
+                        let dist = i.abs_diff(j).min(n - i.abs_diff(j));
+                        if dist <= 1 {
+                            rules.set(i, j, uniform(-1.0, 1.0));
+                        }
+                    }
+                }
+            }
+            Generator::Sparse => {
+                for i in 0..n {
+                    for j in 0..n {
+                        if i == j || uniform(0.0, 1.0) < 0.25 {
+                            rules.set(i, j, uniform(-1.0, 1.0));
+                        }
+                    }
+                }
+            }
+        }
+
+        rules
+    }
+}
+
+/// Hand-picked matrices that work for any number of species.
+pub struct Preset {
+    pub name: &'static str,
+    build: fn(usize) -> Rules,
+}
+
+impl Preset {
+    pub fn build(&self, species: usize) -> Rules {
+        (self.build)(species)
+    }
+}
+
+pub const PRESETS: &[Preset] = &[
+    Preset { name: "clusters", build: clusters },
+    Preset { name: "rock-paper-scissors", build: rock_paper_scissors },
+    Preset { name: "snakes", build: snakes },
+    Preset { name: "cells", build: cells },
+    Preset { name: "orbits", build: orbits },
+    Preset { name: "gas", build: Rules::new },
+];
+
+fn clusters(n: usize) -> Rules {
+    let mut rules = Rules::new(n);
+    for i in 0..n {
+        for j in 0..n {
+            rules.set(i, j, if i == j { 1.0 } else { -0.2 });
+        }
+    }
+    rules
+}
+
+fn rock_paper_scissors(n: usize) -> Rules {
+    let mut rules = Rules::new(n);
+    for i in 0..n {
+        rules.set(i, i, 0.2);
+        // as in Generator::Chase, two species make a single pair
+        if n > 2 || (n == 2 && i == 0) {
+            rules.set(i, (i + 1) % n, 1.0);
+            rules.set((i + 1) % n, i, -1.0);
+        }
+    }
+    rules
+}
+
+fn snakes(n: usize) -> Rules {
+    let mut rules = Rules::new(n);
+    for i in 0..n {
+        for j in 0..n {
+            rules.set(i, j, -0.1);
+        }
+        rules.set(i, i, 1.0);
+        if i + 1 < n {
+            rules.set(i, i + 1, 0.6);
+        }
+    }
+    rules
+}
+
+fn cells(n: usize) -> Rules {
+    let mut rules = Rules::new(n);
+    for i in 0..n {
+        for j in 0..n {
+            let v = match i.abs_diff(j) {
+                0 => 0.8,
+                1 => 0.3,
+                _ => -0.3,
+            };
+            rules.set(i, j, v);
+        }
+    }
+    rules
+}
+
+fn orbits(n: usize) -> Rules {
+    let mut rules = Rules::new(n);
+    for i in 0..n {
+        for j in 0..n {
+            let v = if i == j {
+                0.5
+            } else if (j + n - i) % n <= n / 2 {
+                0.4
+            } else {
+                -0.4
+            };
+            rules.set(i, j, v);
+        }
+    }
+    rules
+}
@@ -5,6 +5,22 @@ struct circle {
     vel: vec2<f32>,
 }
 
+struct Params {
+    racc: f32,
+    rmax: f32,
+    rmin: f32,
+    mu: f32,
+    ff: f32,
+    world_size: f32,
+    // numbered like friction::Friction
+    friction: u32,
+    temperature: f32,
+    thermostat: u32,
+    // numbered like contact::Contact
+    contact: u32,
+    stiffness: f32,
+}
+
 @group(0) @binding(0)
 var<uniform> dt: f32;
 @group(0) @binding(1)
@@ -13,54 +29,826 @@ var constraints: texture_2d<f32>;
 var colors: texture_1d<f32>;
 @group(0) @binding(3)
 var d_sampler: sampler;
+@group(0) @binding(4)
+var<uniform> params: Params;
+// force curves of tabulated pairs, a row of points per pair
+@group(0) @binding(5)
+var curves: texture_2d<f32>;
+
+// laid out like species::Species
+struct Species {
+    mass: f32,
+    drag: f32,
+    max_speed: f32,
+    radius: f32,
+}
+
+@group(0) @binding(6)
+var<storage, read> species: array<Species>;
+
+// laid out like gpu::GpuReaction, whose from, with and into are reserved
+// words here
+struct Reaction {
+    reactant: i32,
+    partner: i32,
+    product: i32,
+    range: f32,
+    time: f32,
+}
+
+struct Reactions {
+    count: u32,
+    list: array<Reaction, 32>,
+}
+
+@group(0) @binding(7)
+var<storage, read> reactions: Reactions;
+
+// laid out like gpu::GpuLifecycle, with no partner below zero
+struct Lifecycle {
+    lifespan: f32,
+    range: f32,
+    partner: i32,
+    crowd: u32,
+    period: f32,
+    lonely: u32,
+}
+
+@group(0) @binding(8)
+var<storage, read> lifecycles: array<Lifecycle>;
+
+// laid out like gpu::GpuBondRule
+struct BondRule {
+    a: i32,
+    b: i32,
+    valency: u32,
+    range: f32,
+    rest: f32,
+    stiffness: f32,
+    strength: f32,
+}
+
+struct BondRules {
+    count: u32,
+    list: array<BondRule, 16>,
+}
+
+@group(0) @binding(9)
+var<storage, read> bond_rules: BondRules;
+
+// laid out like gpu::GpuPart, the numbers being fields::Field::to_parts or
+// fields::Obstacle::to_parts
+struct Part {
+    kind: u32,
+    x: f32,
+    y: f32,
+    z: f32,
+    w: f32,
+}
+
+struct Fields {
+    count: u32,
+    list: array<Part, 16>,
+}
+
+struct Obstacles {
+    count: u32,
+    list: array<Part, 32>,
+}
+
+@group(0) @binding(10)
+var<storage, read> fields: Fields;
+@group(0) @binding(11)
+var<storage, read> obstacles: Obstacles;
 
 @group(1) @binding(0)
 var<storage, read_write> circles: array<circle>;
+// the particles as they were at the start of the step
+@group(2) @binding(0)
+var<storage, read> prev: array<circle>;
+
+// what the passes of a step share, laid out like gpu::Frame; the particles
+// past `count` are dead, and births can fill them up to `limit`, scattered
+// by draws at `life_clock`
+struct Frame {
+    seed: u32,
+    clock: u32,
+    kinetic: f32,
+    count: u32,
+    limit: u32,
+    life_clock: u32,
+}
+
+@group(3) @binding(0)
+var<storage, read_write> frame: Frame;
+
+// reactions::Timer
+struct Timer {
+    reaction: u32,
+    elapsed: f32,
+}
+
+@group(3) @binding(1)
+var<storage, read_write> timers: array<Timer>;
+
+// lifecycle::Life
+struct Life {
+    age: f32,
+    since: f32,
+    alive: u32,
+    breeds: u32,
+}
+
+@group(3) @binding(2)
+var<storage, read_write> lives: array<Life>;
+
+// bonds::Bond
+struct Bond {
+    a: u32,
+    b: u32,
+    rest: f32,
+    stiffness: f32,
+    strength: f32,
+}
+
+struct Bonds {
+    count: u32,
+    list: array<Bond>,
+}
+
+// the bonds as a step leaves them, appended to from every invocation
+struct BondList {
+    count: atomic<u32>,
+    list: array<Bond>,
+}
+
+@group(3) @binding(3)
+var<storage, read_write> bonds: BondList;
+
+// what the bond passes work out about a particle: how many bonds it has
+// that hold and the other ends of the first MAX_VALENCY of them, which is
+// all a particle that can still bond has, and the particle it proposes a
+// bond with and under which rule; then the slot the lifecycle passes move
+// it to, and its child's rank among the births, both counted within its
+// block by scan_main. Zeroed before every step with bonds.
+struct Link {
+    degree: atomic<u32>,
+    partners: array<u32, 6>,
+    partner: u32,
+    rule: u32,
+    remap: u32,
+    child: u32,
+}
+
+@group(3) @binding(4)
+var<storage, read_write> links: array<Link>;
+
+// a particle as scan_main sets it aside, for scatter_main to move
+struct Spare {
+    circle: circle,
+    life: Life,
+    timer: Timer,
+}
+
+@group(3) @binding(5)
+var<storage, read_write> spares: array<Spare>;
+
+// the survivors and births in each block of SCAN slots, which scan_blocks
+// turns into those in the blocks before it; and the survivors and the
+// particles there are once the lifecycle passes are done
+struct Scan {
+    kept: u32,
+    count: u32,
+    blocks: array<vec2<u32>>,
+}
+
+@group(3) @binding(6)
+var<storage, read_write> scan: Scan;
+
+// the bonds as they were at the start of the step
+@group(3) @binding(7)
+var<storage, read> held: Bonds;
+
+// no partner or slot in a Link
+const NONE: u32 = 0xffffffffu;
+// bonds::MAX_VALENCY
+const MAX_VALENCY: u32 = 6u;
+
+// force between two particles d apart, in units of rmax, along the direction
+// away from the other one, with the rule acc and the shape numbered like
+// kernel::Kernel
+fn kernel(shape: u32, d: f32, acc: f32) -> f32 {
+    if d >= 1.0 {
+        return 0.0;
+    }
+    let rmin = params.rmin;
+    let repel = params.racc * (1.0 - d / rmin);
+    // lennard-jones
+    if shape == 2u {
+        let r = rmin / max(d, 1e-6);
+        let s = min(r * r * r * r * r * r, 2.0);
+        return params.racc * s * (s - 1.0) + acc * s;
+    }
+    if d < rmin {
+        return repel;
+    }
+    switch shape {
+        // cosine
+        case 1u: {
+            return acc * 0.5 * (1.0 - cos(6.2831855 * (d - rmin) / (1.0 - rmin)));
+        }
+        // gaussian
+        case 3u: {
+            let x = (d - (1.0 + rmin) / 2.0) / ((1.0 - rmin) / 4.0);
+            return acc * exp(-x * x);
+        }
+        // inverse-square
+        case 4u: {
+            return acc * (rmin / d) * (rmin / d);
+        }
+        // triangle
+        default: {
+            return acc * (1.0 - abs(2.0 * d - 1.0 - rmin) / (1.0 - rmin));
+        }
+    }
+}
+
+// force from the curve in row `pair`, interpolating between the two nearest
+// points spread over d from 0 to 1
+fn tabulated(pair: u32, d: f32) -> f32 {
+    let samples = textureDimensions(curves).x;
+    let x = clamp(d, 0.0, 1.0) * f32(samples - 1u);
+    let k = min(u32(x), samples - 2u);
+    let t = x - f32(k);
+    let a = textureLoad(curves, vec2(k, pair), 0).x;
+    let b = textureLoad(curves, vec2(k + 1u, pair), 0).x;
+    return a + (b - a) * t;
+}
+
+// unit vector from particle j to particle i; coincident particles are split
+// along x, the later index going right
+fn direction(diff: vec2<f32>, i: u32, j: u32) -> vec2<f32> {
+    if (diff.x == 0.0) && (diff.y == 0.0) {
+        return vec2(select(-1.0, 1.0, i > j), 0.0);
+    }
+    return normalize(diff);
+}
 
 @compute @workgroup_size(1)
-fn compute_main(
-    @builtin(workgroup_id) wgid: vec3<u32>,
-    @builtin(num_workgroups) wgs: vec3<u32>,
-) {
+fn compute_main(@builtin(workgroup_id) wgid: vec3<u32>) {
     let i = wgid.x;
+    if i >= frame.count {return;}
 
-    let racc: f32 = 1.0;
-    let rmax: f32 = 2.0;
-    let rmin: f32 = 0.3 * rmax;
+    let rmax = params.rmax;
 
-    let mu: f32 = 5.0;
-    let ff: f32 = 1.0;
+    let mu = params.mu;
+    let ff = params.ff;
 
-    let world_size = 25.0;
+    let world_size = params.world_size;
 
+    var me = prev[i];
     var a = vec2(0.0, 0.0);
+    var correction = vec2(0.0, 0.0);
+    let mass = species[me.color].mass;
+
+    for (var j: u32 = u32(0); j < frame.count; j++) {
+        if j == i {continue;}
 
-    for (var j: u32 = u32(1); j < wgs.x; j++) {
         // get vector and length between self and other
-        let diff = circles[i].pos - circles[j].pos;
-        let d = length(diff) / rmax;
-        if (diff.x == 0.0) || (diff.y == 0.0) || (d >= rmax) {continue;}
+        let diff = me.pos - prev[j].pos;
+        let len = length(diff);
+        let d = len / rmax;
+        if d >= 1.0 {continue;}
 
-        var acc = ff * textureLoad(constraints, vec2(circles[i].color, circles[j].color), 0).x;
+        let rule = textureLoad(constraints, vec2(me.color, prev[j].color), 0);
+        let acc = ff * rule.x;
+        let dir = direction(diff, i, j);
 
-        if d <= 0.125 {
-            circles[i].pos += normalize(diff) * d/2.;
+        let overlap = max(me.rad + prev[j].rad - len, 0.0);
+        switch params.contact {
+            case 1u: {
+                a += dir * (params.stiffness * overlap);
+            }
+            case 2u: {
+                let other_mass = species[prev[j].color].mass;
+                correction += dir * (overlap * (other_mass / (mass + other_mass)));
+            }
+            default: {
+                if d <= 0.125 {
+                    me.pos += dir * d/2.;
+                }
+            }
         }
-        if d < rmin {
-            a -= normalize(diff) * racc * ((d/rmin) - 1.0);
+        let shape = u32(rule.y);
+        if shape == 5u {
+            let count = textureDimensions(constraints).x;
+            a += dir * tabulated(u32(me.color) * count + u32(prev[j].color), d);
         } else {
-            a += normalize(diff) * acc * (1.0 - abs(2.0 * d - 1.0 - rmin)/(1.0-rmin));
+            a += dir * kernel(shape, d, acc);
         }
     }
+    me.pos += correction;
 
-    if length(circles[i].pos) > world_size {
-        a -= normalize(circles[i].pos) * (length(circles[i].pos) - world_size) * 25.0;
+    if length(me.pos) > world_size {
+        a -= normalize(me.pos) * (length(me.pos) - world_size) * 25.0;
+    }
+    for (var k = 0u; k < fields.count; k++) {
+        a += field(fields.list[k], me.pos, me.vel, mass);
     }
 
-    a -= (mu * pow(length(circles[i].vel), 2.0)) * normalize(circles[i].vel);
+    let props = species[me.color];
+    me.vel = cap(integrate(me.vel, a, mu, props.drag, rmax * dt / props.mass), props.max_speed);
+    me.pos += me.vel * dt;
+    for (var k = 0u; k < obstacles.count; k++) {
+        let hit = push(obstacles.list[k], me.pos, props.radius);
+        if hit.z > 0.0 {
+            me.pos += hit.xy * hit.z;
+            me.vel -= hit.xy * min(dot(me.vel, hit.xy), 0.0);
+        }
+    }
+    me.rad = props.radius;
+    circles[i] = me;
+}
 
-    circles[i].vel += a * rmax * dt;
-    circles[i].pos += circles[i].vel * dt; 
+// fields::Field::force
+fn field(f: Part, pos: vec2<f32>, vel: vec2<f32>, mass: f32) -> vec2<f32> {
+    switch f.kind {
+        // gravity
+        case 0u: {
+            return vec2(f.x, f.y) * mass;
+        }
+        // attractor
+        case 1u: {
+            let diff = vec2(f.x, f.y) - pos;
+            let r2 = dot(diff, diff) + f.w * f.w;
+            return diff * (f.z / (r2 * sqrt(r2)));
+        }
+        // vortex
+        case 2u: {
+            let diff = pos - vec2(f.x, f.y);
+            return vec2(-diff.y, diff.x) * (f.z / (dot(diff, diff) + f.w * f.w));
+        }
+        // shear
+        default: {
+            return vec2(f.z * (f.x * pos.y - vel.x), -f.z * vel.y);
+        }
+    }
+}
+
+// fields::Obstacle::push, as the normal and a depth that isn't positive when
+// the disc is clear
+fn push(o: Part, pos: vec2<f32>, rad: f32) -> vec3<f32> {
+    // circle
+    if o.kind == 0u {
+        let diff = pos - vec2(o.x, o.y);
+        let len = length(diff);
+        var normal = vec2(1.0, 0.0);
+        if len > 0.0 {
+            normal = diff / len;
+        }
+        return vec3(normal, o.z + rad - len);
+    }
+    // rect
+    let lo = vec2(o.x, o.y);
+    let hi = vec2(o.z, o.w);
+    let diff = pos - clamp(pos, lo, hi);
+    let len = length(diff);
+    if len > 0.0 {
+        return vec3(diff / len, rad - len);
+    }
+    var best = vec3(-1.0, 0.0, pos.x - lo.x);
+    if hi.x - pos.x < best.z {
+        best = vec3(1.0, 0.0, hi.x - pos.x);
+    }
+    if pos.y - lo.y < best.z {
+        best = vec3(0.0, -1.0, pos.y - lo.y);
+    }
+    if hi.y - pos.y < best.z {
+        best = vec3(0.0, 1.0, hi.y - pos.y);
+    }
+    return vec3(best.xy, best.z + rad);
+}
+
+// velocity after a step under acceleration a with the friction model in
+// params, gain turning a into a change of velocity
+fn integrate(vel: vec2<f32>, a: vec2<f32>, mu: f32, drag: f32, gain: f32) -> vec2<f32> {
+    let k = mu * drag;
+    var acc = a;
+    var keep = 1.0;
+    switch params.friction {
+        case 1u: { acc -= k * vel; }
+        case 2u: { acc -= k * length(vel) * vel; }
+        case 3u: { keep = pow(0.5, k * dt); }
+        default: {}
+    }
+    let next = vel * keep + acc * gain;
+    if params.friction == 4u {
+        return cap(next, mu / drag);
+    }
+    return next;
+}
+
+fn cap(vel: vec2<f32>, max_speed: f32) -> vec2<f32> {
+    let speed = length(vel);
+    if speed > max_speed {
+        return vel * (max_speed / speed);
+    }
+    return vel;
+}
+
+// bonds::accelerations for particle i, then bonds::kick
+@compute @workgroup_size(1)
+fn spring_main(@builtin(workgroup_id) wgid: vec3<u32>) {
+    let i = wgid.x;
+    if i >= frame.count {return;}
+
+    var a = vec2(0.0, 0.0);
+    for (var k = 0u; k < held.count; k++) {
+        let bond = held.list[k];
+        var j = bond.a;
+        if bond.a == i {
+            j = bond.b;
+        } else if bond.b != i {
+            continue;
+        }
+        let diff = prev[i].pos - prev[j].pos;
+        a += direction(diff, i, j) * (-bond.stiffness * (length(diff) - bond.rest));
+    }
+    if all(a == vec2(0.0, 0.0)) {return;}
+
+    let props = species[prev[i].color];
+    let dv = a * (params.rmax * dt / props.mass);
+    var me = circles[i];
+    me.vel = cap(me.vel + dv, props.max_speed);
+    me.pos += dv * dt;
+    circles[i] = me;
+}
+
+// whether a bond is stretched past its strength at the start of the step
+fn broken(bond: Bond) -> bool {
+    return length(prev[bond.a].pos - prev[bond.b].pos) - bond.rest > bond.strength;
+}
+
+// counts a bond that holds this step at its end i, noting the other end j
+fn hold(i: u32, j: u32) {
+    let slot = atomicAdd(&links[i].degree, 1u);
+    if slot < MAX_VALENCY {
+        links[i].partners[slot] = j;
+    }
+}
+
+// the first of the bond passes, run for every bond slot: empties the bond
+// list for keep_main and bonds_main to fill again, and counts each bond
+// that holds at both its ends
+@compute @workgroup_size(64)
+fn hold_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let k = id.x;
+    if k == 0u {
+        atomicStore(&bonds.count, 0u);
+    }
+    if k >= held.count {return;}
+    let bond = held.list[k];
+    if broken(bond) {return;}
+    hold(bond.a, bond.b);
+    hold(bond.b, bond.a);
+}
+
+// whether particle i, with `degree` bonds, has one to j that holds
+fn bonded(i: u32, j: u32, degree: u32) -> bool {
+    for (var k = 0u; k < min(degree, MAX_VALENCY); k++) {
+        if links[i].partners[k] == j {
+            return true;
+        }
+    }
+    return false;
+}
+
+// the proposal bonds::update gives particle i: the nearest particle it can
+// bond to under the first rule that has one, the lower index winning a tie
+@compute @workgroup_size(1)
+fn link_main(@builtin(workgroup_id) wgid: vec3<u32>) {
+    let i = wgid.x;
+    if i >= frame.count {return;}
+    let me = prev[i];
+    let degree = atomicLoad(&links[i].degree);
+
+    var found = NONE;
+    var found_rule = 0u;
+    for (var r = 0u; r < bond_rules.count && found == NONE; r++) {
+        let rule = bond_rules.list[r];
+        var partner = rule.a;
+        if me.color == rule.a {
+            partner = rule.b;
+        } else if me.color != rule.b {
+            continue;
+        }
+        if degree >= rule.valency {continue;}
+        var best = 0.0;
+        for (var j = 0u; j < frame.count; j++) {
+            let len = length(me.pos - prev[j].pos);
+            if j == i || prev[j].color != partner || len >= rule.range || (found != NONE && len >= best) {continue;}
+            if atomicLoad(&links[j].degree) >= rule.valency || bonded(i, j, degree) {continue;}
+            found = j;
+            found_rule = r;
+            best = len;
+        }
+    }
+    links[i].partner = found;
+    links[i].rule = found_rule;
+    // staying put unless the lifecycle passes move it
+    links[i].remap = i;
+}
+
+// appends a bond unless the list already has `room`; one that doesn't fit
+// takes its slot back, which leaves the count at `room` as the count only
+// goes past it while such slots are taken
+fn append(bond: Bond, room: u32) {
+    let n = atomicAdd(&bonds.count, 1u);
+    if n < room {
+        bonds.list[n] = bond;
+    } else {
+        atomicSub(&bonds.count, 1u);
+    }
+}
+
+// the bonds that hold, moved to their particles' new slots like
+// bonds::remap; run for every bond slot after the lifecycle passes, which
+// leave each particle's new slot in its link
+@compute @workgroup_size(64)
+fn keep_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let k = id.x;
+    if k >= held.count {return;}
+    var bond = held.list[k];
+    if broken(bond) {return;}
+    bond.a = links[bond.a].remap;
+    bond.b = links[bond.b].remap;
+    if bond.a == NONE || bond.b == NONE {return;}
+    append(bond, arrayLength(&bonds.list));
+}
+
+// the rest of bonds::update: the lower of two particles that propose each
+// other bonds them, in their new slots, while formation has room. As a
+// particle makes at most one bond a step, which pairs bond doesn't depend
+// on the order invocations run in, though the order bonds are listed in
+// does, and so at the limit can which bonds form.
+@compute @workgroup_size(1)
+fn bonds_main(@builtin(workgroup_id) wgid: vec3<u32>) {
+    let i = wgid.x;
+    if i >= frame.count {return;}
+    let j = links[i].partner;
+    if j == NONE || j < i || links[j].partner != i {return;}
+    let a = links[i].remap;
+    let b = links[j].remap;
+    if a == NONE || b == NONE {return;}
+    let rule = bond_rules.list[links[i].rule];
+    append(Bond(a, b, rule.rest, rule.stiffness, rule.strength), min(frame.limit * MAX_VALENCY / 2u, arrayLength(&bonds.list)));
+}
+
+// reactions::react, reading the particles as they were at the start of the
+// step so no particle sees another's new species
+@compute @workgroup_size(1)
+fn react_main(@builtin(workgroup_id) wgid: vec3<u32>) {
+    let i = wgid.x;
+    if i >= frame.count {return;}
+    let me = prev[i];
+
+    let none = reactions.count;
+    var found = none;
+    for (var k = 0u; k < reactions.count && found == none; k++) {
+        let r = reactions.list[k];
+        if r.reactant != me.color {continue;}
+        for (var j = 0u; j < frame.count; j++) {
+            if j != i && prev[j].color == r.partner && length(me.pos - prev[j].pos) < r.range {
+                found = k;
+                break;
+            }
+        }
+    }
+
+    var timer = timers[i];
+    if found == none {
+        timer.elapsed = 0.0;
+    } else {
+        if timer.reaction != found {
+            timer.reaction = found;
+            timer.elapsed = 0.0;
+        }
+        timer.elapsed += dt;
+        let r = reactions.list[found];
+        if timer.elapsed >= r.time {
+            timer.elapsed = 0.0;
+            circles[i].color = r.product;
+            circles[i].rad = species[r.product].radius;
+        }
+    }
+    timers[i] = timer;
+}
+
+// lifecycle::judge
+@compute @workgroup_size(1)
+fn life_main(@builtin(workgroup_id) wgid: vec3<u32>) {
+    let i = wgid.x;
+    if i >= frame.count {return;}
+    let me = prev[i];
+    let rule = lifecycles[me.color];
+
+    var neighbours = 0u;
+    var partners = 0u;
+    for (var j = 0u; j < frame.count; j++) {
+        if j != i && length(me.pos - prev[j].pos) < rule.range {
+            neighbours++;
+            if prev[j].color == rule.partner {
+                partners++;
+            }
+        }
+    }
+
+    var life = lives[i];
+    life.age += dt;
+    life.since += dt;
+    let alive = life.age < rule.lifespan && neighbours >= rule.lonely;
+    let breeds = alive && rule.partner >= 0 && partners >= rule.crowd && life.since >= rule.period;
+    if breeds {
+        life.since = 0.0;
+    }
+    life.alive = u32(alive);
+    life.breeds = u32(breeds);
+    lives[i] = life;
+}
+
+// slots per workgroup of the lifecycle scan
+const SCAN: u32 = 256u;
+
+var<workgroup> sums: array<vec2<u32>, 256>;
+
+// turns each invocation's entry of `sums` into the sum of those up to and
+// including it
+fn scan_workgroup(k: u32) {
+    for (var s = 1u; s < SCAN; s *= 2u) {
+        workgroupBarrier();
+        var before = vec2(0u);
+        if k >= s {
+            before = sums[k - s];
+        }
+        workgroupBarrier();
+        sums[k] += before;
+    }
+    workgroupBarrier();
+}
+
+// the first of the lifecycle::compact passes: sets each particle aside and
+// ranks it among the survivors and its child among the births of its block
+@compute @workgroup_size(256)
+fn scan_main(@builtin(workgroup_id) wgid: vec3<u32>, @builtin(local_invocation_index) k: u32) {
+    let i = wgid.x * SCAN + k;
+    var flags = vec2(0u);
+    if i < frame.count {
+        let life = lives[i];
+        flags = vec2(life.alive, life.breeds);
+        spares[i] = Spare(circles[i], life, timers[i]);
+    }
+    sums[k] = flags;
+    scan_workgroup(k);
+    if i < frame.count {
+        let before = sums[k] - flags;
+        links[i].remap = before.x;
+        links[i].child = before.y;
+    }
+    if k == SCAN - 1u {
+        scan.blocks[wgid.x] = sums[k];
+    }
+}
+
+// the survivors and births in the blocks before each, run as a single
+// workgroup after scan_main, each invocation summing a run of blocks; and
+// the particles there will be, births stopping at the limit
+@compute @workgroup_size(256)
+fn scan_blocks(@builtin(local_invocation_index) k: u32) {
+    let blocks = (frame.count + SCAN - 1u) / SCAN;
+    let run = (blocks + SCAN - 1u) / SCAN;
+    let end = min((k + 1u) * run, blocks);
+    var total = vec2(0u);
+    for (var b = k * run; b < end; b++) {
+        total += scan.blocks[b];
+    }
+    sums[k] = total;
+    scan_workgroup(k);
+    var before = sums[k] - total;
+    for (var b = k * run; b < end; b++) {
+        let block = scan.blocks[b];
+        scan.blocks[b] = before;
+        before += block;
+    }
+    if k == SCAN - 1u {
+        scan.kept = sums[k].x;
+        scan.count = min(sums[k].x + sums[k].y, frame.limit);
+    }
+}
+
+// the rest of lifecycle::compact: moves each survivor to its rank so the
+// survivors keep their order, and puts each child after them in the order
+// of its parent, so births take the same slots on every backend; the slots
+// left dead get a radius of zero so drawing them shows nothing
+@compute @workgroup_size(256)
+fn scatter_main(@builtin(workgroup_id) wgid: vec3<u32>, @builtin(local_invocation_index) k: u32) {
+    let i = wgid.x * SCAN + k;
+    if i >= frame.count {return;}
+    if i >= scan.count {
+        circles[i].rad = 0.0;
+    }
+    let spare = spares[i];
+    if spare.life.alive == 0u {
+        links[i].remap = NONE;
+        return;
+    }
+    let before = scan.blocks[wgid.x];
+    let kept = before.x + links[i].remap;
+    links[i].remap = kept;
+    circles[kept] = spare.circle;
+    lives[kept] = spare.life;
+    timers[kept] = spare.timer;
+
+    let n = scan.kept + before.y + links[i].child;
+    if spare.life.breeds != 0u && n < frame.limit {
+        var child = spare.circle;
+        child.pos += gaussian(~frame.seed, frame.life_clock, n) * child.rad;
+        circles[n] = child;
+        lives[n] = Life();
+        timers[n] = Timer();
+    }
+}
+
+// ends the lifecycle passes once bonds_main has read the old count, and
+// moves on the clock births draw at
+@compute @workgroup_size(1)
+fn settle_main() {
+    frame.count = scan.count;
+    frame.life_clock += 1u;
+}
+
+const TAU: f32 = 6.283185307179586;
+// thermal::RELAXATION
+const RELAXATION: f32 = 0.1;
+
+// thermal::pcg
+fn pcg(v: u32) -> u32 {
+    let state = v * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+// thermal::gaussian
+fn gaussian(seed: u32, clock: u32, i: u32) -> vec2<f32> {
+    let a = pcg(pcg(pcg(clock) ^ i) ^ seed);
+    let b = pcg(a);
+    let u = f32((a >> 8u) + 1u) / 16777216.0;
+    let v = f32(b >> 8u) / 16777216.0;
+    let r = sqrt(-2.0 * log(u));
+    return vec2(r * cos(TAU * v), r * sin(TAU * v));
+}
+
+var<workgroup> partial: array<f32, 64>;
+
+// advances the clock and sums the kinetic temperature of every particle,
+// run as a single workgroup before thermal_main
+@compute @workgroup_size(64)
+fn thermal_reduce(@builtin(local_invocation_index) k: u32) {
+    var sum = 0.0;
+    for (var i = k; i < frame.count; i += 64u) {
+        let c = circles[i];
+        sum += species[c.color].mass * dot(c.vel, c.vel);
+    }
+    partial[k] = sum;
+    workgroupBarrier();
+    for (var s = 32u; s > 0u; s /= 2u) {
+        if k < s {
+            partial[k] += partial[k + s];
+        }
+        workgroupBarrier();
+    }
+    if k == 0u {
+        frame.clock += 1u;
+        frame.kinetic = partial[0] / f32(2u * max(frame.count, 1u));
+    }
+}
+
+// thermal::kick, with the thermostat's factor from thermal::rescale
+@compute @workgroup_size(1)
+fn thermal_main(@builtin(workgroup_id) wgid: vec3<u32>) {
+    let i = wgid.x;
+    if i >= frame.count {return;}
+    var me = circles[i];
+    let props = species[me.color];
+
+    var scale = 1.0;
+    if params.thermostat != 0u && frame.kinetic > 0.0 {
+        let t = params.temperature;
+        let next = frame.kinetic + dt / RELAXATION * (t - frame.kinetic) - 2.0 * t * dt;
+        scale = sqrt(max(next / frame.kinetic, 0.0));
+    }
+    let sigma = sqrt(2.0 * params.temperature * dt / props.mass);
+    me.vel = cap(me.vel * scale + gaussian(frame.seed, frame.clock, i) * sigma, props.max_speed);
+    circles[i] = me;
 }
 
 struct VertexInput {
@@ -102,4 +890,28 @@ fn fs_main(
 ) -> @location(0) vec4<f32> {
     let l = smoothstep(0.0, 0.05, 1.0 - length(in.tex_coords));
     return vec4<f32>(textureLoad(colors, in.color, 0).xyz, l);
+}
+
+@group(2) @binding(0)
+var<storage, read> drawn_bonds: Bonds;
+
+// one line per bond slot, from particle a to particle b; slots past the
+// bond count are put outside the view
+@vertex
+fn vs_bond(
+    @builtin(vertex_index) vx: u32,
+    @builtin(instance_index) ix: u32,
+) -> @builtin(position) vec4<f32> {
+    if ix >= drawn_bonds.count {
+        return vec4(2.0, 2.0, 0.0, 1.0);
+    }
+    let bond = drawn_bonds.list[ix];
+    var pos = circles[select(bond.a, bond.b, vx == 1u)].pos;
+    pos.x *= f32(size.y) / f32(size.x);
+    return camera * vec4<f32>(pos, 0.0, 1.0);
+}
+
+@fragment
+fn fs_bond() -> @location(0) vec4<f32> {
+    return vec4(0.8, 0.8, 0.8, 0.6);
 }
\ No newline at end of file
@@ -0,0 +1,197 @@
+use rayon::prelude::*;
+
+use crate::circle::Circle;
+use crate::contact::{overlap, Contact};
+use crate::fields;
+use crate::friction::cap;
+use crate::grid::Grid;
+use crate::params::Params;
+use crate::rules::Rules;
+
+/// Longest time step the viewer takes, and the one headless runs use.
+pub const DT: f32 = 0.005;
+
+/// How each particle finds the others it interacts with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Neighbours {
+    /// every other particle, like the shader
+    All,
+    /// only particles in the surrounding cells of a grid sized to the
+    /// interaction range; sums in a different order, so results drift apart
+    /// from `All` over time
+    Grid,
+}
+
+impl Neighbours {
+    pub const ALL: [Neighbours; 2] = [Neighbours::All, Neighbours::Grid];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Neighbours::All => "all",
+            Neighbours::Grid => "grid",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|n| n.name() == name)
+    }
+}
+
+/// CPU port of `compute_main` in shader.wgsl. Like the shader it reads
+/// every particle from `prev` and writes the stepped particle to `next`, so
+/// the result doesn't depend on the order particles are updated in.
+pub fn step(prev: &[Circle], next: &mut [Circle], rules: &Rules, params: &Params, dt: f32) {
+    step_with(prev, next, rules, params, dt, Neighbours::All);
+}
+
+pub fn step_with(prev: &[Circle], next: &mut [Circle], rules: &Rules, params: &Params, dt: f32, neighbours: Neighbours) {
+    match neighbours {
+        Neighbours::All => {
+            for (i, out) in next.iter_mut().enumerate() {
+                *out = step_circle(prev, i, rules, params, dt, |f| (0..prev.len()).for_each(f));
+            }
+        }
+        Neighbours::Grid => {
+            let grid = grid(prev, params);
+            for (i, out) in next.iter_mut().enumerate() {
+                *out = step_circle(prev, i, rules, params, dt, |f| grid.for_each_near(prev[i].pos, f));
+            }
+        }
+    }
+}
+
+/// `step_with` spread over every core. Each particle is still computed by
+/// the same code from the same `prev`, so the result is bit-for-bit the
+/// same as the single-threaded step.
+pub fn par_step_with(prev: &[Circle], next: &mut [Circle], rules: &Rules, params: &Params, dt: f32, neighbours: Neighbours) {
+    match neighbours {
+        Neighbours::All => {
+            next.par_iter_mut().enumerate().for_each(|(i, out)| {
+                *out = step_circle(prev, i, rules, params, dt, |f| (0..prev.len()).for_each(f));
+            });
+        }
+        Neighbours::Grid => {
+            let grid = grid(prev, params);
+            next.par_iter_mut().enumerate().for_each(|(i, out)| {
+                *out = step_circle(prev, i, rules, params, dt, |f| grid.for_each_near(prev[i].pos, f));
+            });
+        }
+    }
+}
+
+/// Grid whose cells cover the interaction range, `rmax` in world lengths.
+pub fn grid(circles: &[Circle], params: &Params) -> Grid {
+    let positions: Vec<[f32; 2]> = circles.iter().map(|c| c.pos).collect();
+    Grid::new(&positions, params.rmax)
+}
+
+/// Steps `circles` in place `steps` times with a scratch buffer.
+pub fn run(circles: &mut Vec<Circle>, rules: &Rules, params: &Params, dt: f32, steps: usize) {
+    run_with(circles, rules, params, dt, steps, Neighbours::All);
+}
+
+pub fn run_with(circles: &mut Vec<Circle>, rules: &Rules, params: &Params, dt: f32, steps: usize, neighbours: Neighbours) {
+    let mut next = circles.clone();
+    for _ in 0..steps {
+        step_with(circles, &mut next, rules, params, dt, neighbours);
+        std::mem::swap(circles, &mut next);
+    }
+}
+
+/// Steps particle `i` against the candidates `neighbours` passes to its
+/// callback, which may include `i` itself.
+pub fn step_circle(
+    circles: &[Circle],
+    i: usize,
+    rules: &Rules,
+    params: &Params,
+    dt: f32,
+    neighbours: impl FnOnce(&mut dyn FnMut(usize)),
+) -> Circle {
+    let rmax = params.rmax;
+
+    let mu = params.mu;
+
+    let world_size = params.world_size;
+
+    let contact = params.contact();
+    let mass = rules.properties(circles[i].color as usize).mass;
+
+    let mut me = circles[i];
+    let mut a = [0.0, 0.0];
+    // taken out of the position after the loop, so the order neighbours
+    // come in doesn't matter
+    let mut correction = [0.0, 0.0];
+
+    neighbours(&mut |j| {
+        if j == i { return; }
+        let other = &circles[j];
+
+        // get vector and length between self and other
+        let diff = sub(me.pos, other.pos);
+        let len = length(diff);
+        let d = len / rmax;
+        if d >= 1.0 { return; }
+
+        let dir = direction(diff, i, j);
+
+        match contact {
+            Contact::Push if d <= 0.125 => me.pos = add(me.pos, scale(dir, d / 2.0)),
+            Contact::Push => {}
+            Contact::Spring => a = add(a, scale(dir, params.stiffness * overlap(len, me.rad, other.rad))),
+            Contact::Constraint => {
+                let other_mass = rules.properties(other.color as usize).mass;
+                let share = other_mass / (mass + other_mass);
+                correction = add(correction, scale(dir, overlap(len, me.rad, other.rad) * share));
+            }
+        }
+        a = add(a, scale(dir, rules.force(me.color as usize, other.color as usize, d, params)));
+    });
+    me.pos = add(me.pos, correction);
+
+    if length(me.pos) > world_size {
+        a = sub(a, scale(normalize(me.pos), (length(me.pos) - world_size) * 25.0));
+    }
+    a = add(a, fields::force(rules.fields(), me.pos, me.vel, mass));
+
+    let species = rules.properties(me.color as usize);
+    me.vel = params.friction().integrate(me.vel, a, mu, species.drag, rmax * dt / species.mass, dt);
+    me.vel = cap(me.vel, species.max_speed);
+    me.pos = add(me.pos, scale(me.vel, dt));
+    (me.pos, me.vel) = fields::collide(rules.obstacles(), me.pos, me.vel, species.radius);
+    me.rad = species.radius;
+    me
+}
+
+/// Unit vector from particle `j` to particle `i`, `diff` being the offset
+/// between them. Coincident particles are split along x, the later index
+/// going right, so they repel each other instead of producing NaNs.
+/// `direction` in shader.wgsl.
+pub fn direction(diff: [f32; 2], i: usize, j: usize) -> [f32; 2] {
+    if diff == [0.0, 0.0] {
+        [if i > j { 1.0 } else { -1.0 }, 0.0]
+    } else {
+        normalize(diff)
+    }
+}
+
+pub fn add(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    [a[0] + b[0], a[1] + b[1]]
+}
+
+pub fn sub(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    [a[0] - b[0], a[1] - b[1]]
+}
+
+pub fn scale(a: [f32; 2], s: f32) -> [f32; 2] {
+    [a[0] * s, a[1] * s]
+}
+
+pub fn length(a: [f32; 2]) -> f32 {
+    (a[0] * a[0] + a[1] * a[1]).sqrt()
+}
+
+// like WGSL's normalize, a zero vector comes out as NaN
+pub fn normalize(a: [f32; 2]) -> [f32; 2] {
+    scale(a, 1.0 / length(a))
+}
@@ -0,0 +1,262 @@
+use rayon::prelude::*;
+use std::f32::consts::TAU;
+
+use wide::{f32x8, CmpEq, CmpLe, CmpLt, CmpNe};
+
+use crate::circle::Circle;
+use crate::contact::Contact;
+use crate::curves;
+use crate::fields;
+use crate::friction;
+use crate::kernel::Kernel;
+use crate::params::Params;
+use crate::rules::Rules;
+
+const LANES: usize = 8;
+const LANE_INDEX: f32x8 = f32x8::new([0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]);
+
+/// Particles as one array per field, so the force loop can load eight
+/// neighbours' coordinates at a time.
+#[derive(Clone, Debug, Default)]
+pub struct Particles {
+    pub x: Vec<f32>,
+    pub y: Vec<f32>,
+    pub vx: Vec<f32>,
+    pub vy: Vec<f32>,
+    pub species: Vec<u32>,
+    pub rad: Vec<f32>,
+}
+
+impl Particles {
+    pub fn from_circles(circles: &[Circle]) -> Self {
+        Self {
+            x: circles.iter().map(|c| c.pos[0]).collect(),
+            y: circles.iter().map(|c| c.pos[1]).collect(),
+            vx: circles.iter().map(|c| c.vel[0]).collect(),
+            vy: circles.iter().map(|c| c.vel[1]).collect(),
+            species: circles.iter().map(|c| c.color.max(0) as u32).collect(),
+            rad: circles.iter().map(|c| c.rad).collect(),
+        }
+    }
+
+    pub fn to_circles(&self) -> Vec<Circle> {
+        (0..self.len())
+            .map(|i| Circle {
+                color: self.species[i] as i32,
+                rad: self.rad[i],
+                pos: [self.x[i], self.y[i]],
+                vel: [self.vx[i], self.vy[i]],
+            })
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.x.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.x.is_empty()
+    }
+}
+
+// lanes past the end sit at infinity, where every test masks them out
+fn load(v: &[f32], start: usize, fill: f32) -> f32x8 {
+    let mut lanes = [fill; LANES];
+    let end = v.len().min(start + LANES);
+    lanes[..end - start].copy_from_slice(&v[start..end]);
+    f32x8::from(lanes)
+}
+
+/// SIMD version of `sim::step`, testing eight pairs per instruction and
+/// spreading particles over every core. The one difference from the scalar
+/// port is that `Contact::Push` nudges are summed from each particle's
+/// position at the start of the step rather than applied as the loop goes,
+/// so the two agree closely rather than exactly when particles overlap.
+pub fn step(prev: &Particles, next: &mut Particles, rules: &Rules, params: &Params, dt: f32) {
+    let n = prev.len();
+    next.species.clone_from(&prev.species);
+    next.rad = prev.species.iter().map(|&s| rules.properties(s as usize).radius).collect();
+    let rows: Vec<Row> = (0..rules.species()).map(|s| Row::new(rules, params, s)).collect();
+    let masses: Vec<f32> = (0..rules.species()).map(|s| rules.properties(s).mass).collect();
+    let mut out = vec![[0.0; 4]; n];
+    out.par_iter_mut()
+        .enumerate()
+        .for_each(|(i, out)| *out = step_particle(prev, i, &rows, &masses, rules, params, dt));
+    next.x = out.iter().map(|o| o[0]).collect();
+    next.y = out.iter().map(|o| o[1]).collect();
+    next.vx = out.iter().map(|o| o[2]).collect();
+    next.vy = out.iter().map(|o| o[3]).collect();
+}
+
+// what a species' particles look up by each neighbour's species, built once
+// a step rather than once a particle
+struct Row {
+    // rules scaled by ff
+    acc: Vec<f32>,
+    // kernel indices, as floats to compare lanes against
+    shapes: Vec<f32>,
+    // the distinct kernels among them, in index order
+    used: Vec<Kernel>,
+}
+
+impl Row {
+    fn new(rules: &Rules, params: &Params, si: usize) -> Self {
+        let kernels: Vec<Kernel> = (0..rules.species()).map(|b| rules.kernel(si, b)).collect();
+        let mut used = kernels.clone();
+        used.sort_by_key(|k| k.index());
+        used.dedup();
+        Self {
+            acc: (0..rules.species()).map(|b| params.ff * rules.get(si, b)).collect(),
+            shapes: kernels.iter().map(|k| k.index() as f32).collect(),
+            used,
+        }
+    }
+}
+
+/// Position and velocity of particle `i` after one step, as [x, y, vx, vy],
+/// `rows` and `masses` being indexed by species.
+fn step_particle(
+    p: &Particles,
+    i: usize,
+    rows: &[Row],
+    masses: &[f32],
+    rules: &Rules,
+    params: &Params,
+    dt: f32,
+) -> [f32; 4] {
+    let rmax = f32x8::splat(params.rmax);
+    let one = f32x8::splat(1.0);
+    // d < 1 with d = len / rmax, squared
+    let range2 = f32x8::splat(params.rmax * params.rmax);
+
+    let si = p.species[i] as usize;
+    let row = &rows[si];
+
+    let contact = params.contact();
+    let stiffness = f32x8::splat(params.stiffness);
+    let (radi, massi) = (f32x8::splat(p.rad[i]), f32x8::splat(rules.properties(si).mass));
+
+    let (xi, yi) = (f32x8::splat(p.x[i]), f32x8::splat(p.y[i]));
+    let me = f32x8::splat(i as f32);
+    let mut ax = f32x8::ZERO;
+    let mut ay = f32x8::ZERO;
+    let mut px = f32x8::ZERO;
+    let mut py = f32x8::ZERO;
+
+    for start in (0..p.len()).step_by(LANES) {
+        let dx = xi - load(&p.x, start, f32::INFINITY);
+        let dy = yi - load(&p.y, start, f32::INFINITY);
+        let len2 = dx * dx + dy * dy;
+        // most chunks are entirely out of range, so test that cheaply first
+        let near = len2.cmp_lt(range2);
+        if near.none() {
+            continue;
+        }
+        let len = len2.sqrt();
+        let d = len / rmax;
+
+        let species = &p.species[start..p.len().min(start + LANES)];
+        let mut acc = [0.0; LANES];
+        let mut shape = [0.0; LANES];
+        let mut mass = [1.0; LANES];
+        for (k, &b) in species.iter().enumerate() {
+            acc[k] = row.acc.get(b as usize).copied().unwrap_or(0.0);
+            shape[k] = row.shapes.get(b as usize).copied().unwrap_or(0.0);
+            mass[k] = masses.get(b as usize).copied().unwrap_or(1.0);
+        }
+        let (acc, shape, mass) = (f32x8::from(acc), f32x8::from(shape), f32x8::from(mass));
+
+        let index = f32x8::splat(start as f32) + LANE_INDEX;
+        let mask = index.cmp_ne(me) & near & d.cmp_lt(one);
+
+        // coincident particles split along x like `sim::direction`
+        let coincident = len2.cmp_eq(f32x8::ZERO);
+        let nx = coincident.blend(index.cmp_lt(me).blend(one, -one), dx / len);
+        let ny = coincident.blend(f32x8::ZERO, dy / len);
+        // usually every pair in a row shares one kernel
+        let mut force = f32x8::ZERO;
+        for &kernel in &row.used {
+            let f = match kernel {
+                Kernel::Tabulated => tabulated8(rules, si, species, d),
+                kernel => force8(kernel, d, acc, params),
+            };
+            force = if row.used.len() == 1 { f } else { shape.cmp_eq(f32x8::splat(kernel.index() as f32)).blend(f, force) };
+        }
+        ax += mask.blend(nx * force, f32x8::ZERO);
+        ay += mask.blend(ny * force, f32x8::ZERO);
+
+        let overlap = (radi + load(&p.rad, start, 0.0) - len).max(f32x8::ZERO);
+        match contact {
+            Contact::Push => {
+                let push = mask & d.cmp_le(f32x8::splat(0.125));
+                px += push.blend(nx * d * 0.5, f32x8::ZERO);
+                py += push.blend(ny * d * 0.5, f32x8::ZERO);
+            }
+            Contact::Spring => {
+                ax += mask.blend(nx * stiffness * overlap, f32x8::ZERO);
+                ay += mask.blend(ny * stiffness * overlap, f32x8::ZERO);
+            }
+            Contact::Constraint => {
+                let shift = overlap * (mass / (massi + mass));
+                px += mask.blend(nx * shift, f32x8::ZERO);
+                py += mask.blend(ny * shift, f32x8::ZERO);
+            }
+        }
+    }
+
+    let mut pos = [p.x[i] + px.reduce_add(), p.y[i] + py.reduce_add()];
+    let mut vel = [p.vx[i], p.vy[i]];
+    let mut a = [ax.reduce_add(), ay.reduce_add()];
+
+    let r = (pos[0] * pos[0] + pos[1] * pos[1]).sqrt();
+    if r > params.world_size {
+        let k = (r - params.world_size) * 25.0 / r;
+        a = [a[0] - pos[0] * k, a[1] - pos[1] * k];
+    }
+    let props = rules.properties(si);
+    let f = fields::force(rules.fields(), pos, vel, props.mass);
+    a = [a[0] + f[0], a[1] + f[1]];
+
+    vel = params.friction().integrate(vel, a, params.mu, props.drag, params.rmax * dt / props.mass, dt);
+    vel = friction::cap(vel, props.max_speed);
+    pos = [pos[0] + vel[0] * dt, pos[1] + vel[1] * dt];
+    (pos, vel) = fields::collide(rules.obstacles(), pos, vel, props.radius);
+    [pos[0], pos[1], vel[0], vel[1]]
+}
+
+// curve lookups don't vectorise, so tabulated pairs go one lane at a time
+fn tabulated8(rules: &Rules, si: usize, species: &[u32], d: f32x8) -> f32x8 {
+    let d = d.to_array();
+    let mut force = [0.0; LANES];
+    for (k, &b) in species.iter().enumerate() {
+        if (b as usize) < rules.species() {
+            force[k] = curves::sample(rules.curve(si, b as usize), d[k]);
+        }
+    }
+    f32x8::from(force)
+}
+
+// `Kernel::force` eight pairs at a time
+fn force8(kernel: Kernel, d: f32x8, acc: f32x8, params: &Params) -> f32x8 {
+    let one = f32x8::splat(1.0);
+    let rmin = f32x8::splat(params.rmin);
+    let racc = f32x8::splat(params.racc);
+    let band = match kernel {
+        Kernel::Triangle => acc * (one - (d * 2.0 - one - rmin).abs() / (one - rmin)),
+        Kernel::Cosine => acc * 0.5 * (one - (f32x8::splat(TAU) * (d - rmin) / (one - rmin)).cos()),
+        Kernel::LennardJones => {
+            let r = rmin / d.max(f32x8::splat(1e-6));
+            let s = (r * r * r * r * r * r).min(f32x8::splat(2.0));
+            return d.cmp_lt(one).blend(racc * s * (s - one) + acc * s, f32x8::ZERO);
+        }
+        Kernel::Gaussian => {
+            let x = (d - (one + rmin) * 0.5) / ((one - rmin) * 0.25);
+            acc * (-(x * x)).exp()
+        }
+        Kernel::InverseSquare => acc * (rmin / d) * (rmin / d),
+        // looked up by `tabulated8` instead
+        Kernel::Tabulated => return f32x8::ZERO,
+    };
+    let force = d.cmp_lt(rmin).blend(racc * (one - d / rmin), band);
+    d.cmp_lt(one).blend(force, f32x8::ZERO)
+}
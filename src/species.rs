@@ -0,0 +1,46 @@
+/// Physical properties shared by every particle of a species. Laid out for
+/// the species buffer `compute_main` reads.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Species {
+    /// divides every force on the particle, so heavier species respond
+    /// more slowly
+    pub mass: f32,
+    /// scales the friction `mu` gives
+    pub drag: f32,
+    /// speed the particle is held to after each step, unlimited if left out
+    #[serde(skip_serializing_if = "unlimited")]
+    pub max_speed: f32,
+    /// radius the particle is drawn with
+    pub radius: f32,
+}
+
+// serde can't write an infinite float to every format
+fn unlimited(speed: &f32) -> bool {
+    speed.is_infinite()
+}
+
+impl Species {
+    /// Checks the values steps divide by or cap speeds to: a positive,
+    /// finite `mass` and `radius`, a finite `drag` that isn't negative and a
+    /// positive `max_speed`, which alone may be infinite.
+    pub fn validate(&self) -> Result<(), String> {
+        let finite = self.mass.is_finite() && self.drag.is_finite() && self.radius.is_finite();
+        if finite && self.mass > 0.0 && self.drag >= 0.0 && self.max_speed > 0.0 && self.radius > 0.0 {
+            return Ok(());
+        }
+        Err(format!("{:?} needs a finite, positive mass and radius, a finite drag that isn't negative and a positive max_speed", self))
+    }
+}
+
+impl Default for Species {
+    fn default() -> Self {
+        Self {
+            mass: 1.0,
+            drag: 1.0,
+            max_speed: f32::INFINITY,
+            radius: 0.125,
+        }
+    }
+}
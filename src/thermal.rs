@@ -0,0 +1,79 @@
+use std::f32::consts::TAU;
+
+use crate::friction::cap;
+use crate::params::Params;
+use crate::species::Species;
+
+/// Time over which the thermostat brings the kinetic temperature back to
+/// `Params::temperature`.
+pub const RELAXATION: f32 = 0.1;
+
+/// Random numbers shared with the shader: every particle has its own stream
+/// from the seed, and `clock` picks the step's draw from it, so the noise
+/// depends neither on the backend nor on the order particles are stepped in.
+/// `pcg` in shader.wgsl.
+pub fn pcg(v: u32) -> u32 {
+    let state = v.wrapping_mul(747796405).wrapping_add(2891336453);
+    let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277803737);
+    (word >> 22) ^ word
+}
+
+/// Folds a 64-bit seed into the 32 bits the shader works with.
+pub fn fold(seed: u64) -> u32 {
+    (seed ^ (seed >> 32)) as u32
+}
+
+/// Two independent standard normal numbers for particle `i` at step `clock`.
+/// `gaussian` in shader.wgsl.
+pub fn gaussian(seed: u32, clock: u32, i: u32) -> [f32; 2] {
+    let a = pcg(pcg(pcg(clock) ^ i) ^ seed);
+    let b = pcg(a);
+    // (0, 1] so the log is finite
+    let u = ((a >> 8) + 1) as f32 / (1 << 24) as f32;
+    let v = (b >> 8) as f32 / (1 << 24) as f32;
+    let r = (-2.0 * u.ln()).sqrt();
+    [r * (TAU * v).cos(), r * (TAU * v).sin()]
+}
+
+/// Kinetic temperature in two dimensions, the mean of `m |v|^2 / 2` per
+/// particle, from each particle's velocity and mass.
+pub fn kinetic(particles: impl Iterator<Item = ([f32; 2], f32)>) -> f32 {
+    let (sum, n) = particles.fold((0.0, 0), |(sum, n), (v, m)| (sum + m * (v[0] * v[0] + v[1] * v[1]), n + 1));
+    if n == 0 {
+        0.0
+    } else {
+        sum / (2 * n) as f32
+    }
+}
+
+/// What every particle's thermal update in a step shares.
+#[derive(Clone, Copy, Debug)]
+pub struct Frame {
+    pub seed: u32,
+    pub clock: u32,
+    /// the thermostat's factor, from `rescale`
+    pub scale: f32,
+}
+
+/// Factor the thermostat scales every velocity by, or 1 when it's off or
+/// nothing moves. Along with the `2 temperature dt` the noise adds on
+/// average, it moves `kinetic` a fraction `dt / RELAXATION` of the way to
+/// the target, so the two together settle on the target.
+pub fn rescale(params: &Params, kinetic: f32, dt: f32) -> f32 {
+    if !params.thermostat() || kinetic <= 0.0 {
+        return 1.0;
+    }
+    let t = params.temperature;
+    let next = kinetic + dt / RELAXATION * (t - kinetic) - 2.0 * t * dt;
+    (next / kinetic).max(0.0).sqrt()
+}
+
+/// Velocity of particle `i` after the thermal part of a step: scaled by the
+/// thermostat, then kicked by Gaussian noise whose variance per axis is
+/// `2 temperature dt / mass`. `thermal_main` in shader.wgsl.
+pub fn kick(vel: [f32; 2], i: usize, species: &Species, params: &Params, frame: &Frame, dt: f32) -> [f32; 2] {
+    let sigma = (2.0 * params.temperature * dt / species.mass).sqrt();
+    let g = gaussian(frame.seed, frame.clock, i as u32);
+    let scale = frame.scale;
+    cap([vel[0] * scale + g[0] * sigma, vel[1] * scale + g[1] * sigma], species.max_speed)
+}
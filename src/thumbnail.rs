@@ -0,0 +1,33 @@
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+use crate::circle::Circle;
+use crate::COLORS;
+
+/// Draws every circle as a one pixel dot in its species colour on a black
+/// `size` x `size` RGB image covering [-extent, extent] on both axes.
+pub fn render(circles: &[Circle], size: u32, extent: f32) -> Vec<u8> {
+    let mut pixels = vec![0u8; (size * size * 3) as usize];
+    for c in circles {
+        let x = ((c.pos[0] / extent + 1.0) * 0.5 * size as f32).floor();
+        let y = ((1.0 - c.pos[1] / extent) * 0.5 * size as f32).floor();
+        if !(0.0..size as f32).contains(&x) || !(0.0..size as f32).contains(&y) {
+            continue;
+        }
+        let at = ((y as u32 * size + x as u32) * 3) as usize;
+        let color = COLORS[c.color as usize % COLORS.len()];
+        pixels[at..at + 3].copy_from_slice(&color[..3]);
+    }
+    pixels
+}
+
+pub fn save_png(path: impl AsRef<Path>, size: u32, pixels: &[u8]) -> io::Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, size, size);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(pixels)?;
+    Ok(())
+}
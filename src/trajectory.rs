@@ -0,0 +1,241 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::circle::Circle;
+
+const MAGIC: &[u8; 4] = b"PLTR";
+// version 1 has a fixed time step in the header instead of each frame's time
+const VERSION: u16 = 2;
+
+// positions are quantised over [-POS_RANGE, POS_RANGE] in units of world_size,
+// velocities over [-VEL_RANGE, VEL_RANGE] and radii over [0, RAD_RANGE]
+const POS_RANGE: f32 = 2.0;
+const VEL_RANGE: f32 = 16.0;
+const RAD_RANGE: f32 = 4.0;
+
+/// Recording metadata, stored at the head of the file.
+#[derive(Clone, Debug)]
+pub struct Header {
+    /// simulation steps between recorded frames
+    pub every: u32,
+    pub world_size: f32,
+    /// share code of the system being recorded
+    pub code: String,
+}
+
+pub struct Frame {
+    pub step: u64,
+    /// simulated seconds since the run started, which steps of varying
+    /// length make more than `step` times a time step
+    pub time: f64,
+    pub circles: Vec<Circle>,
+}
+
+/// Streams quantised, gzip-compressed frames to a file:
+/// header, then per frame the step number, time, particle count and
+/// particles.
+pub struct Recorder {
+    out: GzEncoder<BufWriter<File>>,
+    world_size: f32,
+    frames: u64,
+}
+
+impl Recorder {
+    pub fn create(path: impl AsRef<Path>, header: &Header) -> io::Result<Self> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut out = GzEncoder::new(BufWriter::new(File::create(path)?), Compression::fast());
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        out.write_all(&header.every.to_le_bytes())?;
+        out.write_all(&header.world_size.to_le_bytes())?;
+        out.write_all(&(header.code.len() as u32).to_le_bytes())?;
+        out.write_all(header.code.as_bytes())?;
+        Ok(Self {
+            out,
+            world_size: header.world_size,
+            frames: 0,
+        })
+    }
+
+    pub fn record(&mut self, step: u64, time: f64, circles: &[Circle]) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(20 + circles.len() * 11);
+        bytes.extend_from_slice(&step.to_le_bytes());
+        bytes.extend_from_slice(&time.to_le_bytes());
+        bytes.extend_from_slice(&(circles.len() as u32).to_le_bytes());
+        let pos_scale = i16::MAX as f32 / (POS_RANGE * self.world_size);
+        let vel_scale = i16::MAX as f32 / VEL_RANGE;
+        for c in circles {
+            for v in c.pos {
+                bytes.extend_from_slice(&quantise(v * pos_scale).to_le_bytes());
+            }
+            for v in c.vel {
+                bytes.extend_from_slice(&quantise(v * vel_scale).to_le_bytes());
+            }
+            let rad = (c.rad / RAD_RANGE * u16::MAX as f32).round().clamp(0.0, u16::MAX as f32) as u16;
+            bytes.extend_from_slice(&rad.to_le_bytes());
+            bytes.push(c.color.clamp(0, u8::MAX as i32) as u8);
+        }
+        self.out.write_all(&bytes)?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        self.out.finish()?.flush()
+    }
+}
+
+// NaN goes to zero, infinities to the ends of the range
+fn quantise(v: f32) -> i16 {
+    v.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16
+}
+
+/// Reads a whole recording into memory. A recording cut off part way, say
+/// by the viewer being killed, gives the frames written in full.
+pub fn load(path: impl AsRef<Path>) -> io::Result<(Header, Vec<Frame>)> {
+    let mut input = GzDecoder::new(BufReader::new(File::open(path)?));
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+    let mut magic = [0; 4];
+    input.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid("not a trajectory file"));
+    }
+    let version = u16::from_le_bytes(take(&mut input)?);
+    if !(1..=VERSION).contains(&version) {
+        return Err(invalid("unsupported trajectory version"));
+    }
+    let dt = if version == 1 { f32::from_le_bytes(take(&mut input)?) } else { 0.0 };
+    let every = u32::from_le_bytes(take(&mut input)?);
+    let world_size = f32::from_le_bytes(take(&mut input)?);
+    let len = u32::from_le_bytes(take(&mut input)?) as usize;
+    let mut code = vec![0; len];
+    input.read_exact(&mut code)?;
+    let code = String::from_utf8(code).map_err(|_| invalid("share code is not utf-8"))?;
+    let header = Header { every, world_size, code };
+
+    let pos_scale = POS_RANGE * world_size / i16::MAX as f32;
+    let vel_scale = VEL_RANGE / i16::MAX as f32;
+    let mut frames = Vec::new();
+    loop {
+        let frame = (|| {
+            let step = u64::from_le_bytes(take(&mut input)?);
+            let time = if version == 1 { step as f64 * dt as f64 } else { f64::from_le_bytes(take(&mut input)?) };
+            let count = u32::from_le_bytes(take(&mut input)?) as usize;
+            let mut raw = vec![0; count * 11];
+            input.read_exact(&mut raw)?;
+            Ok::<_, io::Error>((step, time, raw))
+        })();
+        let (step, time, raw) = match frame {
+            Ok(frame) => frame,
+            Err(e) if truncated(&e) => break,
+            Err(e) => return Err(e),
+        };
+        let circles = raw
+            .chunks_exact(11)
+            .map(|p| {
+                let i16_at = |k: usize| i16::from_le_bytes([p[k], p[k + 1]]) as f32;
+                Circle {
+                    pos: [i16_at(0) * pos_scale, i16_at(2) * pos_scale],
+                    vel: [i16_at(4) * vel_scale, i16_at(6) * vel_scale],
+                    rad: u16::from_le_bytes([p[8], p[9]]) as f32 / u16::MAX as f32 * RAD_RANGE,
+                    color: p[10] as i32,
+                }
+            })
+            .collect();
+        frames.push(Frame { step, time, circles });
+    }
+
+    Ok((header, frames))
+}
+
+// the end of the data, or of the gzip stream when the file was cut off
+// before its trailer
+fn truncated(e: &io::Error) -> bool {
+    e.kind() == io::ErrorKind::UnexpectedEof
+}
+
+fn take<const N: usize>(input: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    input.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Playback position over a loaded recording, as a simulated time between
+/// the first frame's and the last's, so it plays at the pace it was
+/// recorded at whatever steps the run took. Needs at least one frame.
+pub struct Replay {
+    pub header: Header,
+    frames: Vec<Frame>,
+    time: f64,
+    direction: f64,
+}
+
+impl Replay {
+    pub fn new(header: Header, frames: Vec<Frame>) -> Self {
+        let time = frames[0].time;
+        Self {
+            header,
+            frames,
+            time,
+            direction: 1.0,
+        }
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// The last frame at or before the current time.
+    pub fn index(&self) -> usize {
+        self.frames.partition_point(|f| f.time <= self.time).max(1) - 1
+    }
+
+    pub fn current(&self) -> &Frame {
+        &self.frames[self.index()]
+    }
+
+    pub fn max_particles(&self) -> usize {
+        self.frames.iter().map(|f| f.circles.len()).max().unwrap_or(0)
+    }
+
+    /// Moves on by `seconds` of simulated time. Returns false once it has
+    /// run into either end.
+    pub fn advance(&mut self, seconds: f32) -> bool {
+        let (start, end) = (self.frames[0].time, self.frames[self.frame_count() - 1].time);
+        self.time += self.direction * seconds as f64;
+        let inside = self.time > start && self.time < end;
+        self.time = self.time.clamp(start, end);
+        inside
+    }
+
+    pub fn step(&mut self, frames: i64) {
+        let end = (self.frame_count() - 1) as i64;
+        self.time = self.frames[(self.index() as i64 + frames).clamp(0, end) as usize].time;
+    }
+
+    /// Jumps by a fraction of the whole recording.
+    pub fn scrub(&mut self, fraction: f32) {
+        self.step((fraction * self.frame_count() as f32) as i64);
+    }
+
+    pub fn reverse(&mut self) {
+        self.direction = -self.direction;
+    }
+
+    pub fn reversed(&self) -> bool {
+        self.direction < 0.0
+    }
+}
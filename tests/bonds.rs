@@ -0,0 +1,187 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use physics::bonds::{self, Bond, BondRule, Spring};
+use physics::circle::Circle;
+use physics::code::Setup;
+use physics::contact::Contact;
+use physics::lifecycle::Lifecycle;
+use physics::params::Params;
+use physics::rules::Rules;
+use physics::sim::DT;
+
+mod common;
+
+use common::{backends, pairs, sorted, still, TOLERANCE};
+
+fn row(count: usize, gap: f32) -> Vec<Circle> {
+    (0..count)
+        .map(|i| Circle { color: (i % 2) as i32, rad: 0.125, pos: [i as f32 * gap, 0.0], vel: [0.0, 0.0] })
+        .collect()
+}
+
+fn gap(circles: &[Circle], i: usize, j: usize) -> f32 {
+    let (a, b) = (circles[i].pos, circles[j].pos);
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)).sqrt()
+}
+
+#[test]
+fn springs_pull_toward_their_rest_length() {
+    let spring = Spring { rest: 1.0, stiffness: 20.0, ..Spring::default() };
+    for (backend, mut sim) in backends(&row(2, 3.0), &Rules::new(2), &still()) {
+        sim.set_bonds(&bonds::chain(&[0, 1], spring));
+        sim.step(20);
+        let after = sim.download();
+        assert!(gap(&after, 0, 1) < 3.0, "{}: {:?}", backend.name(), after);
+        // equal and opposite, so the middle stays put
+        assert!((after[0].pos[0] + after[1].pos[0] - 3.0).abs() < TOLERANCE, "{}: {:?}", backend.name(), after);
+        assert_eq!(sim.bonds().len(), 1, "{}", backend.name());
+    }
+}
+
+#[test]
+fn bonds_break_past_their_strength() {
+    let weak = Spring { rest: 1.0, stiffness: 0.0, strength: 0.5 };
+    let strong = Spring { strength: 5.0, ..weak };
+    for (backend, mut sim) in backends(&row(3, 2.0), &Rules::new(2), &still()) {
+        sim.set_bonds(&[Bond::new(0, 1, weak), Bond::new(1, 2, strong)]);
+        sim.step(1);
+        assert_eq!(sim.bonds(), [Bond::new(1, 2, strong)], "{}", backend.name());
+    }
+}
+
+#[test]
+fn invalid_bonds_are_dropped() {
+    for (backend, mut sim) in backends(&row(3, 1.0), &Rules::new(2), &still()) {
+        let spring = Spring::default();
+        sim.set_bonds(&[Bond::new(0, 3, spring), Bond::new(1, 1, spring), Bond::new(0, 2, spring)]);
+        assert_eq!(sim.bonds(), [Bond::new(0, 2, spring)], "{}", backend.name());
+        sim.upload(&row(3, 1.0)).unwrap();
+        assert!(sim.bonds().is_empty(), "{}", backend.name());
+    }
+}
+
+#[test]
+fn formation_respects_valency() {
+    // a row of species 0 and 1 alternating, bonding neighbours into a chain
+    // but at most two bonds each
+    let mut rules = Rules::new(2);
+    let rule = BondRule { a: 0, b: 1, range: 1.5, valency: 2, spring: Spring { rest: 1.0, ..Spring::default() } };
+    rules.set_bond_rules(vec![rule]).unwrap();
+    for (backend, mut sim) in backends(&row(6, 1.0), &rules, &still()) {
+        // each particle proposes its nearest neighbour, the earlier of two
+        // as near, so the chain zips up from its start a link a step
+        let chain = [(0, 1), (1, 2), (2, 3), (3, 4), (4, 5)];
+        for links in 1..=chain.len() {
+            sim.step(1);
+            assert_eq!(pairs(&sim.bonds()), chain[..links], "{}", backend.name());
+        }
+        let formed = sorted(sim.bonds());
+        sim.step(5);
+        assert_eq!(sorted(sim.bonds()), formed, "{}", backend.name());
+    }
+    rules.set_bond_rules(vec![BondRule { valency: 1, ..rule }]).unwrap();
+    for (backend, mut sim) in backends(&row(6, 1.0), &rules, &still()) {
+        sim.step(5);
+        assert_eq!(pairs(&sim.bonds()), [(0, 1), (2, 3), (4, 5)], "{}", backend.name());
+    }
+}
+
+#[test]
+fn nearest_partners_bond_whatever_their_order() {
+    // 1 and 2 sit closest, so they bond rather than each bonding to the
+    // particle past it, and being full leave 0 and 3 none to bond to
+    let mut rules = Rules::new(2);
+    rules.set_bond_rules(vec![BondRule { a: 0, b: 1, range: 1.5, valency: 1, spring: Spring::default() }]).unwrap();
+    let circles: Vec<Circle> = row(4, 1.0)
+        .into_iter()
+        .zip([0.0, 1.0, 1.1, 2.0])
+        .map(|(c, x)| Circle { pos: [x, 0.0], ..c })
+        .collect();
+    for (backend, mut sim) in backends(&circles, &rules, &still()) {
+        sim.step(1);
+        assert_eq!(pairs(&sim.bonds()), [(1, 2)], "{}", backend.name());
+        sim.step(3);
+        assert_eq!(pairs(&sim.bonds()), [(1, 2)], "{}", backend.name());
+    }
+}
+
+#[test]
+fn bonds_follow_their_particles_through_deaths() {
+    // species 1 dies young, taking its bonds with it
+    let mut rules = Rules::new(2);
+    rules.set_lifecycle(1, Lifecycle { lifespan: 0.5 * DT, ..Lifecycle::default() });
+    let spring = Spring { rest: 2.0, stiffness: 0.0, ..Spring::default() };
+    for (backend, mut sim) in backends(&row(5, 1.0), &rules, &still()) {
+        sim.set_bonds(&[Bond::new(0, 2, spring), Bond::new(1, 2, spring), Bond::new(2, 4, spring)]);
+        sim.step(1);
+        assert_eq!(sim.download().len(), 3, "{}", backend.name());
+        assert_eq!(sorted(sim.bonds()), [Bond::new(0, 1, spring), Bond::new(1, 2, spring)], "{}", backend.name());
+    }
+}
+
+#[test]
+fn backends_agree_on_bonds() {
+    let mut rng = StdRng::seed_from_u64(9);
+    let mut rules = Rules::new(3);
+    rules
+        .set_bond_rules(vec![
+            BondRule { a: 0, b: 1, range: 0.9, valency: 3, spring: Spring { rest: 0.6, stiffness: 30.0, strength: 0.8 } },
+            BondRule { a: 2, b: 2, range: 0.7, valency: 2, spring: Spring { rest: 0.5, stiffness: 50.0, ..Spring::default() } },
+        ])
+        .unwrap();
+    let circles: Vec<Circle> = (0..200)
+        .map(|_| Circle {
+            color: rng.gen_range(0..3),
+            rad: 0.125,
+            pos: [rng.gen_range(-5.0..5.0), rng.gen_range(-5.0..5.0)],
+            vel: [0.0, 0.0],
+        })
+        .collect();
+    // springs pull particles into each other, where the SIMD kernel only
+    // approximates Contact::Push
+    let mut params = still();
+    params.set_contact(Contact::Constraint);
+    params.mu = 5.0;
+    let (_, bonds) = common::assert_backends_agree(&circles, &rules, &params, 0, 20);
+    assert!(!bonds.is_empty());
+}
+
+#[test]
+fn mesh_rests_at_the_current_distances() {
+    let pos = [[0.0, 0.0], [1.0, 0.0], [0.0, 2.0], [5.0, 5.0]];
+    let mesh = bonds::mesh(&pos, &[0, 1, 2, 3], 2.5, Spring::default());
+    let pairs: Vec<(u32, u32, f32)> = mesh.iter().map(|b| (b.a, b.b, b.rest)).collect();
+    assert_eq!(pairs, [(0, 1, 1.0), (0, 2, 2.0), (1, 2, 5.0f32.sqrt())]);
+    // at rest, so no spring pushes
+    assert!(bonds::accelerations(&mesh, &pos).iter().all(|a| a[0].abs() < 1e-6 && a[1].abs() < 1e-6));
+}
+
+#[test]
+fn bond_rules_survive_codes_and_presets() {
+    let mut rules = Rules::new(2);
+    rules
+        .set_bond_rules(vec![
+            BondRule { a: 0, b: 1, range: 1.0, valency: 4, spring: Spring { rest: 0.5, stiffness: 10.0, strength: 0.25 } },
+            BondRule { a: 1, b: 1, range: 0.5, valency: 1, spring: Spring::default() },
+        ])
+        .unwrap();
+    let setup = Setup { seed: 1, rules, params: Params::default() };
+    for back in common::round_trips(&setup) {
+        assert_eq!(back, setup);
+    }
+}
+
+#[test]
+fn invalid_bond_rules_are_rejected() {
+    let mut rules = Rules::new(2);
+    let rule = BondRule { a: 0, b: 1, range: 1.0, valency: 2, spring: Spring::default() };
+    assert!(rules.set_bond_rules(vec![BondRule { b: 2, ..rule }]).is_err());
+    assert!(rules.set_bond_rules(vec![BondRule { range: 0.0, ..rule }]).is_err());
+    assert!(rules.set_bond_rules(vec![BondRule { valency: 0, ..rule }]).is_err());
+    assert!(rules.set_bond_rules(vec![BondRule { valency: bonds::MAX_VALENCY as u32 + 1, ..rule }]).is_err());
+    assert!(rules.set_bond_rules(vec![rule; bonds::MAX_BOND_RULES + 1]).is_err());
+    assert!(rules.set_bond_rules(vec![rule]).is_ok());
+    let text = "matrix = [[0.0]]\nbonds = [{ a = 0, b = 0, range = 1.0, valency = 2, rest = 0.4 }]\n";
+    let rules = toml::from_str::<Rules>(text).unwrap();
+    assert_eq!(rules.bond_rules()[0].spring, Spring { rest: 0.4, ..Spring::default() });
+}
@@ -0,0 +1,84 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+
+use physics::code::{self, CodeError, Setup};
+use physics::gpu;
+use physics::lifecycle::Lifecycle;
+use physics::params::Params;
+use physics::rules::Rules;
+use physics::species::Species;
+
+fn setup(rules: Rules) -> Setup {
+    Setup { seed: 7, rules, params: Params::default() }
+}
+
+#[test]
+fn codes_round_trip() {
+    let mut rules = Rules::new(3);
+    rules.set(0, 1, 3.5);
+    rules.set(2, 0, -4.0);
+    let setup = setup(rules);
+    assert_eq!(code::decode(&code::encode(&setup).unwrap()).unwrap(), setup);
+}
+
+#[test]
+fn setups_codes_cant_hold_are_refused() {
+    assert!(matches!(code::encode(&setup(Rules::new(256))), Err(CodeError::Species(256))));
+    let mut rules = Rules::new(2);
+    rules.set(1, 0, 4.5);
+    assert!(matches!(code::encode(&setup(rules.clone())), Err(CodeError::Rule(1, 0, _))));
+    rules.set(1, 0, f32::NAN);
+    assert!(matches!(code::encode(&setup(rules)), Err(CodeError::Rule(1, 0, _))));
+    let mut bad = setup(Rules::new(2));
+    bad.params.rmin = 1.0;
+    assert!(matches!(code::encode(&bad), Err(CodeError::Params(_))));
+    let mut bad = setup(Rules::new(2));
+    bad.params.max_particles = gpu::MAX_PARTICLES as u32 + 1;
+    assert!(matches!(code::encode(&bad), Err(CodeError::Params(_))));
+}
+
+#[test]
+fn codes_with_bad_params_are_rejected() {
+    let good = code::encode(&setup(Rules::new(2))).unwrap();
+    // rmin follows the version, species count, racc and rmax
+    for rmin in [1.0f32, -0.5, f32::NAN] {
+        let mut bytes = URL_SAFE_NO_PAD.decode(&good).unwrap();
+        bytes[10..14].copy_from_slice(&rmin.to_le_bytes());
+        let bad = URL_SAFE_NO_PAD.encode(bytes);
+        assert!(matches!(code::decode(&bad), Err(CodeError::Params(_))), "{}", rmin);
+    }
+}
+
+#[test]
+fn codes_with_bad_species_are_rejected() {
+    let mut rules = Rules::new(2);
+    rules.set_properties(1, Species { mass: 2.75, ..Species::default() });
+    let good = URL_SAFE_NO_PAD.decode(code::encode(&setup(rules.clone())).unwrap()).unwrap();
+    // found by its mass, followed by its drag, max_speed and radius
+    let at = good.windows(4).position(|w| w == 2.75f32.to_le_bytes()).unwrap();
+    for (offset, v) in [(0, 0.0f32), (0, f32::NAN), (0, f32::INFINITY), (4, -1.0), (4, f32::INFINITY), (8, 0.0), (12, -0.125), (12, f32::INFINITY)] {
+        let mut bytes = good.clone();
+        bytes[at + offset..at + offset + 4].copy_from_slice(&v.to_le_bytes());
+        let bad = URL_SAFE_NO_PAD.encode(bytes);
+        assert!(matches!(code::decode(&bad), Err(CodeError::Properties(_))), "{} at {}", v, offset);
+    }
+    rules.set_properties(0, Species { radius: 0.0, ..Species::default() });
+    assert!(matches!(code::encode(&setup(rules)), Err(CodeError::Properties(_))));
+}
+
+#[test]
+fn codes_with_bad_lifecycles_are_rejected() {
+    let mut rules = Rules::new(2);
+    rules.set_lifecycle(1, Lifecycle { lifespan: 2.75, partner: Some(0), ..Lifecycle::default() });
+    let good = URL_SAFE_NO_PAD.decode(code::encode(&setup(rules.clone())).unwrap()).unwrap();
+    // found by its lifespan, followed by its range and period
+    let at = good.windows(4).position(|w| w == 2.75f32.to_le_bytes()).unwrap();
+    for (offset, v) in [(0, f32::NAN), (0, -1.0f32), (4, 0.0), (4, f32::NAN), (8, -0.5)] {
+        let mut bytes = good.clone();
+        bytes[at + offset..at + offset + 4].copy_from_slice(&v.to_le_bytes());
+        let bad = URL_SAFE_NO_PAD.encode(bytes);
+        assert!(matches!(code::decode(&bad), Err(CodeError::Lifecycle(_))), "{} at {}", v, offset);
+    }
+    rules.set_lifecycle(0, Lifecycle { range: -1.0, ..Lifecycle::default() });
+    assert!(matches!(code::encode(&setup(rules)), Err(CodeError::Lifecycle(_))));
+}
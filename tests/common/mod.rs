@@ -0,0 +1,128 @@
+// helpers shared by the integration tests; each test binary builds its own
+// copy and uses only some of them
+#![allow(dead_code)]
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use physics::backend::{Backend, SimBackend};
+use physics::bonds::Bond;
+use physics::circle::Circle;
+use physics::code::{self, Setup};
+use physics::params::Params;
+use physics::rules::Rules;
+use physics::sim::Neighbours;
+
+pub const TOLERANCE: f32 = 1e-3;
+
+// every backend that can run here; the GPU one is missing without an adapter
+pub fn backends(circles: &[Circle], rules: &Rules, params: &Params) -> Vec<(Backend, Box<dyn SimBackend>)> {
+    backends_with(circles, rules, params, Neighbours::All)
+}
+
+pub fn backends_with(
+    circles: &[Circle],
+    rules: &Rules,
+    params: &Params,
+    neighbours: Neighbours,
+) -> Vec<(Backend, Box<dyn SimBackend>)> {
+    Backend::ALL
+        .into_iter()
+        .filter_map(|backend| match backend.create(circles, rules, params, neighbours) {
+            Ok(sim) => Some((backend, sim)),
+            Err(e) => {
+                eprintln!("{}, skipping it", e);
+                None
+            }
+        })
+        .collect()
+}
+
+// no forces and no friction, so particles at rest stay where they are
+pub fn still() -> Params {
+    let mut params = Params::default();
+    params.racc = 0.0;
+    params.mu = 0.0;
+    params
+}
+
+// a jittered lattice, spaced so no two particles push each other apart, that
+// reaches past world_size in the corners once it's wide enough
+pub fn lattice(side: usize, spacing: f32, species: u32, seed: u64) -> Vec<Circle> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let half = side as f32 * spacing / 2.0;
+    let mut circles = Vec::new();
+    for y in 0..side {
+        for x in 0..side {
+            circles.push(Circle {
+                color: rng.gen_range(0..species) as i32,
+                rad: 0.125,
+                pos: [
+                    x as f32 * spacing - half + rng.gen_range(-0.1..0.1),
+                    y as f32 * spacing - half + rng.gen_range(-0.1..0.1),
+                ],
+                vel: [rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)],
+            });
+        }
+    }
+    circles
+}
+
+// the same particles in the same order, with positions and velocities
+// within `tolerance` relative to the larger of their size and 1
+pub fn assert_within(tolerance: f32, label: &str, expected: &[Circle], actual: &[Circle]) {
+    assert_eq!(expected.len(), actual.len(), "{}", label);
+    for (i, (a, b)) in expected.iter().zip(actual).enumerate() {
+        assert_eq!(a.color, b.color, "{} particle {}", label, i);
+        for (x, y) in a.pos.iter().chain(&a.vel).zip(b.pos.iter().chain(&b.vel)) {
+            assert!((x - y).abs() <= tolerance * x.abs().max(1.0), "{} particle {}: expected {:?} got {:?}", label, i, a, b);
+        }
+    }
+}
+
+pub fn assert_close(label: &str, expected: &[Circle], actual: &[Circle]) {
+    assert_within(TOLERANCE, label, expected, actual);
+}
+
+// the bonds in order of their ends, as the GPU lists them in any order
+pub fn sorted(mut bonds: Vec<Bond>) -> Vec<Bond> {
+    bonds.sort_by_key(|b| (b.a, b.b));
+    bonds
+}
+
+// the ends of each bond, in order
+pub fn pairs(bonds: &[Bond]) -> Vec<(u32, u32)> {
+    sorted(bonds.to_vec()).iter().map(|b| (b.a, b.b)).collect()
+}
+
+// steps every backend from the same start and seed and checks each ends
+// where the first did, bonds included; returns the first's result so callers
+// can check something happened at all
+pub fn assert_backends_agree(
+    circles: &[Circle],
+    rules: &Rules,
+    params: &Params,
+    seed: u64,
+    steps: usize,
+) -> (Vec<Circle>, Vec<Bond>) {
+    let mut expected: Option<(Vec<Circle>, Vec<Bond>)> = None;
+    for (backend, mut sim) in backends(circles, rules, params) {
+        sim.set_seed(seed);
+        sim.step(steps);
+        let (after, bonds) = (sim.download(), sorted(sim.bonds()));
+        match &expected {
+            None => expected = Some((after, bonds)),
+            Some((circles, expected)) => {
+                assert_close(backend.name(), circles, &after);
+                assert_eq!(pairs(expected), pairs(&bonds), "{}", backend.name());
+            }
+        }
+    }
+    expected.expect("the CPU backends always run")
+}
+
+// the setup after a trip through a share code and after one through a preset
+pub fn round_trips(setup: &Setup) -> [Setup; 2] {
+    let decoded = code::decode(&code::encode(setup).unwrap()).unwrap();
+    let loaded = toml::from_str::<Setup>(&toml::to_string(setup).unwrap()).unwrap();
+    [decoded, loaded]
+}
@@ -0,0 +1,102 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use physics::curves;
+use physics::kernel::Kernel;
+use physics::params::Params;
+use physics::rules::{Generator, Rules};
+use physics::sim::{self, Neighbours};
+
+mod common;
+
+use common::{assert_close, backends, backends_with, lattice, TOLERANCE};
+
+const STEPS: usize = 10;
+
+#[test]
+fn seeded_scenarios_agree_across_backends() {
+    let params = Params::default();
+    for (seed, generator) in [(1, Generator::Random), (2, Generator::Symmetric), (3, Generator::Chase)] {
+        let rules = generator.generate(6, &mut StdRng::seed_from_u64(seed));
+        let circles = lattice(20, 1.3, 6, seed);
+        let mut expected = circles.clone();
+        sim::run(&mut expected, &rules, &params, sim::DT, STEPS);
+        for neighbours in Neighbours::ALL {
+            for (backend, mut sim) in backends_with(&circles, &rules, &params, neighbours) {
+                sim.step(STEPS);
+                assert_close(backend.name(), &expected, &sim.download());
+            }
+        }
+    }
+}
+
+#[test]
+fn every_kernel_agrees_across_backends() {
+    let params = Params::default();
+    let circles = lattice(20, 1.3, 6, 8);
+    let mut mixed = Generator::Random.generate(6, &mut StdRng::seed_from_u64(8));
+    let mut kernels = Kernel::ALL.into_iter().cycle();
+    let mut rng = StdRng::seed_from_u64(9);
+    for i in 0..6 {
+        for j in 0..6 {
+            mixed.set_kernel(i, j, kernels.next().unwrap());
+            let curve: Vec<f32> = (0..curves::SAMPLES).map(|_| rng.gen_range(-1.0..1.0)).collect();
+            mixed.set_curve(i, j, &curve);
+        }
+    }
+    let global = Kernel::ALL.map(|kernel| {
+        let mut rules = mixed.clone();
+        rules.set_all_kernels(kernel);
+        rules
+    });
+    for rules in global.iter().chain([&mixed]) {
+        let mut expected = circles.clone();
+        sim::run(&mut expected, rules, &params, sim::DT, STEPS);
+        for (backend, mut sim) in backends(&circles, rules, &params) {
+            sim.step(STEPS);
+            assert_close(backend.name(), &expected, &sim.download());
+        }
+    }
+}
+
+#[test]
+fn zero_rules_leave_only_drag() {
+    let params = Params::default();
+    let rules = Rules::new(6);
+    // every neighbour in range sits past rmin, where only the rules act
+    let circles = lattice(10, 2.0, 6, 5);
+    let mut expected = circles.clone();
+    for _ in 0..STEPS {
+        for c in &mut expected {
+            let speed = sim::length(c.vel);
+            let drag = sim::scale(c.vel, -params.mu * speed * params.rmax * sim::DT);
+            c.vel = sim::add(c.vel, drag);
+            c.pos = sim::add(c.pos, sim::scale(c.vel, sim::DT));
+        }
+    }
+    for neighbours in Neighbours::ALL {
+        for (backend, mut sim) in backends_with(&circles, &rules, &params, neighbours) {
+            sim.step(STEPS);
+            assert_close(backend.name(), &expected, &sim.download());
+        }
+    }
+}
+
+#[test]
+fn without_drag_or_rules_particles_coast() {
+    let mut params = Params::default();
+    params.mu = 0.0;
+    let rules = Rules::new(6);
+    let circles = lattice(10, 2.0, 6, 6);
+    for (backend, mut sim) in backends(&circles, &rules, &params) {
+        sim.step(STEPS);
+        let moved = sim.download();
+        for (a, b) in circles.iter().zip(&moved) {
+            assert_eq!(a.vel, b.vel, "{}", backend.name());
+            let travelled = sim::sub(b.pos, a.pos);
+            for (t, v) in travelled.iter().zip(a.vel) {
+                let expected = v * sim::DT * STEPS as f32;
+                assert!((t - expected).abs() <= TOLERANCE, "{}: {:?} -> {:?}", backend.name(), a, b);
+            }
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use physics::circle::Circle;
+use physics::code::Setup;
+use physics::contact::Contact;
+use physics::params::Params;
+use physics::rules::{Generator, Rules};
+use physics::species::Species;
+
+mod common;
+
+use common::backends;
+
+// only the contact model keeps particles apart: no kernel repulsion
+fn params(contact: Contact) -> Params {
+    let mut params = Params::default();
+    params.racc = 0.0;
+    params.set_contact(contact);
+    params
+}
+
+// species 0 and 1 with discs of radius 0.5
+fn discs() -> Rules {
+    let mut rules = Rules::new(2);
+    for s in 0..2 {
+        rules.set_properties(s, Species { radius: 0.5, ..Species::default() });
+    }
+    rules
+}
+
+// two discs of radius 0.5 whose centres are `gap` apart, at rest
+fn pair(gap: f32) -> Vec<Circle> {
+    vec![
+        Circle { color: 0, rad: 0.5, pos: [0.0, 0.0], vel: [0.0, 0.0] },
+        Circle { color: 1, rad: 0.5, pos: [gap, 0.0], vel: [0.0, 0.0] },
+    ]
+}
+
+fn gap(circles: &[Circle]) -> f32 {
+    let d = [circles[1].pos[0] - circles[0].pos[0], circles[1].pos[1] - circles[0].pos[1]];
+    (d[0] * d[0] + d[1] * d[1]).sqrt()
+}
+
+#[test]
+fn spring_pushes_discs_apart() {
+    for (backend, mut sim) in backends(&pair(0.6), &discs(), &params(Contact::Spring)) {
+        sim.step(400);
+        let after = sim.download();
+        assert!(gap(&after) > 0.98, "{}: {:?}", backend.name(), after);
+    }
+}
+
+#[test]
+fn constraint_removes_overlap_in_one_step() {
+    for (backend, mut sim) in backends(&pair(0.8), &discs(), &params(Contact::Constraint)) {
+        sim.step(1);
+        let after = sim.download();
+        assert!((gap(&after) - 1.0).abs() < 1e-5, "{}: {:?}", backend.name(), after);
+        // equal masses share the correction
+        assert!((after[0].pos[0] + 0.1).abs() < 1e-5, "{}: {:?}", backend.name(), after);
+    }
+}
+
+#[test]
+fn constraint_moves_the_lighter_disc_further() {
+    let mut rules = discs();
+    rules.set_properties(1, Species { mass: 3.0, radius: 0.5, ..Species::default() });
+    for (backend, mut sim) in backends(&pair(0.8), &rules, &params(Contact::Constraint)) {
+        sim.step(1);
+        let after = sim.download();
+        assert!((after[0].pos[0] + 0.15).abs() < 1e-5, "{}: {:?}", backend.name(), after);
+        assert!((after[1].pos[0] - 0.85).abs() < 1e-5, "{}: {:?}", backend.name(), after);
+    }
+}
+
+#[test]
+fn push_is_still_the_default() {
+    assert_eq!(Params::default().contact(), Contact::Push);
+}
+
+#[test]
+fn backends_agree_on_contacts() {
+    let mut rng = StdRng::seed_from_u64(9);
+    let mut rules = Generator::Random.generate(3, &mut rng);
+    for s in 0..3 {
+        rules.set_properties(s, Species { radius: 0.2 + 0.1 * s as f32, mass: 1.0 + s as f32, ..Species::default() });
+    }
+    // packed so many discs overlap, with radii that already match the species
+    let circles: Vec<Circle> = (0..300)
+        .map(|_| {
+            let color = rng.gen_range(0..3);
+            Circle {
+                color,
+                rad: rules.properties(color as usize).radius,
+                pos: [rng.gen_range(-5.0..5.0), rng.gen_range(-5.0..5.0)],
+                vel: [rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)],
+            }
+        })
+        .collect();
+    for contact in [Contact::Spring, Contact::Constraint] {
+        common::assert_backends_agree(&circles, &rules, &params(contact), 0, 5);
+    }
+}
+
+#[test]
+fn contact_survives_codes_and_presets() {
+    let mut params = params(Contact::Constraint);
+    params.stiffness = 42.0;
+    let setup = Setup { seed: 1, rules: Rules::new(2), params };
+    for back in common::round_trips(&setup) {
+        assert_eq!((back.params.contact(), back.params.stiffness), (Contact::Constraint, 42.0));
+    }
+}
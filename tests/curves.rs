@@ -0,0 +1,101 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+
+use physics::code::{self, CodeError, Setup};
+use physics::curves::{self, SAMPLES};
+use physics::kernel::Kernel;
+use physics::params::Params;
+use physics::rules::Rules;
+
+fn scratch(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("physics-curves-{}-{}", std::process::id(), name))
+}
+
+#[test]
+fn sampling_interpolates_between_points() {
+    let curve: Vec<f32> = (0..SAMPLES).map(|k| k as f32).collect();
+    assert_eq!(curves::sample(&curve, 0.0), 0.0);
+    assert_eq!(curves::sample(&curve, 1.0), (SAMPLES - 1) as f32);
+    let halfway = (curves::distance(10) + curves::distance(11)) / 2.0;
+    assert!((curves::sample(&curve, halfway) - 10.5).abs() < 1e-4);
+}
+
+#[test]
+fn tabulating_a_kernel_reproduces_it_at_the_points() {
+    let params = Params::default();
+    let mut rules = Rules::new(1);
+    rules.set(0, 0, 0.7);
+    rules.set_kernel(0, 0, Kernel::Cosine);
+    let analytic: Vec<f32> = (0..SAMPLES).map(|k| rules.force(0, 0, curves::distance(k), &params)).collect();
+    rules.set_curve(0, 0, &analytic);
+    rules.set_kernel(0, 0, Kernel::Tabulated);
+    for (k, expected) in analytic.iter().enumerate() {
+        let d = curves::distance(k);
+        assert!((rules.force(0, 0, d, &params) - expected).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn csv_round_trips() {
+    let mut rules = Rules::new(2);
+    let curve: Vec<f32> = (0..SAMPLES).map(|k| (k as f32 * 0.3).sin()).collect();
+    rules.set_curve(1, 0, &curve);
+    rules.set_kernel(1, 0, Kernel::Tabulated);
+
+    let path = scratch("round-trip.csv");
+    curves::save_csv(&path, &rules).unwrap();
+    let mut loaded = Rules::new(2);
+    assert_eq!(curves::load_csv(&path, &mut loaded).unwrap(), 1);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded.kernel(1, 0), Kernel::Tabulated);
+    assert_eq!(loaded.kernel(0, 1), Kernel::Triangle);
+    for (a, b) in curve.iter().zip(loaded.curve(1, 0)) {
+        assert!((a - b).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn coarse_csv_is_resampled() {
+    let path = scratch("coarse.csv");
+    // a ramp from 1 at contact to -1 at the cutoff, given at three points
+    std::fs::write(&path, "d,0-0\n0,1\n0.5,0\n1,-1\n").unwrap();
+    let mut rules = Rules::new(1);
+    curves::load_csv(&path, &mut rules).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    for k in 0..SAMPLES {
+        let expected = 1.0 - 2.0 * curves::distance(k);
+        assert!((rules.curve(0, 0)[k] - expected).abs() < 1e-5, "point {}", k);
+    }
+}
+
+#[test]
+fn bad_csv_is_rejected() {
+    for (name, text) in [("pair", "d,0-3\n0,1\n"), ("columns", "d,0-0\n0,1,2\n"), ("order", "d,0-0\n1,1\n0,1\n"), ("nan", "d,0-0\n0,NaN\n")] {
+        let path = scratch(name);
+        std::fs::write(&path, text).unwrap();
+        let result = curves::load_csv(&path, &mut Rules::new(2));
+        std::fs::remove_file(&path).unwrap();
+        assert!(result.is_err(), "{} accepted", name);
+    }
+}
+
+#[test]
+fn curves_that_arent_finite_are_rejected() {
+    for bad in ["nan", "inf"] {
+        let force = vec!["0.0"; SAMPLES - 1].join(", ");
+        let text = format!("matrix = [[0.0]]\ncurves = [{{ pair = [0, 0], force = [{}, {}] }}]\n", bad, force);
+        assert!(toml::from_str::<Rules>(&text).is_err(), "{}", bad);
+    }
+    let mut rules = Rules::new(1);
+    rules.set_kernel(0, 0, Kernel::Tabulated);
+    rules.set_curve(0, 0, &[2.75; SAMPLES]);
+    let setup = Setup { seed: 1, rules: rules.clone(), params: Params::default() };
+    let good = URL_SAFE_NO_PAD.decode(code::encode(&setup).unwrap()).unwrap();
+    let at = good.windows(4).position(|w| w == 2.75f32.to_le_bytes()).unwrap();
+    let mut bytes = good;
+    bytes[at..at + 4].copy_from_slice(&f32::INFINITY.to_le_bytes());
+    assert!(matches!(code::decode(&URL_SAFE_NO_PAD.encode(bytes)), Err(CodeError::Curve(0, 0, _))));
+    rules.set_curve(0, 0, &[f32::NAN; SAMPLES]);
+    assert!(matches!(code::encode(&Setup { rules, ..setup }), Err(CodeError::Curve(0, 0, _))));
+}
@@ -0,0 +1,173 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use physics::circle::Circle;
+use physics::code::Setup;
+use physics::contact::Contact;
+use physics::fields::{self, Field, Obstacle};
+use physics::params::Params;
+use physics::rules::Rules;
+
+mod common;
+
+use common::{backends, still, TOLERANCE};
+
+fn at(pos: [f32; 2], vel: [f32; 2]) -> Circle {
+    Circle { color: 0, rad: 0.125, pos, vel }
+}
+
+fn with(fields: Vec<Field>, obstacles: Vec<Obstacle>) -> Rules {
+    let mut rules = Rules::new(1);
+    rules.set_fields(fields).unwrap();
+    rules.set_obstacles(obstacles).unwrap();
+    rules
+}
+
+#[test]
+fn gravity_pulls_everything_the_same_way() {
+    let rules = with(vec![Field::Gravity { g: [0.0, -1.0] }], vec![]);
+    let circles = [at([0.0, 0.0], [0.0, 0.0]), at([3.0, 2.0], [0.0, 0.0])];
+    for (backend, mut sim) in backends(&circles, &rules, &still()) {
+        sim.step(10);
+        let after = sim.download();
+        for (a, b) in circles.iter().zip(&after) {
+            assert!(b.vel[1] < 0.0 && b.vel[0].abs() < TOLERANCE, "{}: {:?}", backend.name(), after);
+            assert!(b.pos[1] < a.pos[1], "{}: {:?}", backend.name(), after);
+        }
+        assert!((after[0].vel[1] - after[1].vel[1]).abs() < TOLERANCE, "{}: {:?}", backend.name(), after);
+    }
+}
+
+#[test]
+fn attractors_pull_and_vortices_turn() {
+    let attractor = Field::Attractor { at: [0.0, 0.0], strength: 2.0, radius: 0.5 };
+    let f = attractor.force([3.0, 0.0], [0.0, 0.0], 1.0);
+    assert!(f[0] < 0.0 && f[1] == 0.0);
+    let far = attractor.force([6.0, 0.0], [0.0, 0.0], 1.0);
+    assert!(far[0] > f[0]);
+    // anticlockwise, so straight up to the right of the centre
+    let vortex = Field::Vortex { at: [1.0, 1.0], strength: 1.0, radius: 0.5 };
+    let f = vortex.force([2.0, 1.0], [0.0, 0.0], 1.0);
+    assert!(f[0].abs() < 1e-6 && f[1] > 0.0);
+    let rules = with(vec![attractor], vec![]);
+    for (backend, mut sim) in backends(&[at([3.0, 0.0], [0.0, 0.0])], &rules, &still()) {
+        sim.step(10);
+        let after = sim.download();
+        assert!(after[0].pos[0] < 3.0 && after[0].vel[0] < 0.0, "{}: {:?}", backend.name(), after);
+    }
+}
+
+#[test]
+fn shear_drags_along_the_flow() {
+    let shear = Field::Shear { rate: 0.5, coupling: 2.0 };
+    assert_eq!(shear.force([0.0, 2.0], [0.0, 0.0], 1.0), [2.0, 0.0]);
+    assert_eq!(shear.force([0.0, -2.0], [0.0, 0.0], 1.0), [-2.0, 0.0]);
+    // moving with the flow, so no drag along x
+    assert_eq!(shear.force([0.0, 2.0], [1.0, 0.5], 1.0), [0.0, -1.0]);
+    let fields = [shear, Field::Gravity { g: [1.0, 0.0] }];
+    assert_eq!(fields::force(&fields, [0.0, 2.0], [0.0, 0.0], 2.0), [4.0, 0.0]);
+}
+
+#[test]
+fn obstacles_keep_particles_out() {
+    let circle = Obstacle::Circle { at: [2.0, 0.0], radius: 1.0 };
+    let rect = Obstacle::Rect { min: [-1.0, 2.0], max: [1.0, 3.0] };
+    let rules = with(vec![], vec![circle, rect]);
+    // one heading into each, and one sliding past the circle
+    let circles = [at([-0.5, 0.0], [5.0, 0.0]), at([0.0, 0.5], [0.0, 5.0]), at([2.0, 1.5], [1.0, -3.0])];
+    for (backend, mut sim) in backends(&circles, &rules, &still()) {
+        sim.step(60);
+        let after = sim.download();
+        for c in &after {
+            assert!(circle.push(c.pos, c.rad - TOLERANCE).is_none(), "{}: {:?}", backend.name(), after);
+            assert!(rect.push(c.pos, c.rad - TOLERANCE).is_none(), "{}: {:?}", backend.name(), after);
+        }
+        // stopped at the circle's side and the rect's bottom, where the
+        // last keeps its velocity along the edge
+        assert!(after[0].vel[0].abs() < TOLERANCE && (after[0].pos[0] - 0.875).abs() < TOLERANCE, "{}: {:?}", backend.name(), after);
+        assert!(after[1].vel[1].abs() < TOLERANCE && (after[1].pos[1] - 1.875).abs() < TOLERANCE, "{}: {:?}", backend.name(), after);
+        assert!(after[2].vel[0] > 0.0, "{}: {:?}", backend.name(), after);
+    }
+}
+
+#[test]
+fn rects_push_out_the_nearest_side() {
+    let rect = Obstacle::Rect { min: [0.0, 0.0], max: [4.0, 2.0] };
+    assert_eq!(rect.push([1.0, 1.5], 0.25), Some(([0.0, 1.0], 0.75)));
+    assert_eq!(rect.push([3.5, 1.0], 0.25), Some(([1.0, 0.0], 0.75)));
+    assert_eq!(rect.push([5.0, 1.0], 0.25), None);
+    let (pos, vel) = fields::collide(&[rect], [4.1, 1.0], [-1.0, 2.0], 0.25);
+    assert!((pos[0] - 4.25).abs() < 1e-6 && pos[1] == 1.0);
+    assert_eq!(vel, [0.0, 2.0]);
+}
+
+#[test]
+fn backends_agree_on_fields() {
+    let mut rng = StdRng::seed_from_u64(10);
+    let mut rules = Rules::new(3);
+    rules
+        .set_fields(vec![
+            Field::Gravity { g: [0.0, -0.5] },
+            Field::Attractor { at: [2.0, 1.0], strength: 3.0, radius: 1.0 },
+            Field::Vortex { at: [-2.0, 0.0], strength: 2.0, radius: 0.5 },
+            Field::Shear { rate: 0.2, coupling: 0.5 },
+        ])
+        .unwrap();
+    rules
+        .set_obstacles(vec![
+            Obstacle::Circle { at: [0.0, -2.0], radius: 1.0 },
+            Obstacle::Rect { min: [-4.0, -5.0], max: [4.0, -4.0] },
+        ])
+        .unwrap();
+    let circles: Vec<Circle> = (0..200)
+        .map(|_| Circle {
+            color: rng.gen_range(0..3),
+            rad: 0.125,
+            pos: [rng.gen_range(-4.0..4.0), rng.gen_range(-3.0..4.0)],
+            vel: [0.0, 0.0],
+        })
+        .collect();
+    // obstacles push particles into each other, where the SIMD kernel only
+    // approximates Contact::Push
+    let mut params = Params::default();
+    params.set_contact(Contact::Constraint);
+    common::assert_backends_agree(&circles, &rules, &params, 0, 20);
+}
+
+#[test]
+fn fields_survive_codes_and_presets() {
+    let rules = with(
+        vec![Field::Gravity { g: [0.0, -9.8] }, Field::Shear { rate: 0.5, coupling: 1.0 }],
+        vec![Obstacle::Circle { at: [1.0, 2.0], radius: 0.5 }, Obstacle::Rect { min: [-1.0, -1.0], max: [0.0, 0.0] }],
+    );
+    let setup = Setup { seed: 1, rules, params: Params::default() };
+    for back in common::round_trips(&setup) {
+        assert_eq!(back, setup);
+    }
+}
+
+#[test]
+fn invalid_fields_and_obstacles_are_rejected() {
+    let mut rules = Rules::new(1);
+    assert!(rules.set_fields(vec![Field::Attractor { at: [0.0, 0.0], strength: 1.0, radius: 0.0 }]).is_err());
+    assert!(rules.set_fields(vec![Field::Vortex { at: [0.0, 0.0], strength: 1.0, radius: -1.0 }]).is_err());
+    assert!(rules.set_fields(vec![Field::Shear { rate: 1.0, coupling: -1.0 }]).is_err());
+    assert!(rules.set_fields(vec![Field::Gravity { g: [0.0, 1.0] }; fields::MAX_FIELDS + 1]).is_err());
+    for bad in [f32::NAN, f32::INFINITY] {
+        assert!(rules.set_fields(vec![Field::Gravity { g: [bad, 1.0] }]).is_err());
+        assert!(rules.set_fields(vec![Field::Attractor { at: [0.0, bad], strength: 1.0, radius: 1.0 }]).is_err());
+        assert!(rules.set_fields(vec![Field::Attractor { at: [0.0, 0.0], strength: bad, radius: 1.0 }]).is_err());
+        assert!(rules.set_fields(vec![Field::Vortex { at: [0.0, 0.0], strength: 1.0, radius: bad }]).is_err());
+        assert!(rules.set_fields(vec![Field::Shear { rate: bad, coupling: 1.0 }]).is_err());
+        assert!(rules.set_fields(vec![Field::Shear { rate: 1.0, coupling: bad }]).is_err());
+    }
+    assert!(rules.fields().is_empty());
+    assert!(rules.set_obstacles(vec![Obstacle::Circle { at: [0.0, 0.0], radius: 0.0 }]).is_err());
+    assert!(rules.set_obstacles(vec![Obstacle::Rect { min: [1.0, 0.0], max: [0.0, 1.0] }]).is_err());
+    let text = "matrix = [[0.0]]\nfields = [{ kind = \"vortex\", at = [1.0, 0.0], strength = 2.0, radius = 0.5 }]\n\
+                obstacles = [{ shape = \"rect\", min = [0.0, 0.0], max = [1.0, 1.0] }]\n";
+    let rules = toml::from_str::<Rules>(text).unwrap();
+    assert_eq!(rules.fields(), [Field::Vortex { at: [1.0, 0.0], strength: 2.0, radius: 0.5 }]);
+    assert_eq!(rules.obstacles(), [Obstacle::Rect { min: [0.0, 0.0], max: [1.0, 1.0] }]);
+    let text = "matrix = [[0.0]]\nobstacles = [{ shape = \"rect\", min = [0.0, 0.0], max = [0.0, 1.0] }]\n";
+    assert!(toml::from_str::<Rules>(text).is_err());
+}
@@ -0,0 +1,88 @@
+use physics::circle::Circle;
+use physics::code::Setup;
+use physics::friction::Friction;
+use physics::params::Params;
+use physics::rules::Rules;
+
+mod common;
+
+use common::backends;
+
+const STEPS: usize = 20;
+
+fn params(friction: Friction, mu: f32) -> Params {
+    let mut params = Params::default();
+    params.mu = mu;
+    params.set_friction(friction);
+    params
+}
+
+// one particle on its own, so only friction acts on it
+fn lone(vel: [f32; 2]) -> Vec<Circle> {
+    vec![Circle { color: 0, rad: 0.125, pos: [1.0, 2.0], vel }]
+}
+
+fn speed(c: &Circle) -> f32 {
+    (c.vel[0] * c.vel[0] + c.vel[1] * c.vel[1]).sqrt()
+}
+
+#[test]
+fn stationary_particles_stay_put() {
+    for friction in Friction::ALL {
+        for (backend, mut sim) in backends(&lone([0.0, 0.0]), &Rules::new(1), &params(friction, 5.0)) {
+            sim.step(STEPS);
+            let after = sim.download();
+            assert_eq!((after[0].pos, after[0].vel), ([1.0, 2.0], [0.0, 0.0]), "{} on {}", friction.name(), backend.name());
+        }
+    }
+}
+
+#[test]
+fn every_model_but_none_slows_particles() {
+    for friction in Friction::ALL {
+        for (backend, mut sim) in backends(&lone([3.0, -4.0]), &Rules::new(1), &params(friction, 2.0)) {
+            sim.step(STEPS);
+            let after = sim.download();
+            let name = format!("{} on {}", friction.name(), backend.name());
+            assert!(after[0].vel.iter().all(|v| v.is_finite()), "{}: {:?}", name, after);
+            if friction == Friction::None {
+                assert_eq!(after[0].vel, [3.0, -4.0], "{}", name);
+            } else {
+                assert!(speed(&after[0]) < 5.0, "{}: {:?}", name, after);
+                // slowed, never turned around
+                assert!(after[0].vel[0] >= 0.0 && after[0].vel[1] <= 0.0, "{}: {:?}", name, after);
+            }
+        }
+    }
+}
+
+#[test]
+fn cap_holds_the_speed_to_mu() {
+    for (backend, mut sim) in backends(&lone([3.0, -4.0]), &Rules::new(1), &params(Friction::Cap, 2.0)) {
+        sim.step(1);
+        let after = sim.download();
+        assert!((speed(&after[0]) - 2.0).abs() < 1e-5, "{}: {:?}", backend.name(), after);
+    }
+}
+
+#[test]
+fn half_life_halves_the_velocity() {
+    // mu halvings per unit of time, so one step of 1 / mu halves it once
+    let params = params(Friction::HalfLife, 4.0);
+    for (backend, mut sim) in backends(&lone([3.0, -4.0]), &Rules::new(1), &params) {
+        sim.set_dt(0.25);
+        sim.step(1);
+        let after = sim.download();
+        assert!((speed(&after[0]) - 2.5).abs() < 1e-5, "{}: {:?}", backend.name(), after);
+    }
+}
+
+#[test]
+fn models_survive_codes_and_presets() {
+    for friction in Friction::ALL {
+        let setup = Setup { seed: 1, rules: Rules::new(2), params: params(friction, 1.5) };
+        for back in common::round_trips(&setup) {
+            assert_eq!(back.params.friction(), friction);
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use std::fmt::Write;
+
+use physics::backend::{Backend, SimBackend};
+use physics::circle::Circle;
+use physics::params::Params;
+use physics::rules::Rules;
+use physics::sim::Neighbours;
+
+// expected accelerations, one `scenario particle ax ay` line per particle;
+// run with GOLDEN_UPDATE=1 to rewrite it after a deliberate kernel change
+const GOLDEN: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/kernel.txt");
+const TOLERANCE: f32 = 1e-4;
+
+// a long step makes the velocity change large next to the starting velocity
+const DT: f32 = 1.0;
+
+// particles start at rest, which every friction model allows
+const VEL: [f32; 2] = [0.0, 0.0];
+
+fn circle(color: i32, pos: [f32; 2]) -> Circle {
+    Circle {
+        color,
+        rad: 0.125,
+        pos,
+        vel: VEL,
+    }
+}
+
+// species 0 and 1 have rules of opposite sign, so each pair shows both halves
+// of the matrix
+fn scenarios() -> Vec<(&'static str, Vec<Circle>)> {
+    let pair = |offset: [f32; 2]| vec![circle(0, [0.5, 0.25]), circle(1, [0.5 + offset[0], 0.25 + offset[1]])];
+    vec![
+        // |diff| = 0.2, d = 0.1: overlapping, pushed apart as well
+        ("overlap", pair([0.12, 0.16])),
+        // |diff| = 1, d = 0.5 < rmin
+        ("repulsion", pair([0.6, 0.8])),
+        // |diff| = 1.4, d = 0.7, halfway up the triangle
+        ("attraction", pair([0.84, 1.12])),
+        // |diff| = 1.2, d = rmin, where the repulsion hands over to the band
+        ("at_rmin", pair([0.72, 0.96])),
+        // |diff| = 1.6, d = 0.8, the top of the triangle
+        ("peak", pair([0.96, 1.28])),
+        // |diff| = 2, d = 1, the cutoff
+        ("at_cutoff", pair([1.2, 1.6])),
+        // |diff| = 3, d = 1.5, past the cutoff at d = 1
+        ("far_band", pair([1.8, 2.4])),
+        // |diff| = 5, d = 2.5 >= rmax
+        ("beyond_rmax", pair([3.0, 4.0])),
+        // |diff| = 1, d = 0.5 < rmin, along x
+        ("axis_aligned", pair([1.0, 0.0])),
+        // d = 0, split along x
+        ("coincident", pair([0.0, 0.0])),
+        // |pos| = 50, twice world_size
+        ("outside_world", vec![circle(0, [30.0, 40.0])]),
+    ]
+}
+
+fn rules() -> Rules {
+    let mut rules = Rules::new(2);
+    rules.set(0, 1, 1.0);
+    rules.set(1, 0, -0.5);
+    rules
+}
+
+fn params() -> Params {
+    let mut params = Params::default();
+    params.mu = 0.0;
+    params
+}
+
+// accelerations after one step, recovered from the velocity change
+fn accelerations(sim: &mut dyn SimBackend, params: &Params) -> Vec<[f32; 2]> {
+    sim.set_dt(DT);
+    sim.step(1);
+    sim.download()
+        .iter()
+        .map(|c| [(c.vel[0] - VEL[0]) / (params.rmax * DT), (c.vel[1] - VEL[1]) / (params.rmax * DT)])
+        .collect()
+}
+
+fn parse(golden: &str) -> Vec<(String, usize, [f32; 2])> {
+    golden
+        .lines()
+        .filter(|l| !l.starts_with('#') && !l.trim().is_empty())
+        .map(|l| {
+            let f: Vec<&str> = l.split_whitespace().collect();
+            (f[0].to_string(), f[1].parse().unwrap(), [f[2].parse().unwrap(), f[3].parse().unwrap()])
+        })
+        .collect()
+}
+
+#[test]
+fn kernel_matches_golden_accelerations() {
+    let (rules, params) = (rules(), params());
+
+    if std::env::var_os("GOLDEN_UPDATE").is_some() {
+        let mut out = String::from("# scenario particle ax ay\n");
+        for (name, circles) in scenarios() {
+            let mut sim = Backend::Cpu.create(&circles, &rules, &params, Neighbours::All).unwrap();
+            for (i, a) in accelerations(sim.as_mut(), &params).iter().enumerate() {
+                writeln!(out, "{} {} {:.6} {:.6}", name, i, a[0], a[1]).unwrap();
+            }
+        }
+        std::fs::write(GOLDEN, out).unwrap();
+        return;
+    }
+
+    let golden = parse(&std::fs::read_to_string(GOLDEN).unwrap());
+    for backend in Backend::ALL {
+        let mut checked = 0;
+        for (name, circles) in scenarios() {
+            let mut sim = match backend.create(&circles, &rules, &params, Neighbours::All) {
+                Ok(sim) => sim,
+                Err(e) => {
+                    eprintln!("{}, skipping it", e);
+                    break;
+                }
+            };
+            let actual = accelerations(sim.as_mut(), &params);
+            for (_, i, expected) in golden.iter().filter(|g| g.0 == name) {
+                let a = actual[*i];
+                assert!(
+                    (a[0] - expected[0]).abs() <= TOLERANCE * expected[0].abs().max(1.0)
+                        && (a[1] - expected[1]).abs() <= TOLERANCE * expected[1].abs().max(1.0),
+                    "{} {} particle {}: expected {:?} got {:?}",
+                    backend.name(),
+                    name,
+                    i,
+                    expected,
+                    a
+                );
+                checked += 1;
+            }
+        }
+        assert!(checked == 0 || checked == golden.len(), "{} missed golden lines", backend.name());
+    }
+}
+
+// worked out by hand from the triangle, so regenerating the file can't
+// quietly freeze a mistake at the edges of the band
+#[test]
+fn golden_edges_match_hand_values() {
+    let golden = parse(&std::fs::read_to_string(GOLDEN).unwrap());
+    let hand = [
+        ("at_rmin", 0, [0.0, 0.0]),
+        ("at_rmin", 1, [0.0, 0.0]),
+        // the whole rule, 1 and -0.5, along (0.6, 0.8) away from the other
+        ("peak", 0, [-0.6, -0.8]),
+        ("peak", 1, [-0.3, -0.4]),
+        ("at_cutoff", 0, [0.0, 0.0]),
+        ("at_cutoff", 1, [0.0, 0.0]),
+        ("far_band", 0, [0.0, 0.0]),
+        ("far_band", 1, [0.0, 0.0]),
+    ];
+    for (name, i, expected) in hand {
+        let (_, _, a) = golden.iter().find(|g| g.0 == name && g.1 == i).unwrap();
+        assert!(
+            (a[0] - expected[0]).abs() <= TOLERANCE && (a[1] - expected[1]).abs() <= TOLERANCE,
+            "{} particle {}: expected {:?} got {:?}",
+            name,
+            i,
+            expected,
+            a
+        );
+    }
+}
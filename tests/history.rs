@@ -0,0 +1,63 @@
+use physics::code::Setup;
+use physics::history::History;
+use physics::params::Params;
+use physics::rules::Rules;
+
+#[test]
+fn undo_and_redo_walk_the_entries() {
+    let mut history = History::new(0, 10);
+    assert_eq!(history.undo(), None);
+    history.push(1);
+    history.push(2);
+    assert_eq!(history.position(), (3, 3));
+    assert_eq!(history.undo(), Some(&1));
+    assert_eq!(history.undo(), Some(&0));
+    assert_eq!(history.undo(), None);
+    assert_eq!(history.redo(), Some(&1));
+    assert_eq!(*history.current(), 1);
+    assert_eq!(history.position(), (2, 3));
+}
+
+#[test]
+fn pushing_drops_the_redo_entries() {
+    let mut history = History::new(0, 10);
+    history.push(1);
+    history.push(2);
+    history.undo();
+    history.push(3);
+    assert_eq!(history.redo(), None);
+    assert_eq!(history.undo(), Some(&1));
+    assert_eq!(history.position(), (2, 3));
+}
+
+#[test]
+fn the_oldest_entries_fall_off_at_the_cap() {
+    let mut history = History::new(0, 3);
+    for i in 1..6 {
+        history.push(i);
+    }
+    assert_eq!(history.position(), (3, 3));
+    assert_eq!(history.undo(), Some(&4));
+    assert_eq!(history.undo(), Some(&3));
+    assert_eq!(history.undo(), None);
+    // a cap of zero still keeps the current entry
+    let mut history = History::new(0, 0);
+    history.push(1);
+    assert_eq!((*history.current(), history.position()), (1, (1, 1)));
+}
+
+#[test]
+fn any_seed_survives_a_preset() {
+    for seed in [0, 42, i64::MAX as u64, i64::MAX as u64 + 1, u64::MAX] {
+        let setup = Setup { seed, rules: Rules::new(2), params: Params::default() };
+        let text = toml::to_string(&setup).unwrap();
+        assert_eq!(toml::from_str::<Setup>(&text).unwrap(), setup);
+    }
+}
+
+#[test]
+fn empty_rules_serialise() {
+    let setup = Setup { seed: 1, rules: Rules::new(0), params: Params::default() };
+    let text = toml::to_string(&setup).unwrap();
+    assert_eq!(toml::from_str::<Setup>(&text).unwrap(), setup);
+}
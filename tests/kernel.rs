@@ -0,0 +1,133 @@
+use rand::{rngs::StdRng, SeedableRng};
+
+use physics::circle::Circle;
+use physics::curves;
+use physics::kernel::Kernel;
+use physics::params::Params;
+use physics::rules::{Generator, Rules};
+
+mod common;
+
+use common::{backends, lattice};
+
+const STEPS: usize = 10;
+
+fn momentum(circles: &[Circle]) -> [f64; 2] {
+    circles.iter().fold([0.0, 0.0], |m, c| [m[0] + c.vel[0] as f64, m[1] + c.vel[1] as f64])
+}
+
+#[test]
+fn band_kernels_are_continuous_at_rmin() {
+    let params = Params::default();
+    for kernel in [Kernel::Triangle, Kernel::Cosine] {
+        let below = kernel.force(params.rmin - 1e-5, 1.0, &params);
+        let above = kernel.force(params.rmin + 1e-5, 1.0, &params);
+        assert!(below.abs() < 1e-3 && above.abs() < 1e-3, "{}: {} {}", kernel.name(), below, above);
+    }
+}
+
+#[test]
+fn force_is_zero_and_continuous_at_the_cutoff() {
+    let mut params = Params::default();
+    params.mu = 0.0;
+    for d in [1.0 - 1e-5, 1.0, 1.0 + 1e-5] {
+        let f = Kernel::Triangle.force(d, 1.0, &params);
+        assert!(f.abs() < 1e-3, "{}: {}", d, f);
+    }
+    // past d = 1 no pair feels anything, however far the triangle's line
+    // would carry on below zero
+    let mut rules = Rules::new(1);
+    rules.set(0, 0, 1.0);
+    for d in [1.0 - 1e-4, 1.0 + 1e-4, 1.5, 1.99] {
+        let circles = vec![
+            Circle { color: 0, rad: 0.125, pos: [0.0, 0.0], vel: [0.0, 0.0] },
+            Circle { color: 0, rad: 0.125, pos: [d * params.rmax, 0.0], vel: [0.0, 0.0] },
+        ];
+        for (backend, mut sim) in backends(&circles, &rules, &params) {
+            sim.step(1);
+            let after = sim.download();
+            assert!(after.iter().all(|c| c.vel[0].abs() < 1e-4), "{} at {}: {:?}", backend.name(), d, after);
+        }
+    }
+}
+
+#[test]
+fn every_kernel_is_cut_off_at_one() {
+    let params = Params::default();
+    let mut rules = Rules::new(1);
+    rules.set(0, 0, 1.0);
+    rules.set_curve(0, 0, &[1.0; curves::SAMPLES]);
+    for kernel in Kernel::ALL {
+        rules.set_kernel(0, 0, kernel);
+        for d in [1.0, 1.2, 2.0] {
+            assert_eq!(rules.force(0, 0, d, &params), 0.0, "{} at {}", kernel.name(), d);
+        }
+    }
+}
+
+#[test]
+fn every_kernel_is_finite_and_repels_at_contact() {
+    let params = Params::default();
+    // tabulated pairs have no shape of their own
+    for kernel in Kernel::ALL.into_iter().filter(|&k| k != Kernel::Tabulated) {
+        let curve = kernel.curve(1.0, &params, 201);
+        assert!(curve.iter().all(|f| f.is_finite()), "{}: {:?}", kernel.name(), curve);
+        // d = 0 repels whatever the rule
+        assert!(curve[0] > 0.0, "{}: {}", kernel.name(), curve[0]);
+    }
+}
+
+#[test]
+fn particle_zero_acts_on_others() {
+    let mut params = Params::default();
+    params.mu = 0.0;
+    let rules = Rules::new(1);
+    let circles = vec![
+        Circle { color: 0, rad: 0.125, pos: [0.0, 0.0], vel: [1.0, 1.0] },
+        Circle { color: 0, rad: 0.125, pos: [0.3, 0.4], vel: [1.0, 1.0] },
+    ];
+    for (backend, mut sim) in backends(&circles, &rules, &params) {
+        sim.step(1);
+        let after = sim.download();
+        // pushed apart equally, particle 1 by particle 0 as much as the reverse
+        for k in 0..2 {
+            let (kick0, kick1) = (after[0].vel[k] - 1.0, after[1].vel[k] - 1.0);
+            assert!(kick1 > 0.0 && (kick0 + kick1).abs() < 1e-5, "{}: {:?}", backend.name(), after);
+        }
+    }
+}
+
+#[test]
+fn coincident_particles_separate() {
+    let params = Params::default();
+    let rules = Rules::new(1);
+    let circles = vec![
+        Circle { color: 0, rad: 0.125, pos: [1.0, 2.0], vel: [0.5, 0.5] },
+        Circle { color: 0, rad: 0.125, pos: [1.0, 2.0], vel: [0.5, 0.5] },
+    ];
+    for (backend, mut sim) in backends(&circles, &rules, &params) {
+        sim.step(STEPS);
+        let after = sim.download();
+        for c in &after {
+            assert!(c.pos.iter().chain(&c.vel).all(|v| v.is_finite()), "{}: {:?}", backend.name(), after);
+        }
+        assert!(after[1].pos[0] > after[0].pos[0], "{}: {:?}", backend.name(), after);
+    }
+}
+
+#[test]
+fn symmetric_rules_conserve_momentum() {
+    let mut params = Params::default();
+    params.mu = 0.0;
+    let rules = Generator::Symmetric.generate(6, &mut StdRng::seed_from_u64(7));
+    // inside world_size, so nothing but the pair forces acts
+    let circles = lattice(20, 1.0, 6, 7);
+    let before = momentum(&circles);
+    for (backend, mut sim) in backends(&circles, &rules, &params) {
+        sim.step(STEPS);
+        let after = momentum(&sim.download());
+        for (a, b) in before.iter().zip(after) {
+            assert!((a - b).abs() < 1e-3, "{}: {:?} -> {:?}", backend.name(), before, after);
+        }
+    }
+}
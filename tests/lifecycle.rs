@@ -0,0 +1,183 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use physics::backend::Backend;
+use physics::circle::Circle;
+use physics::code::Setup;
+use physics::contact::Contact;
+use physics::gpu;
+use physics::lifecycle::Lifecycle;
+use physics::metrics::{self, Population};
+use physics::params::Params;
+use physics::rules::Rules;
+use physics::sim::{Neighbours, DT};
+
+mod common;
+
+use common::backends;
+
+fn still(max_particles: u32) -> Params {
+    let mut params = common::still();
+    params.max_particles = max_particles;
+    params
+}
+
+// a row of particles `gap` apart, alternating species 0 and 1
+fn row(count: usize, gap: f32) -> Vec<Circle> {
+    (0..count)
+        .map(|i| Circle { color: (i % 2) as i32, rad: 0.125, pos: [i as f32 * gap, 0.0], vel: [0.0, 0.0] })
+        .collect()
+}
+
+fn with_lifecycle(lifecycle: Lifecycle) -> Rules {
+    let mut rules = Rules::new(2);
+    rules.set_lifecycle(0, lifecycle);
+    rules
+}
+
+#[test]
+fn particles_die_of_old_age() {
+    // the lifespan falls between steps so rounding can't move it
+    let rules = with_lifecycle(Lifecycle { lifespan: 9.5 * DT, ..Lifecycle::default() });
+    for (backend, mut sim) in backends(&row(6, 1.0), &rules, &still(0)) {
+        sim.step(9);
+        assert_eq!(sim.download().len(), 6, "{}", backend.name());
+        sim.step(1);
+        let after = sim.download();
+        assert!(after.len() == 3 && after.iter().all(|c| c.color == 1), "{}: {:?}", backend.name(), after);
+    }
+}
+
+#[test]
+fn isolated_particles_die() {
+    let rules = with_lifecycle(Lifecycle { range: 1.5, lonely: 1, ..Lifecycle::default() });
+    // two close together and one far off
+    let mut circles = row(2, 1.0);
+    circles.push(Circle { color: 0, rad: 0.125, pos: [10.0, 0.0], vel: [0.0, 0.0] });
+    for (backend, mut sim) in backends(&circles, &rules, &still(0)) {
+        sim.step(1);
+        let after = sim.download();
+        assert_eq!(after.len(), 2, "{}: {:?}", backend.name(), after);
+        // the survivors keep their order
+        assert_eq!((after[0].color, after[1].color), (0, 1), "{}", backend.name());
+    }
+}
+
+#[test]
+fn crowded_particles_breed_up_to_the_limit() {
+    let rules = with_lifecycle(Lifecycle { range: 1.5, partner: Some(1), crowd: 1, period: 0.0, ..Lifecycle::default() });
+    for (backend, mut sim) in backends(&row(2, 1.0), &rules, &still(5)) {
+        sim.step(1);
+        let after = sim.download();
+        assert_eq!(after.len(), 3, "{}: {:?}", backend.name(), after);
+        let child = after[2];
+        let d = ((child.pos[0] - after[0].pos[0]).powi(2) + (child.pos[1] - after[0].pos[1]).powi(2)).sqrt();
+        assert!(child.color == 0 && d < 1.0, "{}: {:?}", backend.name(), after);
+        sim.step(1);
+        assert_eq!(sim.download().len(), 5, "{}", backend.name());
+        sim.step(5);
+        assert_eq!(sim.download().len(), 5, "{}", backend.name());
+    }
+}
+
+#[test]
+fn successive_births_land_apart() {
+    let rules = with_lifecycle(Lifecycle { range: 1.5, partner: Some(1), crowd: 1, period: 0.0, ..Lifecycle::default() });
+    for (backend, mut sim) in backends(&row(2, 1.0), &rules, &still(3)) {
+        sim.set_seed(4);
+        // the same parent breeds into the same slot twice, with no thermal
+        // steps in between
+        sim.step(1);
+        let first = sim.download()[2];
+        sim.upload(&row(2, 1.0)).unwrap();
+        sim.step(1);
+        let second = sim.download()[2];
+        assert_ne!(first.pos, second.pos, "{}", backend.name());
+    }
+}
+
+#[test]
+fn births_refill_to_the_starting_count() {
+    // species 0 breeds next to 1 and species 1 dies young
+    let mut rules = with_lifecycle(Lifecycle { range: 1.5, partner: Some(1), crowd: 1, period: 0.0, ..Lifecycle::default() });
+    rules.set_lifecycle(1, Lifecycle { lifespan: 1.5 * DT, ..Lifecycle::default() });
+    for (backend, mut sim) in backends(&row(4, 1.0), &rules, &still(0)) {
+        sim.step(2);
+        let after = sim.download();
+        assert!(after.len() == 4 && after.iter().all(|c| c.color == 0), "{}: {:?}", backend.name(), after);
+    }
+}
+
+#[test]
+fn births_stop_at_what_the_gpu_can_step() {
+    // presets aren't validated, so a limit past the GPU's is held to it
+    let params = still(u32::MAX);
+    assert!(params.validate().is_err());
+    assert_eq!(params.limit(10), gpu::MAX_PARTICLES);
+    assert_eq!(still(0).limit(gpu::MAX_PARTICLES + 1), gpu::MAX_PARTICLES + 1);
+}
+
+#[test]
+fn the_gpu_refuses_more_than_it_can_step() {
+    let rules = Rules::new(2);
+    let many = row(gpu::MAX_PARTICLES + 1, 0.01);
+    assert!(Backend::Gpu.create(&many, &rules, &still(0), Neighbours::All).is_err());
+    for (backend, mut sim) in backends(&row(2, 1.0), &rules, &still(0)) {
+        let uploaded = sim.upload(&many);
+        assert_eq!(uploaded.is_err(), backend == Backend::Gpu, "{}", backend.name());
+    }
+}
+
+#[test]
+fn backends_agree_on_lifecycles() {
+    // no forces, so only births and deaths tell the backends apart
+    let mut rng = StdRng::seed_from_u64(6);
+    let mut rules = Rules::new(3);
+    rules.set_lifecycle(0, Lifecycle { lifespan: 20.5 * DT, range: 1.2, partner: Some(1), crowd: 2, period: 4.5 * DT, lonely: 0 });
+    rules.set_lifecycle(1, Lifecycle { range: 0.8, lonely: 1, ..Lifecycle::default() });
+    rules.set_lifecycle(2, Lifecycle { range: 1.0, partner: Some(2), crowd: 1, period: 7.5 * DT, ..Lifecycle::default() });
+    let circles: Vec<Circle> = (0..200)
+        .map(|_| Circle {
+            color: rng.gen_range(0..3),
+            rad: 0.125,
+            pos: [rng.gen_range(-6.0..6.0), rng.gen_range(-6.0..6.0)],
+            vel: [0.0, 0.0],
+        })
+        .collect();
+    // children overlap their parents, where the SIMD kernel only
+    // approximates Contact::Push
+    let mut params = still(300);
+    params.set_contact(Contact::Constraint);
+    let (after, _) = common::assert_backends_agree(&circles, &rules, &params, 2, 25);
+    assert_ne!(after.len(), circles.len());
+}
+
+#[test]
+fn lifecycles_survive_codes_and_presets() {
+    let mut rules = with_lifecycle(Lifecycle { lifespan: 3.0, range: 2.0, partner: Some(1), crowd: 4, period: 0.5, lonely: 2 });
+    rules.set_lifecycle(1, Lifecycle { lonely: 1, ..Lifecycle::default() });
+    let setup = Setup { seed: 1, rules, params: still(4000) };
+    for back in common::round_trips(&setup) {
+        assert_eq!(back, setup);
+    }
+}
+
+#[test]
+fn lifecycle_partner_must_exist() {
+    let text = "matrix = [[0.0]]\nlifecycles = [{ partner = 3 }]\n";
+    assert!(toml::from_str::<Rules>(text).is_err());
+    let text = "matrix = [[0.0]]\nlifecycles = [{ partner = 0, lifespan = 2.0 }]\n";
+    let rules = toml::from_str::<Rules>(text).unwrap();
+    assert!(rules.living() && rules.lifecycle(0).lifespan == 2.0);
+}
+
+#[test]
+fn population_keeps_the_latest_samples() {
+    let mut population = Population::new(3);
+    for k in 0..5 {
+        population.record(metrics::species_counts(&row(k + 1, 1.0), 2));
+    }
+    assert_eq!(population.len(), 3);
+    assert_eq!(population.series(0), [2.0, 2.0, 3.0]);
+    assert_eq!(population.series(1), [1.0, 2.0, 2.0]);
+    assert_eq!(population.peak(), 3);
+}
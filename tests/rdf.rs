@@ -0,0 +1,36 @@
+use physics::circle::Circle;
+use physics::rdf::Rdf;
+
+fn at(color: i32, pos: [f32; 2]) -> Circle {
+    Circle { color, rad: 0.125, pos, vel: [0.0, 0.0] }
+}
+
+#[test]
+fn same_and_cross_species_pairs_normalise_alike() {
+    // one pair of each kind the same distance apart, far from the other
+    let circles = [at(0, [0.0, 0.0]), at(0, [1.0, 0.0]), at(0, [10.0, 0.0]), at(1, [11.0, 0.0])];
+    let mut rdf = Rdf::new(2, 8, 2.0, 20.0);
+    rdf.add(&circles);
+    let k = 4;
+    assert!(rdf.pair(0, 0)[k] > 0.0);
+    // three of species 0 make 3 * 2 ordered pairs, two of them close, and
+    // 3 * 1 pairs with species 1, one of them close
+    let ratio = rdf.pair(0, 0)[k] / rdf.pair(0, 1)[k];
+    assert!((ratio - 1.0).abs() < 1e-5, "{}", ratio);
+    assert!(rdf.pair(0, 0).iter().enumerate().all(|(i, g)| i == k || *g == 0.0));
+}
+
+#[test]
+fn total_counts_every_pair_once_each_way() {
+    let circles = [at(0, [0.0, 0.0]), at(1, [1.0, 0.0])];
+    let mut rdf = Rdf::new(2, 8, 2.0, 20.0);
+    rdf.add(&circles);
+    rdf.add(&circles);
+    assert_eq!(rdf.frames(), 2);
+    // two particles, each with the other in shell 4
+    let world = std::f32::consts::PI * 400.0;
+    let shell = std::f32::consts::PI * (1.25f32.powi(2) - 1.0);
+    let expected = world / shell;
+    assert!((rdf.total()[4] - expected).abs() < expected * 1e-5, "{} vs {}", rdf.total()[4], expected);
+    assert!((rdf.pair(0, 1)[4] - expected).abs() < expected * 1e-5);
+}
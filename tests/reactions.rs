@@ -0,0 +1,140 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use physics::circle::Circle;
+use physics::code::Setup;
+use physics::contact::Contact;
+use physics::metrics;
+use physics::params::Params;
+use physics::reactions::{self, Reaction, Timer};
+use physics::rules::{Generator, Rules};
+use physics::sim::DT;
+use physics::species::Species;
+
+mod common;
+
+use common::{backends, still};
+
+// species 0 next to species 1 for 10 steps becomes species 2, which is
+// bigger; the time falls between steps so rounding can't move it
+fn rules(range: f32) -> Rules {
+    let mut rules = Rules::new(3);
+    rules.set_properties(2, Species { radius: 0.25, ..Species::default() });
+    rules
+        .set_reactions(vec![Reaction { from: 0, with: 1, range, time: 9.5 * DT, into: 2 }])
+        .unwrap();
+    rules
+}
+
+fn pair(gap: f32) -> Vec<Circle> {
+    vec![
+        Circle { color: 0, rad: 0.125, pos: [0.0, 0.0], vel: [0.0, 0.0] },
+        Circle { color: 1, rad: 0.125, pos: [gap, 0.0], vel: [0.0, 0.0] },
+    ]
+}
+
+#[test]
+fn reaction_takes_its_time() {
+    for (backend, mut sim) in backends(&pair(1.0), &rules(2.0), &still()) {
+        sim.step(9);
+        assert_eq!(sim.download()[0].color, 0, "{}", backend.name());
+        sim.step(1);
+        let after = sim.download();
+        assert_eq!((after[0].color, after[0].rad, after[1].color), (2, 0.25, 1), "{}: {:?}", backend.name(), after);
+    }
+}
+
+#[test]
+fn no_reaction_out_of_range() {
+    for (backend, mut sim) in backends(&pair(3.0), &rules(2.0), &still()) {
+        sim.step(50);
+        assert_eq!(sim.download()[0].color, 0, "{}", backend.name());
+    }
+}
+
+#[test]
+fn leaving_reach_restarts_the_timer() {
+    let reaction = Reaction { from: 0, with: 1, range: 2.0, time: 3.0, into: 2 };
+    let species = [0, 1];
+    let mut timers = [Timer::default(); 2];
+    let react = |pos: &[[f32; 2]], timers: &mut [Timer]| reactions::react(&[reaction], pos, &species, timers, 1.0);
+    let near = [[0.0, 0.0], [1.0, 0.0]];
+    let far = [[0.0, 0.0], [5.0, 0.0]];
+    assert_eq!(react(&near, &mut timers), [None, None]);
+    assert_eq!(react(&near, &mut timers), [None, None]);
+    assert_eq!(react(&far, &mut timers), [None, None]);
+    assert_eq!(react(&near, &mut timers), [None, None]);
+    assert_eq!(react(&near, &mut timers), [None, None]);
+    assert_eq!(react(&near, &mut timers), [Some(2), None]);
+}
+
+#[test]
+fn partners_are_found_at_every_range() {
+    // ranges well under and over each other, so the grid's cells are sized
+    // by the longest and the short ones test only part of each cell
+    let reactions = [
+        Reaction { from: 0, with: 1, range: 0.3, time: 0.0, into: 2 },
+        Reaction { from: 1, with: 2, range: 2.5, time: 0.0, into: 0 },
+        Reaction { from: 2, with: 0, range: 1.0, time: 0.0, into: 1 },
+    ];
+    let mut rng = StdRng::seed_from_u64(12);
+    let pos: Vec<[f32; 2]> = (0..500).map(|_| [rng.gen_range(-20.0..20.0), rng.gen_range(-20.0..20.0)]).collect();
+    let species: Vec<i32> = (0..pos.len()).map(|_| rng.gen_range(0..3)).collect();
+    let mut timers = vec![Timer::default(); pos.len()];
+    let changes = reactions::react(&reactions, &pos, &species, &mut timers, 1.0);
+    for (i, change) in changes.iter().enumerate() {
+        let expected = reactions.iter().find(|r| {
+            species[i] == r.from as i32
+                && (0..pos.len()).any(|j| {
+                    let d = ((pos[i][0] - pos[j][0]).powi(2) + (pos[i][1] - pos[j][1]).powi(2)).sqrt();
+                    j != i && species[j] == r.with as i32 && d < r.range
+                })
+        });
+        assert_eq!(*change, expected.map(|r| r.into), "particle {}", i);
+    }
+    assert!(changes.iter().any(|c| c.is_some()) && changes.iter().any(|c| c.is_none()));
+}
+
+#[test]
+fn backends_agree_on_reactions() {
+    let mut rng = StdRng::seed_from_u64(4);
+    let mut rules = Generator::Random.generate(3, &mut rng);
+    rules
+        .set_reactions(vec![
+            Reaction { from: 0, with: 1, range: 1.0, time: 4.5 * DT, into: 1 },
+            Reaction { from: 1, with: 2, range: 0.8, time: 2.5 * DT, into: 2 },
+            Reaction { from: 2, with: 0, range: 0.6, time: 3.5 * DT, into: 0 },
+        ])
+        .unwrap();
+    let circles: Vec<Circle> = (0..300)
+        .map(|_| Circle {
+            color: rng.gen_range(0..3),
+            rad: 0.125,
+            pos: [rng.gen_range(-6.0..6.0), rng.gen_range(-6.0..6.0)],
+            vel: [0.0, 0.0],
+        })
+        .collect();
+    // new species have new radii and overlap, where the SIMD kernel only
+    // approximates Contact::Push
+    let mut params = Params::default();
+    params.set_contact(Contact::Constraint);
+    let (after, _) = common::assert_backends_agree(&circles, &rules, &params, 0, 20);
+    assert_ne!(metrics::species_counts(&after, 3), metrics::species_counts(&circles, 3));
+}
+
+#[test]
+fn reactions_survive_codes_and_presets() {
+    let setup = Setup { seed: 1, rules: rules(1.5), params: Params::default() };
+    for back in common::round_trips(&setup) {
+        assert_eq!(back.rules, setup.rules);
+    }
+}
+
+#[test]
+fn invalid_reactions_are_rejected() {
+    let mut rules = Rules::new(2);
+    let reaction = Reaction { from: 0, with: 1, range: 1.0, time: 1.0, into: 1 };
+    assert!(rules.set_reactions(vec![Reaction { into: 2, ..reaction }]).is_err());
+    assert!(rules.set_reactions(vec![Reaction { range: 0.0, ..reaction }]).is_err());
+    assert!(rules.set_reactions(vec![reaction; reactions::MAX_REACTIONS + 1]).is_err());
+    assert!(rules.set_reactions(vec![reaction]).is_ok());
+}
@@ -0,0 +1,84 @@
+use rand::{rngs::StdRng, SeedableRng};
+
+use physics::rules::{Generator, Rules, PRESETS};
+
+#[test]
+fn texels_put_each_rule_where_the_shader_looks() {
+    let mut rules = Rules::new(3);
+    rules.set(0, 2, 0.5);
+    rules.set(2, 0, -0.25);
+    let texels = rules.texels(4);
+    // textureLoad(constraints, vec2(i, j)) reads column i of row j
+    assert_eq!(texels[2 * 4][0], 0.5);
+    assert_eq!(texels[2][0], -0.25);
+    assert_eq!(texels[3][0], 0.0);
+}
+
+#[test]
+fn generators_fill_the_matrix_in_range() {
+    for generator in Generator::ALL {
+        for n in 0..7 {
+            let rules = generator.generate(n, &mut StdRng::seed_from_u64(n as u64));
+            assert_eq!(rules.species(), n);
+            for i in 0..n {
+                for j in 0..n {
+                    let v = rules.get(i, j);
+                    assert!((-1.0..=1.0).contains(&v), "{} {}: {} {} = {}", generator.name(), n, i, j, v);
+                }
+            }
+            let again = generator.generate(n, &mut StdRng::seed_from_u64(n as u64));
+            assert_eq!(rules, again, "{} {}", generator.name(), n);
+        }
+    }
+}
+
+#[test]
+fn symmetric_generators_keep_their_symmetry() {
+    let mut rng = StdRng::seed_from_u64(3);
+    for n in 1..7 {
+        let symmetric = Generator::Symmetric.generate(n, &mut rng);
+        let antisymmetric = Generator::Antisymmetric.generate(n, &mut rng);
+        for i in 0..n {
+            for j in 0..n {
+                assert_eq!(symmetric.get(i, j), symmetric.get(j, i));
+                assert_eq!(antisymmetric.get(i, j), -antisymmetric.get(j, i));
+            }
+        }
+    }
+}
+
+#[test]
+fn every_species_chases_the_next_and_flees_the_previous() {
+    let mut rng = StdRng::seed_from_u64(4);
+    for n in 2..7 {
+        let generated = Generator::Chase.generate(n, &mut rng);
+        let preset = PRESETS.iter().find(|p| p.name == "rock-paper-scissors").unwrap().build(n);
+        for rules in [generated, preset] {
+            // two species make a single pair, where 1 is both 0's next and
+            // its previous
+            let pairs = if n == 2 { 1 } else { n };
+            for i in 0..pairs {
+                let next = (i + 1) % n;
+                assert!(rules.get(i, next) > 0.0, "{}: {:?}", n, rules);
+                assert!(rules.get(next, i) < 0.0, "{}: {:?}", n, rules);
+            }
+        }
+    }
+}
+
+#[test]
+fn banded_leaves_distant_pairs_alone() {
+    let rules = Generator::Banded.generate(6, &mut StdRng::seed_from_u64(5));
+    assert_eq!(rules.get(0, 3), 0.0);
+    assert_eq!(rules.get(1, 4), 0.0);
+    assert_ne!(rules.get(0, 5), 0.0);
+}
+
+#[test]
+fn presets_build_any_size() {
+    for preset in PRESETS {
+        for n in 0..7 {
+            assert_eq!(preset.build(n).species(), n, "{}", preset.name);
+        }
+    }
+}
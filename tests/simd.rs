@@ -0,0 +1,58 @@
+use rand::{rngs::StdRng, SeedableRng};
+
+use physics::circle::Circle;
+use physics::params::Params;
+use physics::rules::{Generator, Rules};
+use physics::sim;
+use physics::soa::{self, Particles};
+
+mod common;
+
+use common::lattice;
+
+const TOLERANCE: f32 = 1e-4;
+
+fn run_both(circles: &[Circle], rules: &Rules, params: &Params, steps: usize) {
+    let mut scalar = circles.to_vec();
+    sim::run(&mut scalar, rules, params, sim::DT, steps);
+
+    let mut prev = Particles::from_circles(circles);
+    let mut next = prev.clone();
+    for _ in 0..steps {
+        soa::step(&prev, &mut next, rules, params, sim::DT);
+        std::mem::swap(&mut prev, &mut next);
+    }
+    common::assert_within(TOLERANCE, "simd", &scalar, &prev.to_circles());
+}
+
+#[test]
+fn layout_round_trips() {
+    let circles = Circle::scatter(100, 6, 20.0, 3);
+    let back = Particles::from_circles(&circles).to_circles();
+    assert_eq!(bytemuck::cast_slice::<Circle, u8>(&circles), bytemuck::cast_slice::<Circle, u8>(&back));
+}
+
+#[test]
+fn one_step_matches_scalar() {
+    let params = Params::default();
+    for seed in 0..3 {
+        let rules = Generator::Random.generate(6, &mut StdRng::seed_from_u64(seed));
+        run_both(&lattice(40, 1.3, 6, seed), &rules, &params, 1);
+    }
+}
+
+#[test]
+fn several_steps_match_scalar() {
+    let params = Params::default();
+    let rules = Generator::Chase.generate(4, &mut StdRng::seed_from_u64(9));
+    run_both(&lattice(30, 1.3, 4, 9), &rules, &params, 5);
+}
+
+#[test]
+fn counts_off_the_lane_width_match_scalar() {
+    let params = Params::default();
+    let rules = Generator::Symmetric.generate(3, &mut StdRng::seed_from_u64(5));
+    for side in [1, 3, 5, 7] {
+        run_both(&lattice(side, 1.0, 3, side as u64), &rules, &params, 1);
+    }
+}
@@ -0,0 +1,89 @@
+use rand::{rngs::StdRng, SeedableRng};
+
+use physics::circle::Circle;
+use physics::code::Setup;
+use physics::params::Params;
+use physics::rules::{Generator, Rules};
+use physics::species::Species;
+
+mod common;
+
+use common::backends;
+
+fn speed(c: &Circle) -> f32 {
+    (c.vel[0] * c.vel[0] + c.vel[1] * c.vel[1]).sqrt()
+}
+
+// a light and a heavy particle pushing each other apart from rest
+fn pair() -> Vec<Circle> {
+    vec![
+        Circle { color: 0, rad: 0.125, pos: [0.0, 0.0], vel: [0.0, 0.0] },
+        Circle { color: 1, rad: 0.125, pos: [0.3, 0.0], vel: [0.0, 0.0] },
+    ]
+}
+
+#[test]
+fn heavier_species_accelerate_less() {
+    let mut params = Params::default();
+    params.mu = 0.0;
+    let mut rules = Rules::new(2);
+    rules.set_properties(1, Species { mass: 4.0, ..Species::default() });
+    for (backend, mut sim) in backends(&pair(), &rules, &params) {
+        sim.step(1);
+        let after = sim.download();
+        // equal and opposite forces, a quarter of the kick on the heavy one
+        let (light, heavy) = (after[0].vel[0], after[1].vel[0]);
+        assert!(light < 0.0 && heavy > 0.0, "{}: {:?}", backend.name(), after);
+        assert!((light + 4.0 * heavy).abs() < 1e-4, "{}: {:?}", backend.name(), after);
+    }
+}
+
+#[test]
+fn max_speed_and_radius_are_applied() {
+    let params = Params::default();
+    let mut rules = Generator::Random.generate(2, &mut StdRng::seed_from_u64(3));
+    let slow = Species { max_speed: 0.5, radius: 0.3, ..Species::default() };
+    rules.set_properties(0, slow);
+    let circles = Circle::scatter(200, 2, 4.0, 3);
+    for (backend, mut sim) in backends(&circles, &rules, &params) {
+        sim.step(5);
+        for c in sim.download().iter().filter(|c| c.color == 0) {
+            assert!(speed(c) <= 0.5 + 1e-5, "{}: {:?}", backend.name(), c);
+            assert_eq!(c.rad, 0.3, "{}", backend.name());
+        }
+    }
+}
+
+#[test]
+fn drag_scales_friction() {
+    let params = Params::default();
+    let mut rules = Rules::new(2);
+    rules.set_properties(1, Species { drag: 0.0, ..Species::default() });
+    let circles = vec![
+        Circle { color: 0, rad: 0.125, pos: [0.0, 0.0], vel: [1.0, 0.0] },
+        Circle { color: 1, rad: 0.125, pos: [0.0, 5.0], vel: [1.0, 0.0] },
+    ];
+    for (backend, mut sim) in backends(&circles, &rules, &params) {
+        sim.step(10);
+        let after = sim.download();
+        assert!(after[0].vel[0] < 1.0, "{}: {:?}", backend.name(), after);
+        assert_eq!(after[1].vel[0], 1.0, "{}", backend.name());
+    }
+}
+
+#[test]
+fn properties_survive_codes_and_presets() {
+    let mut rules = Generator::Random.generate(3, &mut StdRng::seed_from_u64(5));
+    rules.set_properties(2, Species { mass: 2.5, drag: 0.5, max_speed: 3.0, radius: 0.2 });
+    rules.set_properties(0, Species { radius: 0.05, ..Species::default() });
+    let setup = Setup { seed: 5, rules, params: Params::default() };
+    for back in common::round_trips(&setup) {
+        assert_eq!(back.rules.all_properties(), setup.rules.all_properties());
+    }
+}
+
+#[test]
+fn bad_properties_are_rejected() {
+    let text = "seed = 1\n\n[params]\n\n[rules]\nmatrix = [[0.0]]\n\n[[rules.species]]\nmass = 0.0\n";
+    assert!(toml::from_str::<Setup>(text).is_err());
+}
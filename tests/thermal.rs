@@ -0,0 +1,107 @@
+use physics::backend::Backend;
+use physics::circle::Circle;
+use physics::code::Setup;
+use physics::friction::Friction;
+use physics::params::Params;
+use physics::rules::Rules;
+use physics::sim::Neighbours;
+use physics::thermal;
+
+mod common;
+
+use common::backends;
+
+// noise only: no pair forces and no friction
+fn params(temperature: f32, thermostat: bool) -> Params {
+    let mut params = Params::default();
+    params.set_friction(Friction::None);
+    params.temperature = temperature;
+    params.set_thermostat(thermostat);
+    params
+}
+
+// a gas inside the world, spaced out past the repulsive core so with zero
+// rules only the noise and the thermostat act at first
+fn gas(count: usize) -> Vec<Circle> {
+    (0..count)
+        .map(|i| Circle {
+            color: 0,
+            rad: 0.125,
+            pos: [(i % 16) as f32 * 2.2 - 17.0, (i / 16) as f32 * 2.2 - 17.0],
+            vel: [0.0, 0.0],
+        })
+        .collect()
+}
+
+fn kinetic(circles: &[Circle]) -> f32 {
+    thermal::kinetic(circles.iter().map(|c| (c.vel, 1.0)))
+}
+
+#[test]
+fn noise_is_normal() {
+    let n = 20000;
+    let draws: Vec<f32> = (0..n).flat_map(|i| thermal::gaussian(7, 1, i)).collect();
+    let mean = draws.iter().sum::<f32>() / draws.len() as f32;
+    let var = draws.iter().map(|x| (x - mean) * (x - mean)).sum::<f32>() / draws.len() as f32;
+    assert!(mean.abs() < 0.02 && (var - 1.0).abs() < 0.03, "mean {} variance {}", mean, var);
+    assert!(draws.iter().all(|x| x.is_finite()));
+}
+
+#[test]
+fn noise_follows_the_seed() {
+    let params = params(0.5, false);
+    let run = |seed| {
+        let mut sim = Backend::Cpu.create(&gas(64), &Rules::new(1), &params, Neighbours::All).unwrap();
+        sim.set_seed(seed);
+        sim.step(5);
+        sim.download()
+    };
+    let (a, b, c) = (run(1), run(1), run(2));
+    assert!(a.iter().zip(&b).all(|(a, b)| a.vel == b.vel));
+    assert!(a.iter().zip(&c).any(|(a, c)| a.vel != c.vel));
+}
+
+#[test]
+fn backends_draw_the_same_noise() {
+    let params = params(0.5, true);
+    common::assert_backends_agree(&gas(200), &Rules::new(1), &params, 11, 10);
+}
+
+#[test]
+fn thermostat_holds_the_temperature() {
+    for target in [0.2, 1.0] {
+        for (backend, mut sim) in backends(&gas(256), &Rules::new(1), &params(target, true)) {
+            sim.set_seed(3);
+            // from rest, about ten relaxation times to settle
+            sim.step(200);
+            let mut mean = 0.0;
+            for _ in 0..20 {
+                sim.step(5);
+                mean += kinetic(&sim.download()) / 20.0;
+            }
+            assert!((mean - target).abs() < 0.15 * target, "{}: {} instead of {}", backend.name(), mean, target);
+        }
+    }
+}
+
+#[test]
+fn thermostat_alone_cools_to_zero() {
+    let mut circles = gas(64);
+    for c in &mut circles {
+        c.vel = [1.0, -1.0];
+    }
+    for (backend, mut sim) in backends(&circles, &Rules::new(1), &params(0.0, true)) {
+        sim.set_seed(3);
+        sim.step(400);
+        let k = kinetic(&sim.download());
+        assert!(k < 1e-3, "{}: {}", backend.name(), k);
+    }
+}
+
+#[test]
+fn temperature_survives_codes_and_presets() {
+    let setup = Setup { seed: 1, rules: Rules::new(2), params: params(0.25, true) };
+    for back in common::round_trips(&setup) {
+        assert_eq!((back.params.temperature, back.params.thermostat()), (0.25, true));
+    }
+}
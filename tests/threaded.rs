@@ -0,0 +1,42 @@
+use rand::{rngs::StdRng, SeedableRng};
+
+use physics::backend::Backend;
+use physics::circle::Circle;
+use physics::params::Params;
+use physics::rules::{Generator, Rules};
+use physics::sim::Neighbours;
+
+// every particle reads the previous state and writes only its own slot, so
+// the threads can't change the result in any bit
+fn assert_threaded_matches_serial(circles: &[Circle], rules: &Rules, params: &Params, neighbours: Neighbours, steps: usize) {
+    let mut serial = Backend::Cpu.create(circles, rules, params, neighbours).unwrap();
+    let mut threaded = Backend::CpuThreaded.create(circles, rules, params, neighbours).unwrap();
+    for _ in 0..steps {
+        serial.step(1);
+        threaded.step(1);
+        let (a, b) = (serial.download(), threaded.download());
+        assert_eq!(a.len(), b.len());
+        for (i, (a, b)) in a.iter().zip(&b).enumerate() {
+            assert_eq!((a.pos, a.vel), (b.pos, b.vel), "{} particle {}", neighbours.name(), i);
+        }
+    }
+}
+
+#[test]
+fn threaded_cpu_matches_serial_exactly() {
+    let params = Params::default();
+    let rules = Generator::Random.generate(4, &mut StdRng::seed_from_u64(4));
+    // dense enough that particles overlap and push each other
+    let circles = Circle::scatter(400, 4, 10.0, 4);
+    for neighbours in Neighbours::ALL {
+        assert_threaded_matches_serial(&circles, &rules, &params, neighbours, 10);
+    }
+}
+
+#[test]
+fn threaded_cpu_matches_serial_on_large_systems() {
+    let params = Params::default();
+    let rules = Generator::Chase.generate(6, &mut StdRng::seed_from_u64(5));
+    let circles = Circle::scatter(20_000, 6, 120.0, 5);
+    assert_threaded_matches_serial(&circles, &rules, &params, Neighbours::Grid, 2);
+}
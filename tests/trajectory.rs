@@ -0,0 +1,79 @@
+use physics::circle::Circle;
+use physics::trajectory::{self, Header, Recorder, Replay};
+
+fn temp(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("physics-trajectory-{}-{}", std::process::id(), name))
+}
+
+fn header() -> Header {
+    Header { every: 1, world_size: 10.0, code: "code".to_string() }
+}
+
+// frames of a few particles at uneven times, as the viewer's shortened
+// steps give
+fn record(path: &std::path::Path, frames: usize) {
+    let mut recorder = Recorder::create(path, &header()).unwrap();
+    let mut time = 0.0;
+    for step in 0..frames as u64 {
+        let circles: Vec<Circle> = (0..50)
+            .map(|i| Circle { color: i % 3, rad: 0.125, pos: [i as f32 * 0.1, step as f32], vel: [1.0, -1.0] })
+            .collect();
+        recorder.record(step, time, &circles).unwrap();
+        time += if step % 2 == 0 { 0.005 } else { 0.001 };
+    }
+    recorder.finish().unwrap();
+}
+
+#[test]
+fn recordings_keep_each_frames_time() {
+    let path = temp("times");
+    record(&path, 4);
+    let (header, frames) = trajectory::load(&path).unwrap();
+    assert_eq!((header.every, header.world_size, header.code.as_str()), (1, 10.0, "code"));
+    let times: Vec<f64> = frames.iter().map(|f| f.time).collect();
+    assert_eq!(times, [0.0, 0.005, 0.006, 0.011]);
+    // positions are quantised over twice the world size
+    let pos = frames[3].circles[7].pos;
+    assert!((pos[0] - 0.7).abs() < 1e-3 && (pos[1] - 3.0).abs() < 1e-3, "{:?}", pos);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn replay_advances_by_simulated_time() {
+    let path = temp("replay");
+    record(&path, 4);
+    let (header, frames) = trajectory::load(&path).unwrap();
+    let mut replay = Replay::new(header, frames);
+    assert!(replay.advance(0.004));
+    assert_eq!(replay.index(), 0);
+    assert!(replay.advance(0.0015));
+    assert_eq!(replay.index(), 1);
+    assert!(replay.advance(0.001));
+    assert_eq!(replay.index(), 2);
+    assert!(!replay.advance(1.0));
+    assert_eq!(replay.index(), 3);
+    replay.reverse();
+    replay.step(-2);
+    assert_eq!(replay.current().step, 1);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn truncated_recordings_give_their_complete_frames() {
+    let path = temp("truncated");
+    record(&path, 40);
+    let bytes = std::fs::read(&path).unwrap();
+    let (_, all) = trajectory::load(&path).unwrap();
+    assert_eq!(all.len(), 40);
+    // without the gzip trailer, and cut off part way through the frames
+    for keep in [bytes.len() - 8, bytes.len() * 2 / 3, bytes.len() / 3] {
+        std::fs::write(&path, &bytes[..keep]).unwrap();
+        let (_, frames) = trajectory::load(&path).unwrap();
+        assert!(!frames.is_empty(), "{} of {} bytes", keep, bytes.len());
+        assert!(frames.len() < all.len() || keep == bytes.len() - 8, "{} of {} bytes", keep, bytes.len());
+        for (a, b) in frames.iter().zip(&all) {
+            assert_eq!((a.step, a.time, a.circles.len()), (b.step, b.time, b.circles.len()));
+        }
+    }
+    std::fs::remove_file(path).unwrap();
+}